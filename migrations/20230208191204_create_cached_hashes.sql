@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS cached_hashes (
+    id INTEGER NOT NULL PRIMARY KEY,
+    path VARCHAR NOT NULL,
+    entry VARCHAR NOT NULL,
+    file_size BIGINT NOT NULL,
+    file_mtime BIGINT NOT NULL,
+    header_id INTEGER,
+    size BIGINT NOT NULL,
+    crc VARCHAR,
+    md5 VARCHAR,
+    sha1 VARCHAR,
+    UNIQUE(path, entry)
+);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT
+);
+CREATE TABLE IF NOT EXISTS operations (
+    id INTEGER NOT NULL PRIMARY KEY,
+    run_id INTEGER NOT NULL,
+    operation INTEGER NOT NULL,
+    old_path VARCHAR,
+    new_path VARCHAR,
+    romfile_id INTEGER,
+    FOREIGN KEY (run_id) REFERENCES runs(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS operations_run_id ON operations (run_id);
@@ -0,0 +1,6 @@
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('CHD_VERIFY', 'true');
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('CSO_VERIFY', 'true');
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('RVZ_VERIFY', 'true');
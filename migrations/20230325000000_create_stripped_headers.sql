@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS stripped_headers (
+    rom_id INTEGER NOT NULL PRIMARY KEY,
+    data BLOB NOT NULL,
+    FOREIGN KEY (rom_id) REFERENCES roms(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE systems
+ADD COLUMN preferred_format VARCHAR;
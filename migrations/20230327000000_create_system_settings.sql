@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS system_settings (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL,
+    key VARCHAR NOT NULL,
+    value VARCHAR,
+    FOREIGN KEY (system_id) REFERENCES systems(id) ON DELETE CASCADE,
+    UNIQUE (system_id, key)
+);
@@ -0,0 +1,12 @@
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('SERVER_PASSWORD', NULL);
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('SERVER_READ_ONLY_PASSWORD', NULL);
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('SERVER_READ_ONLY_TOKEN', NULL);
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('SERVER_READ_ONLY_USERNAME', NULL);
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('SERVER_TOKEN', NULL);
+INSERT OR REPLACE INTO settings ("key", value)
+VALUES('SERVER_USERNAME', NULL);
@@ -1,5 +1,4 @@
 use super::checksum::*;
-use super::config::HashAlgorithm;
 use super::database::*;
 use super::progress::*;
 use super::util::*;
@@ -9,7 +8,6 @@ use async_std::io;
 use async_std::io::{ReadExt, WriteExt};
 use async_std::path::Path;
 use clap::{Arg, ArgMatches, Command};
-use indicatif::ProgressBar;
 use sqlx::sqlite::SqliteConnection;
 use std::time::Duration;
 use std::time::Instant;
@@ -29,7 +27,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
@@ -145,60 +143,13 @@ pub async fn main(
         mb_count as f64 / duration.as_secs_f64()
     ));
 
-    // crc speed
+    // multi-digest speed
     let start = Instant::now();
-    get_size_and_hash(
-        connection,
-        progress_bar,
-        &tmp_file_path,
-        &None,
-        1,
-        1,
-        &HashAlgorithm::Crc,
-    )
-    .await?;
-    let duration = start.elapsed();
-
-    progress_bar.println(format!(
-        "CRC Speed: {:.2}Mb/s",
-        mb_count as f64 / duration.as_secs_f64()
-    ));
-
-    // md5 speed
-    let start = Instant::now();
-    get_size_and_hash(
-        connection,
-        progress_bar,
-        &tmp_file_path,
-        &None,
-        1,
-        1,
-        &HashAlgorithm::Md5,
-    )
-    .await?;
-    let duration = start.elapsed();
-
-    progress_bar.println(format!(
-        "MD5 Speed: {:.2}Mb/s",
-        mb_count as f64 / duration.as_secs_f64()
-    ));
-
-    // sha1 speed
-    let start = Instant::now();
-    get_size_and_hash(
-        connection,
-        progress_bar,
-        &tmp_file_path,
-        &None,
-        1,
-        1,
-        &HashAlgorithm::Sha1,
-    )
-    .await?;
+    get_size_and_hashes(connection, progress_bar, &tmp_file_path, &None, 1, 1).await?;
     let duration = start.elapsed();
 
     progress_bar.println(format!(
-        "SHA1 Speed: {:.2}Mb/s",
+        "CRC+MD5+SHA1 Speed: {:.2}Mb/s",
         mb_count as f64 / duration.as_secs_f64()
     ));
 
@@ -1,18 +1,432 @@
+use super::checksum::*;
 use super::config::*;
+use super::model::*;
 use super::progress::*;
 use super::util::*;
 use super::SimpleResult;
 use async_std::io;
 use async_std::path::{Path, PathBuf};
 use async_std::prelude::*;
-use indicatif::ProgressBar;
+use chd::metadata::{KnownMetadata, MetadataRef, MetadataTag};
+use chd::Chd;
+use phf::phf_map;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
 use std::process::Command;
 use std::time::Duration;
 
+const CHD_MAGIC: &[u8] = b"MComprHD";
+const CD_FRAME_SIZE: u64 = 2448;
+const CD_TRACK_PADDING: u64 = 4;
+// 80 minutes of MODE1 sectors
+const CD_MAX_SIZE: u64 = 360_000 * 2048;
+const DVD_METADATA_TAG: u32 = u32::from_be_bytes(*b"DVD ");
+const RAW_HUNK_SIZE: &str = "4096";
+const RAW_UNIT_SIZE: &str = "512";
+
+// systems whose discs are all of the same type, the others are guessed from the size
+static SYSTEMS_CHD_TYPES: phf::Map<&str, ChdType> = phf_map! {
+    "Atari - Jaguar CD Interactive Multimedia System" => ChdType::Cd,
+    "Commodore - Amiga CD32" => ChdType::Cd,
+    "Microsoft - Xbox" => ChdType::Dvd,
+    "Microsoft - Xbox 360" => ChdType::Dvd,
+    "NEC - PC Engine CD & TurboGrafx CD" => ChdType::Cd,
+    "NEC - PC-FX & PC-FXGA" => ChdType::Cd,
+    "Panasonic - 3DO Interactive Multiplayer" => ChdType::Cd,
+    "Philips - CD-i" => ChdType::Cd,
+    "Sega - Dreamcast" => ChdType::Cd,
+    "Sega - Mega CD & Sega CD" => ChdType::Cd,
+    "Sega - Saturn" => ChdType::Cd,
+    "SNK - Neo Geo CD" => ChdType::Cd,
+    "Sony - PlayStation" => ChdType::Cd,
+};
+
+type ChdFile = Chd<std::io::BufReader<fs::File>>;
+
+struct CdTrack {
+    start_frame: u64,
+    frames: u64,
+    sector_size: usize,
+    audio: bool,
+}
+
+// streams the sectors of one or more tracks, without the subcode and in BIN byte order
+struct CdReader<'a> {
+    chd: &'a mut ChdFile,
+    tracks: &'a [CdTrack],
+    compressed_buffer: Vec<u8>,
+    hunk_buffer: Vec<u8>,
+    hunk: Option<u32>,
+    track: usize,
+    frame: u64,
+    sector: Vec<u8>,
+    sector_position: usize,
+}
+
+impl<'a> CdReader<'a> {
+    fn new(chd: &'a mut ChdFile, tracks: &'a [CdTrack]) -> Self {
+        let hunk_buffer = chd.get_hunksized_buffer();
+        Self {
+            chd,
+            tracks,
+            compressed_buffer: Vec::new(),
+            hunk_buffer,
+            hunk: None,
+            track: 0,
+            frame: 0,
+            sector: Vec::new(),
+            sector_position: 0,
+        }
+    }
+
+    fn read_sector(&mut self) -> std::io::Result<()> {
+        let track = &self.tracks[self.track];
+        let hunk_size = self.chd.header().hunk_size() as u64;
+        let offset = (track.start_frame + self.frame) * CD_FRAME_SIZE;
+        let hunk = (offset / hunk_size) as u32;
+        if self.hunk != Some(hunk) {
+            self.chd
+                .hunk(hunk)
+                .and_then(|mut hunk| {
+                    hunk.read_hunk_in(&mut self.compressed_buffer, &mut self.hunk_buffer)
+                })
+                .map_err(|error| std::io::Error::other(error.to_string()))?;
+            self.hunk = Some(hunk);
+        }
+        let start = (offset % hunk_size) as usize;
+        self.sector.clear();
+        self.sector
+            .extend_from_slice(&self.hunk_buffer[start..start + track.sector_size]);
+        // audio is stored big-endian
+        if track.audio {
+            self.sector
+                .chunks_exact_mut(2)
+                .for_each(|sample| sample.swap(0, 1));
+        }
+        self.frame += 1;
+        self.sector_position = 0;
+        Ok(())
+    }
+}
+
+impl<'a> Read for CdReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.sector_position == self.sector.len() {
+            while self.track < self.tracks.len() && self.frame == self.tracks[self.track].frames {
+                self.track += 1;
+                self.frame = 0;
+            }
+            if self.track == self.tracks.len() {
+                return Ok(0);
+            }
+            self.read_sector()?;
+        }
+        let size = buf.len().min(self.sector.len() - self.sector_position);
+        buf[..size]
+            .copy_from_slice(&self.sector[self.sector_position..self.sector_position + size]);
+        self.sector_position += size;
+        Ok(size)
+    }
+}
+
+// streams the logical data of DVD, hard disk and raw CHDs
+struct HunkReader<'a> {
+    chd: &'a mut ChdFile,
+    compressed_buffer: Vec<u8>,
+    hunk_buffer: Vec<u8>,
+    hunk: u32,
+    hunk_position: usize,
+    remaining: u64,
+}
+
+impl<'a> HunkReader<'a> {
+    fn new(chd: &'a mut ChdFile) -> Self {
+        let hunk_buffer = chd.get_hunksized_buffer();
+        let hunk_position = hunk_buffer.len();
+        let remaining = chd.header().logical_bytes();
+        Self {
+            chd,
+            compressed_buffer: Vec::new(),
+            hunk_buffer,
+            hunk: 0,
+            hunk_position,
+            remaining,
+        }
+    }
+}
+
+impl<'a> Read for HunkReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        if self.hunk_position == self.hunk_buffer.len() {
+            self.chd
+                .hunk(self.hunk)
+                .and_then(|mut hunk| {
+                    hunk.read_hunk_in(&mut self.compressed_buffer, &mut self.hunk_buffer)
+                })
+                .map_err(|error| std::io::Error::other(error.to_string()))?;
+            self.hunk += 1;
+            self.hunk_position = 0;
+        }
+        let size = buf
+            .len()
+            .min(self.hunk_buffer.len() - self.hunk_position)
+            .min(self.remaining as usize);
+        buf[..size]
+            .copy_from_slice(&self.hunk_buffer[self.hunk_position..self.hunk_position + size]);
+        self.hunk_position += size;
+        self.remaining -= size as u64;
+        Ok(size)
+    }
+}
+
+impl ChdType {
+    fn create_command(&self) -> &str {
+        match self {
+            ChdType::Auto | ChdType::Cd => "createcd",
+            ChdType::Dvd => "createdvd",
+            ChdType::Hd => "createhd",
+            ChdType::Raw => "createraw",
+        }
+    }
+
+    fn extract_command(&self) -> &str {
+        match self {
+            ChdType::Auto | ChdType::Cd => "extractcd",
+            ChdType::Dvd => "extractdvd",
+            ChdType::Hd => "extracthd",
+            ChdType::Raw => "extractraw",
+        }
+    }
+}
+
+pub fn get_chd_sha1<P: AsRef<Path>>(chd_path: &P) -> SimpleResult<String> {
+    let mut header = [0u8; 124];
+    try_with!(
+        open_file_sync(chd_path)?.read_exact(&mut header),
+        "Failed to read CHD header"
+    );
+    if &header[..8] != CHD_MAGIC {
+        bail!("Not a CHD file");
+    }
+    // the combined SHA1 (raw data and metadata) is what MAME lists for disks
+    let offset = match u32::from_be_bytes(header[12..16].try_into().unwrap()) {
+        3 => 80,
+        4 => 48,
+        5 => 84,
+        version => bail!("Unsupported CHD version {}", version),
+    };
+    Ok(header[offset..offset + 20]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+pub fn get_chd_type<P: AsRef<Path>>(chd_path: &P) -> SimpleResult<ChdType> {
+    let mut chd = open_chd(chd_path)?;
+    Ok(detect_chd_type(&mut chd))
+}
+
+// hash every track separately, in the order they appear on the disc
+pub fn get_chd_tracks_size_and_hashes<P: AsRef<Path>>(
+    progress_bar: &Progress,
+    chd_path: &P,
+) -> SimpleResult<Vec<(u64, Hashes)>> {
+    let mut chd = open_chd(chd_path)?;
+    if detect_chd_type(&mut chd) != ChdType::Cd {
+        let size = chd.header().logical_bytes();
+        let mut reader = HunkReader::new(&mut chd);
+        return Ok(vec![compute_reader_size_and_hashes(
+            progress_bar,
+            &mut reader,
+            size,
+            1,
+            1,
+        )?]);
+    }
+    let tracks = parse_cd_tracks(&mut chd)?;
+    let mut sizes_and_hashes: Vec<(u64, Hashes)> = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let mut reader = CdReader::new(&mut chd, std::slice::from_ref(track));
+        sizes_and_hashes.push(compute_reader_size_and_hashes(
+            progress_bar,
+            &mut reader,
+            track.frames * track.sector_size as u64,
+            i + 1,
+            tracks.len(),
+        )?);
+    }
+    Ok(sizes_and_hashes)
+}
+
+// hash all tracks as a single BIN
+pub fn get_chd_size_and_hashes<P: AsRef<Path>>(
+    progress_bar: &Progress,
+    chd_path: &P,
+) -> SimpleResult<(u64, Hashes)> {
+    let mut chd = open_chd(chd_path)?;
+    if detect_chd_type(&mut chd) != ChdType::Cd {
+        let size = chd.header().logical_bytes();
+        let mut reader = HunkReader::new(&mut chd);
+        return compute_reader_size_and_hashes(progress_bar, &mut reader, size, 1, 1);
+    }
+    let tracks = parse_cd_tracks(&mut chd)?;
+    let size = tracks
+        .iter()
+        .map(|track| track.frames * track.sector_size as u64)
+        .sum();
+    let mut reader = CdReader::new(&mut chd, &tracks);
+    compute_reader_size_and_hashes(progress_bar, &mut reader, size, 1, 1)
+}
+
+fn open_chd<P: AsRef<Path>>(chd_path: &P) -> SimpleResult<ChdFile> {
+    let chd = try_with!(
+        Chd::open(get_reader_sync(chd_path)?, None),
+        "Failed to open {:?}",
+        chd_path.as_ref()
+    );
+    Ok(chd)
+}
+
+fn detect_chd_type(chd: &mut ChdFile) -> ChdType {
+    let metatags: Vec<u32> = chd
+        .metadata_refs()
+        .map(|metadata_ref| metadata_ref.metatag())
+        .collect();
+    if metatags.iter().any(|metatag| {
+        [
+            KnownMetadata::CdRomTrack as u32,
+            KnownMetadata::CdRomTrack2 as u32,
+            KnownMetadata::GdRomTrack as u32,
+        ]
+        .contains(metatag)
+    }) {
+        ChdType::Cd
+    } else if metatags.contains(&DVD_METADATA_TAG) {
+        ChdType::Dvd
+    } else if metatags.contains(&(KnownMetadata::HardDisk as u32)) {
+        ChdType::Hd
+    } else {
+        ChdType::Raw
+    }
+}
+
+// the system only decides when no type was set for it
+pub fn get_system_chd_type(system: &System, chd_type: &ChdType) -> ChdType {
+    match chd_type {
+        ChdType::Auto => SYSTEMS_CHD_TYPES
+            .get(system.name.as_str())
+            .copied()
+            .unwrap_or(ChdType::Auto),
+        chd_type => *chd_type,
+    }
+}
+
+// CUE/BINs are always CDs, ISOs are DVDs when they don't fit on a CD
+fn resolve_chd_type<P: AsRef<Path>>(romfile_path: &P, chd_type: &ChdType) -> ChdType {
+    let is_cue = romfile_path
+        .as_ref()
+        .extension()
+        .map(|extension| extension == CUE_EXTENSION)
+        .unwrap_or_default();
+    match chd_type {
+        _ if is_cue => ChdType::Cd,
+        ChdType::Auto => {
+            let size = fs::metadata(romfile_path.as_ref())
+                .map(|metadata| metadata.len())
+                .unwrap_or_default();
+            match size > CD_MAX_SIZE {
+                true => ChdType::Dvd,
+                false => ChdType::Cd,
+            }
+        }
+        chd_type => *chd_type,
+    }
+}
+
+fn parse_cd_tracks(chd: &mut ChdFile) -> SimpleResult<Vec<CdTrack>> {
+    let metadata_refs: Vec<MetadataRef> = chd
+        .metadata_refs()
+        .filter(|metadata_ref| {
+            [
+                KnownMetadata::CdRomTrack as u32,
+                KnownMetadata::CdRomTrack2 as u32,
+                KnownMetadata::GdRomTrack as u32,
+            ]
+            .contains(&metadata_ref.metatag())
+        })
+        .collect();
+    if metadata_refs.is_empty() {
+        bail!("No CD track metadata found");
+    }
+
+    let mut numbers_tracks_paddings: Vec<(u32, CdTrack, u64)> = Vec::new();
+    for metadata_ref in metadata_refs {
+        let metadata = try_with!(
+            metadata_ref.read(chd.inner()),
+            "Failed to read CHD metadata"
+        );
+        // eg: TRACK:1 TYPE:MODE2_RAW SUBTYPE:NONE FRAMES:1234 PREGAP:0 PGTYPE:MODE1 PGSUB:RW POSTGAP:0
+        let value = String::from_utf8_lossy(&metadata.value);
+        let fields: HashMap<&str, &str> = value
+            .trim_end_matches('\0')
+            .split_whitespace()
+            .filter_map(|field| field.split_once(':'))
+            .collect();
+        let number: u32 = try_with!(
+            fields.get("TRACK").unwrap_or(&"").parse(),
+            "Failed to parse track number"
+        );
+        let frames: u64 = try_with!(
+            fields.get("FRAMES").unwrap_or(&"").parse(),
+            "Failed to parse track frames"
+        );
+        let track_type = fields.get("TYPE").copied().unwrap_or_default();
+        let sector_size = match track_type {
+            "MODE1" | "MODE2_FORM1" => 2048,
+            "MODE2_FORM2" => 2324,
+            "MODE2" | "MODE2_FORM_MIX" => 2336,
+            "MODE1_RAW" | "MODE2_RAW" | "AUDIO" => 2352,
+            _ => bail!("Unsupported track type {}", track_type),
+        };
+        // GD-ROMs declare their padding, CD tracks are padded to a multiple of 4 frames
+        let padding = match fields.get("PAD") {
+            Some(pad) => try_with!(pad.parse(), "Failed to parse track padding"),
+            None => (CD_TRACK_PADDING - frames % CD_TRACK_PADDING) % CD_TRACK_PADDING,
+        };
+        numbers_tracks_paddings.push((
+            number,
+            CdTrack {
+                start_frame: 0,
+                frames,
+                sector_size,
+                audio: track_type == "AUDIO",
+            },
+            padding,
+        ));
+    }
+
+    // tracks are laid out one after the other
+    numbers_tracks_paddings.sort_by_key(|(number, _, _)| *number);
+    let mut tracks: Vec<CdTrack> = Vec::new();
+    let mut start_frame = 0;
+    for (_, mut track, padding) in numbers_tracks_paddings {
+        track.start_frame = start_frame;
+        start_frame += track.frames + padding;
+        tracks.push(track);
+    }
+
+    Ok(tracks)
+}
+
 pub fn create_chd<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     romfile_path: &P,
     directory: &Q,
+    chd_type: &ChdType,
 ) -> SimpleResult<PathBuf> {
     progress_bar.set_message("Creating CHD");
     progress_bar.set_style(get_none_progress_style());
@@ -23,16 +437,32 @@ pub fn create_chd<P: AsRef<Path>, Q: AsRef<Path>>(
         .join(romfile_path.as_ref().file_name().unwrap());
     chd_path.set_extension(CHD_EXTENSION);
 
+    if is_dry_run() {
+        progress_bar.println(format!("Would create {:?}", chd_path.as_os_str()));
+        progress_bar.set_message("");
+        progress_bar.disable_steady_tick();
+        return Ok(chd_path);
+    }
+
     progress_bar.println(format!("Creating {:?}", chd_path.file_name().unwrap()));
 
-    let output = Command::new("chdman")
-        .arg("createcd")
+    let chd_type = resolve_chd_type(romfile_path, chd_type);
+    let mut command = Command::new("chdman");
+    command
+        .arg(chd_type.create_command())
         .arg("-i")
         .arg(romfile_path.as_ref())
         .arg("-o")
-        .arg(&chd_path)
-        .output()
-        .expect("Failed to create CHD");
+        .arg(&chd_path);
+    // raw CHDs can't guess their geometry
+    if chd_type == ChdType::Raw {
+        command
+            .arg("-hs")
+            .arg(RAW_HUNK_SIZE)
+            .arg("-us")
+            .arg(RAW_UNIT_SIZE);
+    }
+    let output = command.output().expect("Failed to create CHD");
 
     if !output.status.success() {
         bail!(String::from_utf8(output.stderr).unwrap().as_str())
@@ -45,12 +475,24 @@ pub fn create_chd<P: AsRef<Path>, Q: AsRef<Path>>(
 }
 
 pub async fn extract_chd_to_multiple_tracks<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     chd_path: &P,
     directory: &Q,
     bin_names_sizes: &[(&str, u64)],
     quiet: bool,
 ) -> SimpleResult<Vec<PathBuf>> {
+    if is_dry_run() {
+        progress_bar.println(format!(
+            "Would extract {:?} to {:?}",
+            chd_path.as_ref().as_os_str(),
+            directory.as_ref().as_os_str()
+        ));
+        return Ok(bin_names_sizes
+            .iter()
+            .map(|(bin_name, _)| directory.as_ref().join(bin_name))
+            .collect());
+    }
+
     progress_bar.set_message("Extracting CHD");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
@@ -60,6 +502,18 @@ pub async fn extract_chd_to_multiple_tracks<P: AsRef<Path>, Q: AsRef<Path>>(
         chd_path.as_ref().file_name().unwrap()
     ));
 
+    let chd_type = get_chd_type(chd_path)?;
+    if chd_type != ChdType::Cd {
+        if bin_names_sizes.len() != 1 {
+            bail!("Only CD CHDs can contain multiple tracks");
+        }
+        let data_path = directory.as_ref().join(bin_names_sizes.first().unwrap().0);
+        extract_chd_data(chd_path, &data_path, &chd_type)?;
+        progress_bar.set_message("");
+        progress_bar.disable_steady_tick();
+        return Ok(vec![data_path]);
+    }
+
     let cue_path = directory.as_ref().join(format!(
         "{}.{}",
         chd_path.as_ref().file_name().unwrap().to_str().unwrap(),
@@ -71,7 +525,7 @@ pub async fn extract_chd_to_multiple_tracks<P: AsRef<Path>, Q: AsRef<Path>>(
     bin_path.set_extension(BIN_EXTENSION);
 
     let output = Command::new("chdman")
-        .arg("extractcd")
+        .arg(ChdType::Cd.extract_command())
         .arg("-i")
         .arg(chd_path.as_ref())
         .arg("-o")
@@ -122,26 +576,47 @@ pub async fn extract_chd_to_multiple_tracks<P: AsRef<Path>, Q: AsRef<Path>>(
 }
 
 pub async fn extract_chd_to_single_track<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     chd_path: &P,
     directory: &Q,
 ) -> SimpleResult<PathBuf> {
+    let mut bin_path = directory
+        .as_ref()
+        .join(chd_path.as_ref().file_name().unwrap());
+    bin_path.set_extension(BIN_EXTENSION);
+
+    if is_dry_run() {
+        progress_bar.println(format!(
+            "Would extract {:?} to {:?}",
+            chd_path.as_ref().as_os_str(),
+            directory.as_ref().as_os_str()
+        ));
+        return Ok(bin_path);
+    }
+
     progress_bar.set_message("Extracting CHD");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
 
+    let chd_type = get_chd_type(chd_path)?;
+    if chd_type != ChdType::Cd {
+        if chd_type == ChdType::Dvd {
+            bin_path.set_extension(ISO_EXTENSION);
+        }
+        extract_chd_data(chd_path, &bin_path, &chd_type)?;
+        progress_bar.set_message("");
+        progress_bar.disable_steady_tick();
+        return Ok(bin_path);
+    }
+
     let cue_path = directory.as_ref().join(format!(
         "{}.{}",
         chd_path.as_ref().file_name().unwrap().to_str().unwrap(),
         CUE_EXTENSION
     ));
-    let mut bin_path = directory
-        .as_ref()
-        .join(chd_path.as_ref().file_name().unwrap());
-    bin_path.set_extension(BIN_EXTENSION);
 
     let output = Command::new("chdman")
-        .arg("extractcd")
+        .arg(ChdType::Cd.extract_command())
         .arg("-i")
         .arg(chd_path.as_ref())
         .arg("-o")
@@ -162,3 +637,34 @@ pub async fn extract_chd_to_single_track<P: AsRef<Path>, Q: AsRef<Path>>(
 
     Ok(bin_path)
 }
+
+// DVD, hard disk and raw CHDs hold a single image
+fn extract_chd_data<P: AsRef<Path>, Q: AsRef<Path>>(
+    chd_path: &P,
+    data_path: &Q,
+    chd_type: &ChdType,
+) -> SimpleResult<()> {
+    let output = Command::new("chdman")
+        .arg(chd_type.extract_command())
+        .arg("-i")
+        .arg(chd_path.as_ref())
+        .arg("-o")
+        .arg(data_path.as_ref())
+        .output()
+        .expect("Failed to spawn chdman process");
+
+    if !output.status.success() {
+        bail!(String::from_utf8(output.stderr).unwrap().as_str());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_multiple_tracks_hashes;
+#[cfg(test)]
+mod test_resolve_chd_type;
+#[cfg(test)]
+mod test_single_track_hashes;
+#[cfg(test)]
+mod test_system_chd_type;
@@ -0,0 +1,44 @@
+use super::*;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+    let cd_path = test_directory.join("Test Game (World) (Multiple Tracks).chd");
+    let gd_path = test_directory.join("Test Game (World) (GD-ROM).chd");
+
+    // when
+    let (size, hashes) = get_chd_size_and_hashes(&progress_bar, &cd_path).unwrap();
+    let cd_sizes_and_hashes = get_chd_tracks_size_and_hashes(&progress_bar, &cd_path).unwrap();
+    let gd_sizes_and_hashes = get_chd_tracks_size_and_hashes(&progress_bar, &gd_path).unwrap();
+
+    // then
+    assert_eq!(size, 14112 + 11760);
+    assert_eq!(
+        hashes.sha1.as_deref(),
+        Some("392e7b4fa163222160a69bf9e700ac357ead45db")
+    );
+
+    // the audio track is byte-swapped and the data track starts after the padding of the first one
+    assert_eq!(cd_sizes_and_hashes.len(), 2);
+    assert_eq!(cd_sizes_and_hashes[0].0, 14112);
+    assert_eq!(
+        cd_sizes_and_hashes[0].1.sha1.as_deref(),
+        Some("0619bf266ae9f6099b9ef7dc359d8c625e8eece4")
+    );
+    assert_eq!(cd_sizes_and_hashes[1].0, 11760);
+    assert_eq!(
+        cd_sizes_and_hashes[1].1.sha1.as_deref(),
+        Some("2b7d4ee3c2f5fe686fa209d55409001a1cd21701")
+    );
+
+    // GD-ROMs declare their own padding
+    assert_eq!(gd_sizes_and_hashes.len(), 2);
+    for ((cd_size, cd_hashes), (gd_size, gd_hashes)) in
+        cd_sizes_and_hashes.iter().zip(&gd_sizes_and_hashes)
+    {
+        assert_eq!(cd_size, gd_size);
+        assert_eq!(cd_hashes.sha1, gd_hashes.sha1);
+    }
+}
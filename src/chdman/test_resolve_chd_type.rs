@@ -0,0 +1,21 @@
+use super::*;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let cue_path = test_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue");
+    let iso_path = test_directory.join("Test Game (USA, Europe).iso");
+
+    // when
+    let cue_auto_type = resolve_chd_type(&cue_path, &ChdType::Auto);
+    let cue_dvd_type = resolve_chd_type(&cue_path, &ChdType::Dvd);
+    let iso_auto_type = resolve_chd_type(&iso_path, &ChdType::Auto);
+    let iso_dvd_type = resolve_chd_type(&iso_path, &ChdType::Dvd);
+
+    // then
+    assert!(cue_auto_type == ChdType::Cd);
+    assert!(cue_dvd_type == ChdType::Cd);
+    assert!(iso_auto_type == ChdType::Cd);
+    assert!(iso_dvd_type == ChdType::Dvd);
+}
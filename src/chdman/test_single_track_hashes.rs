@@ -0,0 +1,23 @@
+use super::*;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+    let chd_path = test_directory.join("Test Game (USA, Europe) (Single Track).chd");
+    let iso_path = test_directory.join("Test Game (USA, Europe).iso");
+
+    // when
+    let (size, hashes) = get_chd_size_and_hashes(&progress_bar, &chd_path).unwrap();
+    let tracks_sizes_and_hashes = get_chd_tracks_size_and_hashes(&progress_bar, &chd_path).unwrap();
+
+    // then
+    let (iso_size, iso_hashes) =
+        compute_size_and_hashes(&progress_bar, &iso_path, &None, &[], 1, 1).unwrap();
+    assert_eq!(size, iso_size);
+    assert_eq!(hashes.sha1, iso_hashes.sha1);
+    assert_eq!(tracks_sizes_and_hashes.len(), 1);
+    assert_eq!(tracks_sizes_and_hashes[0].0, iso_size);
+    assert_eq!(tracks_sizes_and_hashes[0].1.sha1, iso_hashes.sha1);
+}
@@ -0,0 +1,35 @@
+use super::*;
+
+fn system(name: &str) -> System {
+    System {
+        id: 1,
+        name: String::from(name),
+        description: String::from(name),
+        version: String::from("20230322"),
+        url: None,
+        complete: false,
+        arcade: false,
+        merging: 0,
+        preferred_format: None,
+    }
+}
+
+#[test]
+fn test() {
+    // given
+    let cd_system = system("Sony - PlayStation");
+    let dvd_system = system("Microsoft - Xbox");
+    let mixed_system = system("Sony - PlayStation 2");
+
+    // when
+    let cd_auto_type = get_system_chd_type(&cd_system, &ChdType::Auto);
+    let dvd_auto_type = get_system_chd_type(&dvd_system, &ChdType::Auto);
+    let dvd_raw_type = get_system_chd_type(&dvd_system, &ChdType::Raw);
+    let mixed_auto_type = get_system_chd_type(&mixed_system, &ChdType::Auto);
+
+    // then
+    assert!(cd_auto_type == ChdType::Cd);
+    assert!(dvd_auto_type == ChdType::Dvd);
+    assert!(dvd_raw_type == ChdType::Raw);
+    assert!(mixed_auto_type == ChdType::Auto);
+}
@@ -2,19 +2,19 @@
 use super::chdman;
 use super::checksum::*;
 use super::config::*;
+#[cfg(feature = "cso")]
+use super::cso;
 use super::database::*;
 #[cfg(feature = "rvz")]
 use super::dolphin;
-#[cfg(feature = "cso")]
-use super::maxcso;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::sevenzip;
 use super::util::*;
 use async_std::path::Path;
 use cfg_if::cfg_if;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use simple_error::SimpleResult;
 use sqlx::sqlite::SqliteConnection;
 use std::collections::HashMap;
@@ -39,12 +39,19 @@ pub fn subcommand() -> Command {
                 .required(false)
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("NO_CACHE")
+                .long("no-cache")
+                .help("Ignore cached hashes")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
 }
 
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let systems = prompt_for_systems(connection, None, false, matches.get_flag("ALL")).await?;
     let hash_algorithm = match find_setting_by_key(connection, "HASH_ALGORITHM")
@@ -66,6 +73,7 @@ pub async fn main(
             &system,
             matches.get_flag("SIZE"),
             &hash_algorithm,
+            !matches.get_flag("NO_CACHE"),
         )
         .await?;
         progress_bar.println("");
@@ -73,12 +81,13 @@ pub async fn main(
     Ok(())
 }
 
-async fn check_system(
+pub async fn check_system(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     size: bool,
     hash_algorithm: &HashAlgorithm,
+    cache: bool,
 ) -> SimpleResult<()> {
     let header = find_header_by_system_id(connection, system.id).await;
     let roms = find_roms_with_romfile_by_system_id(connection, system.id).await;
@@ -114,51 +123,45 @@ async fn check_system(
                 &romfile_path,
                 roms,
                 hash_algorithm,
+                cache,
             )
             .await;
         } else if CHD_EXTENSION == romfile_extension {
             cfg_if! {
                 if #[cfg(feature = "chd")] {
-                    result = check_chd(
-                        &mut transaction,
-                        progress_bar, &header,
-                        &romfile_path,
-                        roms,
-                        hash_algorithm
-                    )
-                    .await;
+                    // disks only have a SHA1
+                    if roms.first().unwrap().crc.is_none() {
+                        result = check_disk(&romfile_path, roms.first().unwrap());
+                    } else {
+                        result = check_chd(
+                            progress_bar,
+                            &romfile_path,
+                            &roms.iter().collect::<Vec<&Rom>>(),
+                        );
+                    }
                 } else {
                 progress_bar.println("Please rebuild with the CHD feature enabled");
                     continue;
                 }
             }
-        } else if CSO_EXTENSION == romfile_extension {
+        } else if CSO_EXTENSION == romfile_extension || ZSO_EXTENSION == romfile_extension {
             cfg_if! {
                 if #[cfg(feature = "cso")] {
-                    result = check_cso(
-                        &mut transaction,
-                        progress_bar,
-                        &header,
-                        &romfile_path,
-                        roms.get(0).unwrap(),
-                        hash_algorithm
-                    )
-                    .await;
+                    result = check_cso(progress_bar, &romfile_path, roms.get(0).unwrap());
                 } else {
                     progress_bar.println("Please rebuild with the CSO feature enabled");
                     continue;
                 }
             }
-        } else if RVZ_EXTENSION == romfile_extension {
+        } else if DOLPHIN_EXTENSIONS.contains(&romfile_extension) {
             cfg_if! {
                 if #[cfg(feature = "rvz")] {
-                    result = check_rvz(
+                    result = check_dolphin_image(
                         &mut transaction,
                         progress_bar,
                         &header,
                         &romfile_path,
                         roms.get(0).unwrap(),
-                        hash_algorithm
                     )
                     .await;
                 } else {
@@ -173,7 +176,7 @@ async fn check_system(
                 &header,
                 &romfile_path,
                 roms.get(0).unwrap(),
-                hash_algorithm,
+                cache,
             )
             .await;
         }
@@ -208,11 +211,12 @@ async fn check_system(
 
 async fn check_archive<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     header: &Option<Header>,
     romfile_path: &P,
     mut roms: Vec<Rom>,
     hash_algorithm: &HashAlgorithm,
+    cache: bool,
 ) -> SimpleResult<()> {
     let sevenzip_infos = sevenzip::parse_archive(progress_bar, romfile_path)?;
 
@@ -222,226 +226,146 @@ async fn check_archive<P: AsRef<Path>>(
 
     for sevenzip_info in sevenzip_infos {
         let size: u64;
-        let hash: String;
-        if header.is_some() || sevenzip_info.crc.is_empty() || hash_algorithm != &HashAlgorithm::Crc
+        let hashes: Hashes;
+
+        let cached_size_and_hashes = if cache {
+            find_cached_size_and_hashes(connection, romfile_path, &sevenzip_info.path, header).await
+        } else {
+            None
+        };
+
+        if let Some(size_and_hashes) = cached_size_and_hashes {
+            (size, hashes) = size_and_hashes;
+        } else if header.is_some()
+            || sevenzip_info.crc.is_empty()
+            || hash_algorithm != &HashAlgorithm::Crc
         {
             let tmp_directory = create_tmp_directory(connection).await?;
-            let extracted_path = sevenzip::extract_files_from_archive(
+            let extracted_path = sevenzip::extract_files_for_hashing(
                 progress_bar,
                 romfile_path,
                 &[&sevenzip_info.path],
                 &tmp_directory.path(),
             )?
             .remove(0);
-            let size_hash = get_size_and_hash(
+            (size, hashes) =
+                get_size_and_hashes(connection, progress_bar, &extracted_path, header, 1, 1)
+                    .await?;
+            cache_size_and_hashes(
                 connection,
-                progress_bar,
-                &extracted_path,
+                romfile_path,
+                &sevenzip_info.path,
                 header,
-                1,
-                1,
-                hash_algorithm,
+                size,
+                &hashes,
             )
             .await?;
-            size = size_hash.0;
-            hash = size_hash.1;
         } else {
             size = sevenzip_info.size;
-            hash = sevenzip_info.crc.clone();
+            hashes = Hashes::from_crc(&sevenzip_info.crc);
         }
         let rom_index = roms
             .iter()
             .position(|rom| rom.name == sevenzip_info.path)
             .unwrap();
         let rom = roms.remove(rom_index);
-        check_size_and_hash(&rom, i64::try_from(size).unwrap(), &hash, hash_algorithm)?;
+        check_size_and_hashes(&rom, i64::try_from(size).unwrap(), &hashes)?;
     }
 
     Ok(())
 }
 
 #[cfg(feature = "chd")]
-async fn check_chd<P: AsRef<Path>>(
-    connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
-    header: &Option<Header>,
+pub fn check_chd<P: AsRef<Path>>(
+    progress_bar: &Progress,
     romfile_path: &P,
-    roms: Vec<Rom>,
-    hash_algorithm: &HashAlgorithm,
+    roms: &[&Rom],
 ) -> SimpleResult<()> {
-    let tmp_directory = create_tmp_directory(connection).await?;
-
-    let names_sizes: Vec<(&str, u64)> = roms
-        .iter()
-        .map(|rom| (rom.name.as_str(), rom.size as u64))
-        .collect();
-    let bin_paths = chdman::extract_chd_to_multiple_tracks(
-        progress_bar,
-        romfile_path,
-        &tmp_directory.path(),
-        &names_sizes,
-        true,
-    )
-    .await?;
-    let mut hashes: Vec<String> = Vec::new();
-    for (i, bin_path) in bin_paths.iter().enumerate() {
-        let (_, hash) = get_size_and_hash(
-            connection,
-            progress_bar,
-            &bin_path,
-            header,
-            i,
-            bin_paths.len(),
-            hash_algorithm,
-        )
-        .await?;
-        hashes.push(hash);
+    // a single ROM covers the whole disc
+    let sizes_and_hashes = match roms.len() {
+        1 => vec![chdman::get_chd_size_and_hashes(progress_bar, romfile_path)?],
+        _ => chdman::get_chd_tracks_size_and_hashes(progress_bar, romfile_path)?,
+    };
+    if sizes_and_hashes.len() != roms.len() {
+        bail!("CHD contains a different number of tracks");
     }
-
-    match hash_algorithm {
-        HashAlgorithm::Crc => {
-            if roms
-                .iter()
-                .enumerate()
-                .any(|(i, rom)| &hashes[i] != rom.crc.as_ref().unwrap())
-            {
-                bail!("Checksum mismatch");
-            }
-        }
-        HashAlgorithm::Md5 => {
-            if roms
-                .iter()
-                .enumerate()
-                .any(|(i, rom)| &hashes[i] != rom.md5.as_ref().unwrap())
-            {
-                bail!("Checksum mismatch");
-            }
-        }
-        HashAlgorithm::Sha1 => {
-            if roms
-                .iter()
-                .enumerate()
-                .any(|(i, rom)| &hashes[i] != rom.sha1.as_ref().unwrap())
-            {
-                bail!("Checksum mismatch");
-            }
-        }
+    for (rom, (size, hashes)) in roms.iter().zip(sizes_and_hashes) {
+        check_size_and_hashes(rom, i64::try_from(size).unwrap(), &hashes)?;
     }
+    Ok(())
+}
 
+#[cfg(feature = "chd")]
+fn check_disk<P: AsRef<Path>>(romfile_path: &P, rom: &Rom) -> SimpleResult<()> {
+    if rom.sha1.as_ref().unwrap() != &chdman::get_chd_sha1(romfile_path)? {
+        bail!("Checksum mismatch");
+    }
     Ok(())
 }
 
 #[cfg(feature = "cso")]
-async fn check_cso<P: AsRef<Path>>(
-    connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
-    header: &Option<Header>,
+pub fn check_cso<P: AsRef<Path>>(
+    progress_bar: &Progress,
     romfile_path: &P,
     rom: &Rom,
-    hash_algorithm: &HashAlgorithm,
 ) -> SimpleResult<()> {
-    let tmp_directory = create_tmp_directory(connection).await?;
-    let iso_path = maxcso::extract_cso(progress_bar, romfile_path, &tmp_directory.path())?;
-    let (size, hash) = get_size_and_hash(
-        connection,
-        progress_bar,
-        &iso_path,
-        header,
-        1,
-        1,
-        hash_algorithm,
-    )
-    .await?;
-    check_size_and_hash(rom, i64::try_from(size).unwrap(), &hash, hash_algorithm)?;
+    let (size, hashes) = cso::get_cso_size_and_hashes(progress_bar, romfile_path, 1, 1)?;
+    check_size_and_hashes(rom, i64::try_from(size).unwrap(), &hashes)?;
     Ok(())
 }
 
 #[cfg(feature = "rvz")]
-async fn check_rvz<P: AsRef<Path>>(
+pub async fn check_dolphin_image<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     header: &Option<Header>,
     romfile_path: &P,
     rom: &Rom,
-    hash_algorithm: &HashAlgorithm,
 ) -> SimpleResult<()> {
     let tmp_directory = create_tmp_directory(connection).await?;
-    let iso_path = dolphin::extract_rvz(progress_bar, romfile_path, &tmp_directory.path())?;
-    let (size, hash) = get_size_and_hash(
-        connection,
-        progress_bar,
-        &iso_path,
-        header,
-        1,
-        1,
-        hash_algorithm,
-    )
-    .await?;
-    check_size_and_hash(rom, i64::try_from(size).unwrap(), &hash, hash_algorithm)?;
+    let iso_path =
+        dolphin::extract_image_for_hashing(progress_bar, romfile_path, &tmp_directory.path())?;
+    let (size, hashes) =
+        get_size_and_hashes(connection, progress_bar, &iso_path, header, 1, 1).await?;
+    check_size_and_hashes(rom, i64::try_from(size).unwrap(), &hashes)?;
     Ok(())
 }
 
 async fn check_original<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     header: &Option<Header>,
     romfile_path: &P,
     rom: &Rom,
-    hash_algorithm: &HashAlgorithm,
+    cache: bool,
 ) -> SimpleResult<()> {
-    let (size, hash) = get_size_and_hash(
-        connection,
-        progress_bar,
-        romfile_path,
-        header,
-        1,
-        1,
-        hash_algorithm,
-    )
-    .await?;
-    check_size_and_hash(rom, i64::try_from(size).unwrap(), &hash, hash_algorithm)?;
+    let (size, hashes) =
+        get_cached_size_and_hashes(connection, progress_bar, romfile_path, header, 1, 1, cache)
+            .await?;
+    check_size_and_hashes(rom, i64::try_from(size).unwrap(), &hashes)?;
     Ok(())
 }
 
-fn check_size_and_hash(
-    rom: &Rom,
-    size: i64,
-    hash: &str,
-    hash_algorithm: &HashAlgorithm,
-) -> SimpleResult<()> {
+fn check_size_and_hashes(rom: &Rom, size: i64, hashes: &Hashes) -> SimpleResult<()> {
     if size != rom.size {
         bail!("Size mismatch");
     };
-    match hash_algorithm {
-        HashAlgorithm::Crc => {
-            if hash != rom.crc.as_ref().unwrap() {
-                bail!("Checksum mismatch");
-            }
-        }
-        HashAlgorithm::Md5 => {
-            if hash != rom.md5.as_ref().unwrap() {
-                bail!("Checksum mismatch");
-            }
-        }
-        HashAlgorithm::Sha1 => {
-            if hash != rom.sha1.as_ref().unwrap() {
-                bail!("Checksum mismatch");
-            }
-        }
+    if !hashes.matches(rom) {
+        bail!("Checksum mismatch");
     }
     Ok(())
 }
 
 async fn move_to_trash(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     romfile: &Romfile,
 ) -> SimpleResult<()> {
     let new_path = get_trash_directory(connection, progress_bar, Some(system))
         .await?
         .join(Path::new(&romfile.path).file_name().unwrap());
-    rename_file(progress_bar, &romfile.path, &new_path, true).await?;
+    rename_cached_file(connection, progress_bar, &romfile.path, &new_path, true).await?;
     update_romfile(
         connection,
         romfile.id,
@@ -449,6 +373,7 @@ async fn move_to_trash(
         romfile.size as u64,
     )
     .await;
+    journal_rename(connection, &romfile.path, &new_path, Some(romfile.id)).await;
     Ok(())
 }
 
@@ -461,6 +386,8 @@ mod test_cso;
 #[cfg(test)]
 mod test_original;
 #[cfg(test)]
+mod test_original_cache;
+#[cfg(test)]
 mod test_original_crc_mismatch;
 #[cfg(test)]
 mod test_original_size_mismatch;
@@ -12,7 +12,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -23,22 +23,24 @@ async fn test() {
     let tmp_directory = TempDir::new_in(&test_directory).unwrap();
     let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
 
-    let matches = import_dats::subcommand()
-        .get_matches_from(&["import-dats", "tests/Test System (20200721).dat"]);
+    let matches = import_dats::subcommand().get_matches_from(&[
+        "import-dats",
+        "tests/Test System (20231018) (Multiple Tracks).dat",
+    ]);
     import_dats::main(&mut connection, &matches, &progress_bar)
         .await
         .unwrap();
 
-    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue");
+    let romfile_path = tmp_directory.join("Test Game (World) (Multiple Tracks).cue");
     fs::copy(
-        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue"),
+        test_directory.join("Test Game (World) (Multiple Tracks).cue"),
         &romfile_path,
     )
     .await
     .unwrap();
-    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Multiple Tracks).chd");
+    let romfile_path = tmp_directory.join("Test Game (World) (Multiple Tracks).chd");
     fs::copy(
-        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).chd"),
+        test_directory.join("Test Game (World) (Multiple Tracks).chd"),
         &romfile_path,
     )
     .await
@@ -59,6 +61,7 @@ async fn test() {
         &system,
         false,
         &HashAlgorithm::Crc,
+        true,
     )
     .await
     .unwrap();
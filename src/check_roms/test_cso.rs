@@ -21,7 +21,7 @@ async fn test() {
             env::var("PATH").unwrap()
         ),
     );
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -61,6 +61,7 @@ async fn test() {
         &system,
         false,
         &HashAlgorithm::Crc,
+        true,
     )
     .await
     .unwrap();
@@ -12,7 +12,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -52,6 +52,7 @@ async fn test() {
         &system,
         true,
         &HashAlgorithm::Crc,
+        true,
     )
     .await
     .unwrap();
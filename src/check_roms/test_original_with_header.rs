@@ -12,7 +12,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -46,9 +46,12 @@ async fn test() {
         Some(&system),
         &header,
         &romfile_path,
-        &HashAlgorithm::Crc,
-        true,
-        false,
+        &import_roms::ImportOptions {
+            hash_algorithm: HashAlgorithm::Crc,
+            trash: true,
+            force: false,
+            cache: true,
+        },
     )
     .await
     .unwrap();
@@ -60,6 +63,7 @@ async fn test() {
         &system,
         false,
         &HashAlgorithm::Crc,
+        true,
     )
     .await
     .unwrap();
@@ -1,6 +1,6 @@
-use super::config::HashAlgorithm;
+use super::config::{is_dry_run, HashAlgorithm};
 use super::database::*;
-use super::model::Header;
+use super::model::{CachedHashes, Header, Rom, Rule};
 use super::progress::*;
 use super::util::*;
 use super::SimpleResult;
@@ -11,13 +11,13 @@ use digest::generic_array::GenericArray;
 use digest::Digest;
 use digest::OutputSizeUser;
 use digest::{FixedOutput, HashMarker, Reset, Update};
-use indicatif::ProgressBar;
 use md5::Md5;
 use sha1::Sha1;
 use sqlx::sqlite::SqliteConnection;
 use std::fs;
 use std::io;
 use std::io::prelude::*;
+use std::time::UNIX_EPOCH;
 
 #[derive(Clone, Default)]
 struct Crc32 {
@@ -67,145 +67,288 @@ impl io::Write for Crc32 {
     }
 }
 
-pub async fn get_size_and_hash<P: AsRef<Path>>(
+struct MultiDigest {
+    crc: Crc32,
+    md5: Md5,
+    sha1: Sha1,
+}
+
+impl MultiDigest {
+    pub fn new() -> Self {
+        Self {
+            crc: Crc32::new(),
+            md5: Md5::new(),
+            sha1: Sha1::new(),
+        }
+    }
+}
+
+impl io::Write for MultiDigest {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Update::update(&mut self.crc, buf);
+        Update::update(&mut self.md5, buf);
+        Update::update(&mut self.sha1, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Hashes {
+    pub crc: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+impl Hashes {
+    pub fn from_crc(crc: &str) -> Self {
+        Self {
+            crc: Some(crc.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn get(&self, hash_algorithm: &HashAlgorithm) -> Option<&str> {
+        match hash_algorithm {
+            HashAlgorithm::Crc => self.crc.as_deref(),
+            HashAlgorithm::Md5 => self.md5.as_deref(),
+            HashAlgorithm::Sha1 => self.sha1.as_deref(),
+        }
+    }
+
+    // every hash known on both sides must match, and there must be at least one
+    pub fn matches(&self, rom: &Rom) -> bool {
+        let pairs = [
+            (self.crc.as_ref(), rom.crc.as_ref()),
+            (self.md5.as_ref(), rom.md5.as_ref()),
+            (self.sha1.as_ref(), rom.sha1.as_ref()),
+        ];
+        let compared: Vec<bool> = pairs
+            .iter()
+            .filter_map(|(hash, rom_hash)| match (hash, rom_hash) {
+                (Some(hash), Some(rom_hash)) => Some(hash == rom_hash),
+                _ => None,
+            })
+            .collect();
+        !compared.is_empty() && compared.iter().all(|&m| m)
+    }
+
+    // the selected algorithm comes first, followed by the other available ones
+    pub fn prioritized(&self, hash_algorithm: &HashAlgorithm) -> Vec<(HashAlgorithm, &str)> {
+        let mut hash_algorithms = vec![hash_algorithm.clone()];
+        for other_hash_algorithm in [HashAlgorithm::Crc, HashAlgorithm::Md5, HashAlgorithm::Sha1] {
+            if &other_hash_algorithm != hash_algorithm {
+                hash_algorithms.push(other_hash_algorithm);
+            }
+        }
+        hash_algorithms
+            .into_iter()
+            .filter_map(|hash_algorithm| {
+                self.get(&hash_algorithm)
+                    .map(|hash| (hash_algorithm.clone(), hash))
+            })
+            .collect()
+    }
+}
+
+pub async fn get_size_and_hashes<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     file_path: &P,
     header: &Option<Header>,
     position: usize,
     total: usize,
-    hash_algorithm: &HashAlgorithm,
-) -> SimpleResult<(u64, String)> {
-    Ok(match hash_algorithm {
-        HashAlgorithm::Crc => {
-            get_size_and_crc(connection, progress_bar, file_path, header, position, total).await?
-        }
-        HashAlgorithm::Md5 => {
-            get_size_and_md5(connection, progress_bar, file_path, header, position, total).await?
-        }
-        HashAlgorithm::Sha1 => {
-            get_size_and_sha1(connection, progress_bar, file_path, header, position, total).await?
-        }
-    })
+) -> SimpleResult<(u64, Hashes)> {
+    let rules = match header {
+        Some(header) => find_rules_by_header_id(connection, header.id).await,
+        None => Vec::new(),
+    };
+    compute_size_and_hashes(progress_bar, file_path, header, &rules, position, total)
 }
 
-async fn get_size_and_crc<P: AsRef<Path>>(
-    connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+pub fn compute_size_and_hashes<P: AsRef<Path>>(
+    progress_bar: &Progress,
     file_path: &P,
     header: &Option<Header>,
+    rules: &[Rule],
     position: usize,
     total: usize,
-) -> SimpleResult<(u64, String)> {
-    let (mut file, size) = get_file_and_size(connection, file_path, header).await?;
+) -> SimpleResult<(u64, Hashes)> {
+    let (mut file, size) = get_file_and_size(file_path, header, rules)?;
+    compute_reader_size_and_hashes(progress_bar, &mut file, size, position, total)
+}
 
+// hash decompressed data without writing it to disk first
+pub fn compute_reader_size_and_hashes<R: Read>(
+    progress_bar: &Progress,
+    reader: &mut R,
+    size: u64,
+    position: usize,
+    total: usize,
+) -> SimpleResult<(u64, Hashes)> {
     progress_bar.reset();
-    progress_bar.set_message(format!("Computing CRC ({}/{})", position, total));
+    progress_bar.set_message(format!("Computing hashes ({}/{})", position, total));
     progress_bar.set_style(get_bytes_progress_style());
     progress_bar.set_length(size);
 
-    // compute the checksum
-    let mut digest = Crc32::new();
+    // compute all checksums in a single pass
+    let mut digest = MultiDigest::new();
     try_with!(
-        io::copy(&mut file, &mut progress_bar.wrap_write(&mut digest)),
+        io::copy(reader, &mut progress_bar.wrap_write(&mut digest)),
         "Failed to copy data"
     );
-    let crc = format!("{:08x}", digest.finalize()).to_lowercase();
+    let hashes = Hashes {
+        crc: Some(format!("{:08x}", digest.crc.finalize()).to_lowercase()),
+        md5: Some(format!("{:032x}", digest.md5.finalize()).to_lowercase()),
+        sha1: Some(format!("{:040x}", digest.sha1.finalize()).to_lowercase()),
+    };
 
     progress_bar.set_message("");
     progress_bar.set_style(get_none_progress_style());
 
-    Ok((size, crc))
+    Ok((size, hashes))
 }
 
-async fn get_size_and_md5<P: AsRef<Path>>(
+pub async fn find_cached_size_and_hashes<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
     file_path: &P,
+    entry: &str,
     header: &Option<Header>,
-    position: usize,
-    total: usize,
-) -> SimpleResult<(u64, String)> {
-    let (mut file, size) = get_file_and_size(connection, file_path, header).await?;
-
-    progress_bar.reset();
-    progress_bar.set_message(format!("Computing MD5 ({}/{})", position, total));
-    progress_bar.set_style(get_bytes_progress_style());
-    progress_bar.set_length(size);
-
-    let mut digest = Md5::new();
-    try_with!(
-        io::copy(&mut file, &mut progress_bar.wrap_write(&mut digest)),
-        "Failed to copy data"
-    );
-    let md5 = format!("{:032x}", digest.finalize()).to_lowercase();
-
-    progress_bar.set_message("");
-    progress_bar.set_style(get_none_progress_style());
+) -> Option<(u64, Hashes)> {
+    let (file_size, file_mtime) = get_size_and_mtime(file_path).ok()?;
+    let cached_hashes = find_cached_hashes_by_path_and_entry(
+        connection,
+        file_path.as_ref().as_os_str().to_str().unwrap(),
+        entry,
+    )
+    .await?;
+    if cached_hashes.file_size as u64 != file_size
+        || cached_hashes.file_mtime != file_mtime
+        || cached_hashes.header_id != header.as_ref().map(|header| header.id)
+    {
+        return None;
+    }
+    Some((
+        cached_hashes.size as u64,
+        Hashes {
+            crc: cached_hashes.crc,
+            md5: cached_hashes.md5,
+            sha1: cached_hashes.sha1,
+        },
+    ))
+}
 
-    Ok((size, md5))
+pub async fn cache_size_and_hashes<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    file_path: &P,
+    entry: &str,
+    header: &Option<Header>,
+    size: u64,
+    hashes: &Hashes,
+) -> SimpleResult<()> {
+    // files may not have been moved in dry-run mode, and the cache is rolled back anyway
+    if is_dry_run() {
+        return Ok(());
+    }
+    let (file_size, file_mtime) = get_size_and_mtime(file_path)?;
+    create_or_update_cached_hashes(
+        connection,
+        file_path.as_ref().as_os_str().to_str().unwrap(),
+        entry,
+        &CachedHashes {
+            file_size: i64::try_from(file_size).unwrap(),
+            file_mtime,
+            header_id: header.as_ref().map(|header| header.id),
+            size: i64::try_from(size).unwrap(),
+            crc: hashes.crc.clone(),
+            md5: hashes.md5.clone(),
+            sha1: hashes.sha1.clone(),
+        },
+    )
+    .await;
+    Ok(())
 }
 
-async fn get_size_and_sha1<P: AsRef<Path>>(
+pub async fn get_cached_size_and_hashes<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     file_path: &P,
     header: &Option<Header>,
     position: usize,
     total: usize,
-) -> SimpleResult<(u64, String)> {
-    let (mut file, size) = get_file_and_size(connection, file_path, header).await?;
+    cache: bool,
+) -> SimpleResult<(u64, Hashes)> {
+    if cache {
+        if let Some(size_and_hashes) =
+            find_cached_size_and_hashes(connection, file_path, "", header).await
+        {
+            return Ok(size_and_hashes);
+        }
+    }
+    let (size, hashes) =
+        get_size_and_hashes(connection, progress_bar, file_path, header, position, total).await?;
+    cache_size_and_hashes(connection, file_path, "", header, size, &hashes).await?;
+    Ok((size, hashes))
+}
 
-    progress_bar.reset();
-    progress_bar.set_message(format!("Computing SHA1 ({}/{})", position, total));
-    progress_bar.set_style(get_bytes_progress_style());
-    progress_bar.set_length(size);
+fn get_size_and_mtime<P: AsRef<Path>>(file_path: &P) -> SimpleResult<(u64, i64)> {
+    let metadata = try_with!(
+        fs::metadata(file_path.as_ref()),
+        "Failed to get metadata for {:?}",
+        file_path.as_ref()
+    );
+    let mtime = try_with!(
+        try_with!(metadata.modified(), "Failed to get modification time")
+            .duration_since(UNIX_EPOCH),
+        "Failed to compute modification time"
+    );
+    Ok((metadata.len(), mtime.as_nanos() as i64))
+}
 
-    let mut digest = Sha1::new();
+// returns the header when all the rules match
+pub fn read_header<R: Read>(
+    reader: &mut R,
+    header: &Header,
+    rules: &[Rule],
+) -> SimpleResult<Option<Vec<u8>>> {
+    let mut buffer: Vec<u8> = Vec::with_capacity(header.size as usize);
     try_with!(
-        io::copy(&mut file, &mut progress_bar.wrap_write(&mut digest)),
-        "Failed to copy data"
+        reader.take(header.size as u64).read_to_end(&mut buffer),
+        "Failed to read into buffer"
     );
-    let md5 = format!("{:040x}", digest.finalize()).to_lowercase();
 
-    progress_bar.set_message("");
-    progress_bar.set_style(get_none_progress_style());
+    let mut matches: Vec<bool> = Vec::new();
+    for rule in rules {
+        let start_byte = rule.start_byte as usize;
+        let hex_values: Vec<String> = buffer[start_byte..]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let hex_value = hex_values.join("").to_lowercase();
+        matches.push(hex_value.starts_with(&rule.hex_value.to_lowercase()));
+    }
 
-    Ok((size, md5))
+    if matches.iter().all(|&m| m) {
+        Ok(Some(buffer))
+    } else {
+        Ok(None)
+    }
 }
 
-async fn get_file_and_size<P: AsRef<Path>>(
-    connection: &mut SqliteConnection,
+fn get_file_and_size<P: AsRef<Path>>(
     file_path: &P,
     header: &Option<Header>,
+    rules: &[Rule],
 ) -> SimpleResult<(fs::File, u64)> {
     let mut file = open_file_sync(file_path)?;
     let mut size = file.metadata().unwrap().len();
 
     // extract a potential header, revert if none is found
-    if header.is_some() {
-        let header = header.as_ref().unwrap();
-        let rules = find_rules_by_header_id(connection, header.id).await;
-        let mut buffer: Vec<u8> = Vec::with_capacity(header.size as usize);
-        try_with!(
-            (&mut file)
-                .take(header.size as u64)
-                .read_to_end(&mut buffer),
-            "Failed to read into buffer"
-        );
-
-        let mut matches: Vec<bool> = Vec::new();
-        for rule in rules {
-            let start_byte = rule.start_byte as usize;
-            let hex_values: Vec<String> = buffer[start_byte..]
-                .iter()
-                .map(|b| format!("{:02x}", b))
-                .collect();
-            let hex_value = hex_values.join("").to_lowercase();
-            matches.push(hex_value.starts_with(&rule.hex_value.to_lowercase()));
-        }
-
-        if matches.iter().all(|&m| m) {
+    if let Some(header) = header {
+        if read_header(&mut file, header, rules)?.is_some() {
             size -= header.size as u64;
         } else {
             try_with!(file.rewind(), "Failed to rewind file");
@@ -0,0 +1,229 @@
+use super::model::*;
+use super::SimpleResult;
+use std::io::{BufRead, Read};
+use std::iter::Peekable;
+use std::str::Chars;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+enum Token {
+    Open,
+    Close,
+    Value(String),
+}
+
+enum Node {
+    Value(String),
+    Block(Vec<(String, Node)>),
+}
+
+pub fn is_clrmamepro_dat<R: BufRead>(reader: &mut R) -> SimpleResult<bool> {
+    let buffer = try_with!(reader.fill_buf(), "Failed to read DAT file");
+    let buffer = buffer.strip_prefix(UTF8_BOM).unwrap_or(buffer);
+    Ok(buffer
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .map(|byte| *byte != b'<')
+        .unwrap_or(false))
+}
+
+pub fn parse_clrmamepro_dat<R: Read>(mut reader: R) -> SimpleResult<DatfileXml> {
+    let mut input = String::new();
+    try_with!(reader.read_to_string(&mut input), "Failed to read DAT file");
+    let input = input.strip_prefix('\u{feff}').unwrap_or(&input);
+
+    let mut tokens = tokenize(input)?.into_iter().peekable();
+    let entries = parse_entries(&mut tokens, false)?;
+
+    let mut system_xml: Option<SystemXml> = None;
+    let mut games_xml: Vec<GameXml> = Vec::new();
+    for (key, node) in entries {
+        match (key.as_str(), node) {
+            ("clrmamepro", Node::Block(block)) => {
+                system_xml = Some(build_system(&block)?);
+            }
+            ("game", Node::Block(block)) | ("machine", Node::Block(block)) => {
+                games_xml.push(build_game(&block, false)?);
+            }
+            ("resource", Node::Block(block)) => {
+                games_xml.push(build_game(&block, true)?);
+            }
+            _ => (),
+        }
+    }
+
+    match system_xml {
+        Some(system_xml) => Ok(DatfileXml {
+            system: system_xml,
+            games: games_xml,
+        }),
+        None => bail!("Missing clrmamepro header"),
+    }
+}
+
+fn tokenize(input: &str) -> SimpleResult<Vec<Token>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.peek() {
+        match c {
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Value(read_quoted(&mut chars)?));
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() || *c == '(' || *c == ')' {
+                        break;
+                    }
+                    value.push(*c);
+                    chars.next();
+                }
+                tokens.push(Token::Value(value));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// there are no escape sequences, backslashes are path separators in names
+fn read_quoted(chars: &mut Peekable<Chars>) -> SimpleResult<String> {
+    let mut value = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(value);
+        }
+        value.push(c);
+    }
+    bail!("Unterminated string \"{}\"", value);
+}
+
+fn parse_entries<I: Iterator<Item = Token>>(
+    tokens: &mut Peekable<I>,
+    nested: bool,
+) -> SimpleResult<Vec<(String, Node)>> {
+    let mut entries: Vec<(String, Node)> = Vec::new();
+    loop {
+        let key = match tokens.next() {
+            Some(Token::Value(key)) => key,
+            Some(Token::Close) if nested => return Ok(entries),
+            Some(_) => bail!("Unexpected parenthesis"),
+            None if nested => bail!("Unexpected end of file"),
+            None => return Ok(entries),
+        };
+        let node = match tokens.next() {
+            Some(Token::Open) => Node::Block(parse_entries(tokens, true)?),
+            Some(Token::Value(value)) => Node::Value(value),
+            Some(Token::Close) => bail!("Missing value for \"{}\"", key),
+            None => bail!("Unexpected end of file"),
+        };
+        entries.push((key, node));
+    }
+}
+
+fn get_value(block: &[(String, Node)], key: &str) -> Option<String> {
+    block.iter().find_map(|(k, node)| match node {
+        Node::Value(value) if k == key => Some(value.clone()),
+        _ => None,
+    })
+}
+
+fn build_system(block: &[(String, Node)]) -> SimpleResult<SystemXml> {
+    let name = match get_value(block, "name") {
+        Some(name) => name,
+        None => bail!("Missing system name"),
+    };
+    Ok(SystemXml {
+        description: get_value(block, "description").unwrap_or_else(|| name.clone()),
+        name,
+        version: get_value(block, "version").unwrap_or_default(),
+        url: get_value(block, "url").or_else(|| get_value(block, "homepage")),
+        clrmamepros: get_value(block, "header")
+            .map(|header| {
+                vec![ClrMameProXml {
+                    header: Some(header),
+                }]
+            })
+            .unwrap_or_default(),
+    })
+}
+
+fn build_game(block: &[(String, Node)], resource: bool) -> SimpleResult<GameXml> {
+    let name = match get_value(block, "name") {
+        Some(name) => name,
+        None => bail!("Missing game name"),
+    };
+    let mut roms_xml: Vec<RomXml> = Vec::new();
+    let mut disks_xml: Vec<DiskXml> = Vec::new();
+    for (key, node) in block {
+        match (key.as_str(), node) {
+            ("rom", Node::Block(rom_block)) => roms_xml.push(build_rom(rom_block, &name)?),
+            ("disk", Node::Block(disk_block)) => disks_xml.push(build_disk(disk_block, &name)?),
+            _ => (),
+        }
+    }
+    Ok(GameXml {
+        description: get_value(block, "description").unwrap_or_else(|| name.clone()),
+        cloneof: get_value(block, "cloneof"),
+        romof: get_value(block, "romof"),
+        isbios: if resource {
+            Some(String::from("yes"))
+        } else {
+            get_value(block, "isbios")
+        },
+        isdevice: None,
+        runnable: None,
+        comment: get_value(block, "comment"),
+        roms: roms_xml,
+        disks: disks_xml,
+        driver: None,
+        name,
+    })
+}
+
+fn build_rom(block: &[(String, Node)], game_name: &str) -> SimpleResult<RomXml> {
+    let name = match get_value(block, "name") {
+        Some(name) => name,
+        None => bail!("Missing rom name in \"{}\"", game_name),
+    };
+    let size = match get_value(block, "size").map(|size| size.parse::<i64>()) {
+        Some(Ok(size)) => size,
+        _ => bail!("Invalid size for \"{}\"", name),
+    };
+    Ok(RomXml {
+        name,
+        merge: get_value(block, "merge"),
+        size,
+        crc: get_value(block, "crc").map(|crc| crc.to_lowercase()),
+        md5: get_value(block, "md5").map(|md5| md5.to_lowercase()),
+        sha1: get_value(block, "sha1").map(|sha1| sha1.to_lowercase()),
+        status: get_value(block, "status").or_else(|| get_value(block, "flags")),
+    })
+}
+
+fn build_disk(block: &[(String, Node)], game_name: &str) -> SimpleResult<DiskXml> {
+    let name = match get_value(block, "name") {
+        Some(name) => name,
+        None => bail!("Missing disk name in \"{}\"", game_name),
+    };
+    Ok(DiskXml {
+        name,
+        merge: get_value(block, "merge"),
+        sha1: get_value(block, "sha1").map(|sha1| sha1.to_lowercase()),
+        status: get_value(block, "status").or_else(|| get_value(block, "flags")),
+    })
+}
+
+#[cfg(test)]
+mod test_backslash;
@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn test() {
+    // given
+    let dat = r#"
+clrmamepro (
+	name "Test System"
+	description "Test System"
+	version 20200721
+)
+
+game (
+	name "Test Game (USA, Europe)"
+	description "Test Game (USA, Europe)"
+	rom ( name "Test Game (USA, Europe)\Test Game (USA, Europe).rom" size 256 crc cc721e14 )
+)
+"#;
+
+    // when
+    let datfile_xml = parse_clrmamepro_dat(dat.as_bytes()).unwrap();
+
+    // then
+    assert_eq!(datfile_xml.games.len(), 1);
+    let game_xml = &datfile_xml.games[0];
+    assert_eq!(game_xml.roms.len(), 1);
+    assert_eq!(
+        game_xml.roms[0].name,
+        "Test Game (USA, Europe)\\Test Game (USA, Europe).rom"
+    );
+}
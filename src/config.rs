@@ -1,15 +1,25 @@
 use super::database::*;
 use super::dolphin::{RVZ_BLOCK_SIZE_RANGE, RVZ_COMPRESSION_LEVEL_RANGE};
+use super::import_roms::IMPORT_THREADS_RANGE;
+use super::progress::*;
+use super::prompt::*;
 use super::sevenzip::{SEVENZIP_COMPRESSION_LEVEL_RANGE, ZIP_COMPRESSION_LEVEL_RANGE};
 use super::util::*;
 use super::SimpleResult;
+#[cfg(feature = "server")]
+use argon2::password_hash::rand_core::OsRng;
+#[cfg(feature = "server")]
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+#[cfg(feature = "server")]
+use argon2::{Algorithm, Argon2, Params, Version};
 use async_std::path::{Path, PathBuf};
 use cfg_if::cfg_if;
-use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 use phf::phf_map;
 use sqlx::sqlite::SqliteConnection;
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use strum::{Display, EnumString, EnumVariantNames, VariantNames};
 
 cfg_if! {
@@ -31,7 +41,19 @@ cfg_if! {
     }
 }
 
-#[derive(PartialEq, EnumString, EnumVariantNames)]
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, PartialEq, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ChdType {
+    Auto,
+    Cd,
+    Dvd,
+    Hd,
+    Raw,
+}
+
+#[derive(Clone, PartialEq, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "lowercase")]
 pub enum HashAlgorithm {
     Crc,
@@ -73,12 +95,16 @@ pub enum PreferRegion {
 }
 
 const BOOLEANS: &[&str] = &[
+    "CHD_VERIFY",
+    "CSO_VERIFY",
     "GROUP_SUBSYSTEMS",
     "PREFER_PARENTS",
     "REGIONS_ONE_STRICT",
+    "RVZ_VERIFY",
     "SEVENZIP_SOLID_COMPRESSION",
 ];
 const CHOICES: phf::Map<&str, &[&str]> = phf_map! {
+    "CHD_TYPE" => ChdType::VARIANTS,
     "HASH_ALGORITHM" => HashAlgorithm::VARIANTS,
     "PREFER_REGIONS" => PreferRegion::VARIANTS,
     "PREFER_VERSIONS" => PreferVersion::VARIANTS,
@@ -87,6 +113,7 @@ const CHOICES: phf::Map<&str, &[&str]> = phf_map! {
     "RVZ_COMPRESSION_ALGORITHM" => RvzCompressionAlgorithm::VARIANTS,
 };
 const INTEGERS: phf::Map<&str, &[usize; 2]> = phf_map! {
+    "IMPORT_THREADS" => &IMPORT_THREADS_RANGE,
     "RVZ_BLOCK_SIZE" => &RVZ_BLOCK_SIZE_RANGE,
     "RVZ_COMPRESSION_LEVEL" => &RVZ_COMPRESSION_LEVEL_RANGE,
     "SEVENZIP_COMPRESSION_LEVEL" => &SEVENZIP_COMPRESSION_LEVEL_RANGE,
@@ -100,12 +127,49 @@ const LISTS: &[&str] = &[
     "REGIONS_ONE",
 ];
 const PATHS: &[&str] = &["ROM_DIRECTORY", "TMP_DIRECTORY"];
+const STRINGS: &[&str] = &[
+    "SERVER_PASSWORD",
+    "SERVER_READ_ONLY_PASSWORD",
+    "SERVER_READ_ONLY_TOKEN",
+    "SERVER_READ_ONLY_USERNAME",
+    "SERVER_TOKEN",
+    "SERVER_USERNAME",
+];
+// secrets are stored hashed and never printed back
+const SECRETS: &[&str] = &[
+    "SERVER_PASSWORD",
+    "SERVER_READ_ONLY_PASSWORD",
+    "SERVER_READ_ONLY_TOKEN",
+    "SERVER_TOKEN",
+];
+// argon2id with the OWASP recommended cost, in KiB, iterations and lanes
+#[cfg(feature = "server")]
+const SECRET_MEMORY_COST: u32 = 19456;
+#[cfg(feature = "server")]
+const SECRET_TIME_COST: u32 = 2;
+#[cfg(feature = "server")]
+const SECRET_PARALLELISM: u32 = 1;
+const SECRET_MASK: &str = "********";
+// settings that are not read in the scope of a system
+const GLOBALS: &[&str] = &[
+    "HASH_ALGORITHM",
+    "IMPORT_THREADS",
+    "ROM_DIRECTORY",
+    "SERVER_PASSWORD",
+    "SERVER_READ_ONLY_PASSWORD",
+    "SERVER_READ_ONLY_TOKEN",
+    "SERVER_READ_ONLY_USERNAME",
+    "SERVER_TOKEN",
+    "SERVER_USERNAME",
+    "TMP_DIRECTORY",
+];
 
 #[cfg(feature = "chd")]
 pub static BIN_EXTENSION: &str = "bin";
 pub static CHD_EXTENSION: &str = "chd";
 pub static CSO_EXTENSION: &str = "cso";
 pub static CUE_EXTENSION: &str = "cue";
+pub static GCZ_EXTENSION: &str = "gcz";
 pub static ISO_EXTENSION: &str = "iso";
 pub static M3U_EXTENSION: &str = "m3u";
 pub static PKG_EXTENSION: &str = "pkg";
@@ -113,9 +177,14 @@ pub static PUP_EXTENSION: &str = "pup";
 pub static RAP_EXTENSION: &str = "rap";
 pub static RVZ_EXTENSION: &str = "rvz";
 pub static SEVENZIP_EXTENSION: &str = "7z";
+pub static WBFS_EXTENSION: &str = "wbfs";
+pub static WIA_EXTENSION: &str = "wia";
 pub static ZIP_EXTENSION: &str = "zip";
+pub static ZSO_EXTENSION: &str = "zso";
 
 pub static ARCHIVE_EXTENSIONS: [&str; 2] = [SEVENZIP_EXTENSION, ZIP_EXTENSION];
+pub static DOLPHIN_EXTENSIONS: [&str; 4] =
+    [GCZ_EXTENSION, RVZ_EXTENSION, WBFS_EXTENSION, WIA_EXTENSION];
 pub static PS3_EXTENSIONS: [&str; 3] = [PKG_EXTENSION, PUP_EXTENSION, RAP_EXTENSION];
 
 #[cfg(feature = "ird")]
@@ -130,8 +199,7 @@ pub fn subcommand() -> Command {
                 .long("list")
                 .help("Print the whole configuration")
                 .required(false)
-                .action(ArgAction::SetTrue)
-                .exclusive(true),
+                .action(ArgAction::SetTrue),
         )
         .arg(
             Arg::new("GET")
@@ -140,8 +208,7 @@ pub fn subcommand() -> Command {
                 .help("Print a single setting")
                 .required(false)
                 .num_args(1)
-                .value_name("KEY")
-                .exclusive(true),
+                .value_name("KEY"),
         )
         .arg(
             Arg::new("SET")
@@ -150,8 +217,7 @@ pub fn subcommand() -> Command {
                 .help("Configure a single setting")
                 .required(false)
                 .num_args(2)
-                .value_names(["KEY", "VALUE"])
-                .exclusive(true),
+                .value_names(["KEY", "VALUE"]),
         )
         .arg(
             Arg::new("ADD")
@@ -160,8 +226,7 @@ pub fn subcommand() -> Command {
                 .help("Add an entry to a list")
                 .required(false)
                 .num_args(2)
-                .value_names(["KEY", "VALUE"])
-                .exclusive(true),
+                .value_names(["KEY", "VALUE"]),
         )
         .arg(
             Arg::new("REMOVE")
@@ -170,20 +235,51 @@ pub fn subcommand() -> Command {
                 .help("Remove an entry from a list")
                 .required(false)
                 .num_args(2)
-                .value_names(["KEY", "VALUE"])
-                .exclusive(true),
+                .value_names(["KEY", "VALUE"]),
+        )
+        .arg(
+            Arg::new("UNSET")
+                .short('u')
+                .long("unset")
+                .help("Remove a system override")
+                .required(false)
+                .num_args(1)
+                .value_name("KEY")
+                .requires("SYSTEM"),
+        )
+        .group(
+            ArgGroup::new("ACTION")
+                .args(["LIST", "GET", "SET", "ADD", "REMOVE", "UNSET"])
+                .required(false),
+        )
+        .arg(
+            Arg::new("SYSTEM")
+                .long("system")
+                .help("Prompt for a system to override settings for")
+                .required(false)
+                .action(ArgAction::SetTrue),
         )
 }
 
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
+    let system_id = match matches.get_flag("SYSTEM") {
+        true => Some(prompt_for_system(connection, None).await?.id),
+        false => None,
+    };
+
     if matches.get_flag("LIST") {
-        list_settings(connection).await;
+        list_settings(connection, system_id).await;
     } else if matches.contains_id("GET") {
-        get_setting(connection, matches.get_one::<String>("GET").unwrap()).await;
+        get_setting(
+            connection,
+            matches.get_one::<String>("GET").unwrap(),
+            system_id,
+        )
+        .await;
     } else if matches.contains_id("SET") {
         if let [key, value] = matches
             .get_many::<String>("SET")
@@ -191,7 +287,7 @@ pub async fn main(
             .collect::<Vec<_>>()
             .as_slice()
         {
-            set_setting(connection, progress_bar, key, value).await?;
+            set_setting(connection, progress_bar, key, value, system_id).await?;
         };
     } else if matches.contains_id("ADD") {
         if let [key, value] = matches
@@ -200,7 +296,7 @@ pub async fn main(
             .collect::<Vec<_>>()
             .as_slice()
         {
-            add_to_list(connection, key, value).await;
+            add_to_list(connection, key, value, system_id).await;
         };
     } else if matches.contains_id("REMOVE") {
         if let [key, value] = matches
@@ -209,112 +305,256 @@ pub async fn main(
             .collect::<Vec<_>>()
             .as_slice()
         {
-            remove_from_list(connection, key, value).await;
+            remove_from_list(connection, key, value, system_id).await;
         };
+    } else if matches.contains_id("UNSET") {
+        unset_setting(
+            connection,
+            matches.get_one::<String>("UNSET").unwrap(),
+            system_id.unwrap(),
+        )
+        .await;
     }
 
     Ok(())
 }
 
-async fn list_settings(connection: &mut SqliteConnection) {
+async fn list_settings(connection: &mut SqliteConnection, system_id: Option<i64>) {
+    let system_settings: HashMap<String, Option<String>> = match system_id {
+        Some(system_id) => find_system_settings_by_system_id(connection, system_id)
+            .await
+            .into_iter()
+            .map(|system_setting| (system_setting.key, system_setting.value))
+            .collect(),
+        None => HashMap::new(),
+    };
     for setting in find_settings(connection).await {
-        println!("{} = {}", setting.key, setting.value.unwrap_or_default());
+        match system_settings.get(&setting.key) {
+            Some(value) => println!(
+                "{} = {} (system)",
+                setting.key,
+                mask_secret(&setting.key, value.to_owned())
+            ),
+            None => println!(
+                "{} = {}",
+                setting.key,
+                mask_secret(&setting.key, setting.value)
+            ),
+        }
     }
 }
 
-pub async fn get_setting(connection: &mut SqliteConnection, key: &str) {
-    let setting = find_setting_by_key(connection, key).await.unwrap();
-    println!("{} = {}", setting.key, setting.value.unwrap_or_default());
+pub async fn get_setting(connection: &mut SqliteConnection, key: &str, system_id: Option<i64>) {
+    match get_value(connection, key, system_id).await {
+        Some(value) => println!("{} = {}", key, mask_secret(key, value)),
+        None => println!("Unsupported setting"),
+    }
 }
 
-async fn set_setting(
+pub async fn set_setting(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     key: &str,
     value: &str,
+    system_id: Option<i64>,
 ) -> SimpleResult<()> {
-    if PATHS.contains(&key) {
+    if system_id.is_some() && GLOBALS.contains(&key) {
+        bail!("This setting can't be overridden per system");
+    } else if PATHS.contains(&key) {
         let p = get_canonicalized_path(&value.to_owned()).await?;
         create_directory(progress_bar, &p, false).await?;
         set_directory(connection, key, &p).await;
     } else if BOOLEANS.contains(&key) {
         let b: bool = try_with!(FromStr::from_str(value), "Failed to parse bool");
-        set_bool(connection, key, b).await;
+        set_bool(connection, key, b, system_id).await;
     } else if CHOICES.keys().any(|&s| s == key) {
         if CHOICES.get(key).unwrap().contains(&value) {
-            set_string(connection, key, value).await;
+            set_string(connection, key, value, system_id).await;
         } else {
-            println!("Valid choices: {:?}", CHOICES.get(key).unwrap());
+            bail!("Valid choices: {:?}", CHOICES.get(key).unwrap());
         }
     } else if INTEGERS.keys().any(|&i| i == key) {
         let i: usize = try_with!(FromStr::from_str(value), "Failed to parse integer");
         if INTEGERS.get(key).unwrap()[0] <= i && i <= INTEGERS.get(key).unwrap()[1] {
-            set_integer(connection, key, i).await;
+            set_integer(connection, key, i, system_id).await;
         } else {
-            println!("Valid range: {:?}", INTEGERS.get(key).unwrap());
+            bail!("Valid range: {:?}", INTEGERS.get(key).unwrap());
+        }
+    } else if SECRETS.contains(&key) && !value.is_empty() {
+        cfg_if! {
+            if #[cfg(feature = "server")] {
+                set_string(connection, key, &hash_secret(value)?, system_id).await;
+            } else {
+                bail!("Secrets are only used by the server, please enable the server feature");
+            }
         }
+    } else if STRINGS.contains(&key) {
+        set_string(connection, key, value, system_id).await;
     } else if LISTS.contains(&key) {
-        println!("Lists can't be set directly, please use ADD or REMOVE instead");
+        bail!("Lists can't be set directly, please use ADD or REMOVE instead");
     } else {
-        println!("Unsupported setting");
+        bail!("Unsupported setting");
     }
     Ok(())
 }
 
-pub async fn get_bool(connection: &mut SqliteConnection, key: &str) -> bool {
+fn mask_secret(key: &str, value: Option<String>) -> String {
+    match value {
+        Some(value) if SECRETS.contains(&key) && !value.is_empty() => SECRET_MASK.to_owned(),
+        value => value.unwrap_or_default(),
+    }
+}
+
+// salted argon2id, formatted as a PHC string that records the cost, so that it can be raised later
+#[cfg(feature = "server")]
+pub fn hash_secret(secret: &str) -> SimpleResult<String> {
+    let params = try_with!(
+        Params::new(
+            SECRET_MEMORY_COST,
+            SECRET_TIME_COST,
+            SECRET_PARALLELISM,
+            None
+        ),
+        "Failed to set secret hashing parameters"
+    );
+    let salt = SaltString::generate(&mut OsRng);
+    let hashed_secret = try_with!(
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(secret.as_bytes(), &salt),
+        "Failed to hash secret"
+    );
+    Ok(hashed_secret.to_string())
+}
+
+#[cfg(feature = "server")]
+pub fn is_hashed_secret(value: &str) -> bool {
+    matches!(PasswordHash::new(value), Ok(hash) if hash.algorithm == argon2::ARGON2ID_IDENT)
+}
+
+// the cost is read from the hash and the comparison takes the same time wherever the hashes differ
+#[cfg(feature = "server")]
+pub fn verify_secret(secret: &str, hashed_secret: &str) -> bool {
+    match PasswordHash::new(hashed_secret) {
+        Ok(hash) => Argon2::default()
+            .verify_password(secret.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+async fn unset_setting(connection: &mut SqliteConnection, key: &str, system_id: i64) {
+    match find_system_setting_by_key_and_system_id(connection, key, system_id).await {
+        Some(system_setting) => delete_system_setting_by_id(connection, system_setting.id).await,
+        None => println!("Setting not overridden"),
+    }
+}
+
+// system overrides take precedence over global settings
+async fn get_value(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: Option<i64>,
+) -> Option<Option<String>> {
+    if let Some(system_id) = system_id {
+        if let Some(system_setting) =
+            find_system_setting_by_key_and_system_id(connection, key, system_id).await
+        {
+            return Some(system_setting.value);
+        }
+    }
     find_setting_by_key(connection, key)
+        .await
+        .map(|setting| setting.value)
+}
+
+async fn set_value(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: Option<String>,
+    system_id: Option<i64>,
+) {
+    match system_id {
+        Some(system_id) => {
+            match find_system_setting_by_key_and_system_id(connection, key, system_id).await {
+                Some(system_setting) => {
+                    update_system_setting(connection, system_setting.id, value).await
+                }
+                None => create_system_setting(connection, system_id, key, value).await,
+            }
+        }
+        None => match find_setting_by_key(connection, key).await {
+            Some(setting) => update_setting(connection, setting.id, value).await,
+            None => create_setting(connection, key, value).await,
+        },
+    };
+}
+
+pub async fn get_bool(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: Option<i64>,
+) -> bool {
+    get_value(connection, key, system_id)
         .await
         .unwrap()
-        .value
         .unwrap()
         .parse()
         .unwrap()
 }
 
-async fn set_bool(connection: &mut SqliteConnection, key: &str, value: bool) {
-    let setting = find_setting_by_key(connection, key).await;
-    let value = value.to_string();
-    match setting {
-        Some(setting) => update_setting(connection, setting.id, Some(value)).await,
-        None => create_setting(connection, key, Some(value)).await,
-    };
+async fn set_bool(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: bool,
+    system_id: Option<i64>,
+) {
+    set_value(connection, key, Some(value.to_string()), system_id).await;
 }
 
-pub async fn get_integer(connection: &mut SqliteConnection, key: &str) -> usize {
-    find_setting_by_key(connection, key)
+pub async fn get_integer(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: Option<i64>,
+) -> usize {
+    get_value(connection, key, system_id)
         .await
         .unwrap()
-        .value
         .unwrap()
         .parse()
         .unwrap()
 }
 
-async fn set_integer(connection: &mut SqliteConnection, key: &str, value: usize) {
-    let setting = find_setting_by_key(connection, key).await;
-    let value = value.to_string();
-    match setting {
-        Some(setting) => update_setting(connection, setting.id, Some(value)).await,
-        None => create_setting(connection, key, Some(value)).await,
-    };
+async fn set_integer(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: usize,
+    system_id: Option<i64>,
+) {
+    set_value(connection, key, Some(value.to_string()), system_id).await;
 }
 
-pub async fn get_list(connection: &mut SqliteConnection, key: &str) -> Vec<String> {
-    match find_setting_by_key(connection, key).await {
-        Some(setting) => match setting.value {
-            Some(value) => value.split(',').map(|s| s.to_owned()).collect(),
-            None => Vec::new(),
-        },
-        None => Vec::new(),
+pub async fn get_list(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: Option<i64>,
+) -> Vec<String> {
+    match get_value(connection, key, system_id).await {
+        Some(Some(value)) => value.split(',').map(|s| s.to_owned()).collect(),
+        _ => Vec::new(),
     }
 }
 
-pub async fn add_to_list(connection: &mut SqliteConnection, key: &str, value: &str) {
+pub async fn add_to_list(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: &str,
+    system_id: Option<i64>,
+) {
     if LISTS.contains(&key) {
-        let mut list = get_list(connection, key).await;
+        let mut list = get_list(connection, key, system_id).await;
         if !list.contains(&String::from(value)) {
             list.push(value.to_owned());
-            set_list(connection, key, &list).await;
+            set_list(connection, key, &list, system_id).await;
         } else {
             println!("Value already in list");
         }
@@ -323,12 +563,17 @@ pub async fn add_to_list(connection: &mut SqliteConnection, key: &str, value: &s
     }
 }
 
-pub async fn remove_from_list(connection: &mut SqliteConnection, key: &str, value: &str) {
+pub async fn remove_from_list(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: &str,
+    system_id: Option<i64>,
+) {
     if LISTS.contains(&key) {
-        let mut list = get_list(connection, key).await;
+        let mut list = get_list(connection, key, system_id).await;
         if list.contains(&String::from(value)) {
             list.remove(list.iter().position(|v| v == value).unwrap());
-            set_list(connection, key, &list).await;
+            set_list(connection, key, &list, system_id).await;
         } else {
             println!("Value not in list");
         }
@@ -337,17 +582,18 @@ pub async fn remove_from_list(connection: &mut SqliteConnection, key: &str, valu
     }
 }
 
-async fn set_list(connection: &mut SqliteConnection, key: &str, value: &[String]) {
-    let setting = find_setting_by_key(connection, key).await;
+async fn set_list(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: &[String],
+    system_id: Option<i64>,
+) {
     let value = if value.is_empty() {
         None
     } else {
         Some(value.join(","))
     };
-    match setting {
-        Some(setting) => update_setting(connection, setting.id, value).await,
-        None => create_setting(connection, key, value).await,
-    };
+    set_value(connection, key, value, system_id).await;
 }
 
 pub async fn get_directory(connection: &mut SqliteConnection, key: &str) -> Option<PathBuf> {
@@ -365,28 +611,41 @@ pub async fn set_directory<P: AsRef<Path>>(
     key: &str,
     value: &P,
 ) {
-    let setting = find_setting_by_key(connection, key).await;
     let value = value.as_ref().as_os_str().to_str().unwrap().to_owned();
-    match setting {
-        Some(setting) => update_setting(connection, setting.id, Some(value)).await,
-        None => create_setting(connection, key, Some(value)).await,
-    };
+    set_value(connection, key, Some(value), None).await;
 }
 
-pub async fn get_string(connection: &mut SqliteConnection, key: &str) -> String {
-    find_setting_by_key(connection, key)
+pub async fn get_string(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: Option<i64>,
+) -> String {
+    get_value(connection, key, system_id)
         .await
         .unwrap()
-        .value
         .unwrap()
 }
 
-async fn set_string(connection: &mut SqliteConnection, key: &str, value: &str) {
-    let setting = find_setting_by_key(connection, key).await;
-    match setting {
-        Some(setting) => update_setting(connection, setting.id, Some(value.to_string())).await,
-        None => create_setting(connection, key, Some(value.to_string())).await,
-    };
+// empty strings are considered unset
+#[cfg(feature = "server")]
+pub async fn get_optional_string(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: Option<i64>,
+) -> Option<String> {
+    get_value(connection, key, system_id)
+        .await
+        .flatten()
+        .filter(|value| !value.is_empty())
+}
+
+async fn set_string(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: &str,
+    system_id: Option<i64>,
+) {
+    set_value(connection, key, Some(value.to_owned()), system_id).await;
 }
 
 cfg_if::cfg_if! {
@@ -465,6 +724,14 @@ cfg_if::cfg_if! {
     }
 }
 
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
 #[cfg(test)]
 mod test_add_to_list;
 #[cfg(test)]
@@ -476,8 +743,12 @@ mod test_directory;
 #[cfg(test)]
 mod test_list;
 #[cfg(test)]
+mod test_list_system_override;
+#[cfg(test)]
 mod test_remove_from_list;
 #[cfg(test)]
 mod test_remove_from_list_does_not_exist;
+#[cfg(all(test, feature = "server"))]
+mod test_secret;
 #[cfg(test)]
 mod test_set_new_directory_when_old_is_missing;
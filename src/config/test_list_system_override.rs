@@ -0,0 +1,42 @@
+use super::super::import_dats;
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+    let system = find_systems(&mut connection).await.remove(0);
+
+    let key = "REGIONS_ONE";
+    set_list(&mut connection, key, &[String::from("US")], None).await;
+
+    // when
+    add_to_list(&mut connection, key, "JP", Some(system.id)).await;
+
+    // then
+    let list = get_list(&mut connection, key, None).await;
+    assert_eq!(list, vec![String::from("US")]);
+    let list = get_list(&mut connection, key, Some(system.id)).await;
+    assert_eq!(list, vec![String::from("US"), String::from("JP")]);
+
+    unset_setting(&mut connection, key, system.id).await;
+    let list = get_list(&mut connection, key, Some(system.id)).await;
+    assert_eq!(list, vec![String::from("US")]);
+}
@@ -0,0 +1,45 @@
+use super::*;
+use tempfile::NamedTempFile;
+
+#[async_std::test]
+async fn test() {
+    // given
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let key = "SERVER_PASSWORD";
+
+    // when
+    set_setting(&mut connection, &Progress::hidden(), key, "password", None)
+        .await
+        .unwrap();
+    let value = get_value(&mut connection, key, None)
+        .await
+        .flatten()
+        .unwrap();
+
+    // then
+    assert!(is_hashed_secret(&value));
+    assert!(verify_secret("password", &value));
+    assert!(!verify_secret("wrong", &value));
+    assert!(!verify_secret("password", "password"));
+    assert!(value.starts_with(&format!(
+        "$argon2id$v=19$m={},t={},p={}$",
+        SECRET_MEMORY_COST, SECRET_TIME_COST, SECRET_PARALLELISM
+    )));
+    assert_eq!(mask_secret(key, Some(value)), SECRET_MASK);
+
+    // hashes keep working once the cost is raised
+    let salt = SaltString::generate(&mut OsRng);
+    let older_value = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(1024, 1, 1, None).unwrap(),
+    )
+    .hash_password(b"password", &salt)
+    .unwrap()
+    .to_string();
+    assert!(is_hashed_secret(&older_value));
+    assert!(verify_secret("password", &older_value));
+}
@@ -1,30 +1,41 @@
 #[cfg(feature = "chd")]
 use super::chdman;
+#[cfg(feature = "chd")]
+use super::check_roms::check_chd;
+#[cfg(feature = "cso")]
+use super::check_roms::check_cso;
+#[cfg(feature = "rvz")]
+use super::check_roms::check_dolphin_image;
+use super::checksum::*;
 use super::config::*;
+#[cfg(feature = "cso")]
+use super::cso;
 use super::database::*;
 #[cfg(feature = "rvz")]
 use super::dolphin;
-#[cfg(feature = "cso")]
-use super::maxcso;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::sevenzip;
+use super::staging::*;
 use super::util::*;
 use super::SimpleResult;
 use async_std::path::{Path, PathBuf};
 use cfg_if::cfg_if;
 use clap::builder::PossibleValuesParser;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::{HumanBytes, ProgressBar};
+use indicatif::HumanBytes;
 use lazy_static::lazy_static;
 use rayon::prelude::*;
 use sqlx::sqlite::SqliteConnection;
 use std::collections::HashMap;
+use std::fs;
+use std::io::prelude::*;
 use std::mem::drop;
 use std::str::FromStr;
 
 lazy_static! {
-    static ref ALL_FORMATS: Vec<&'static str> = {
+    pub static ref ALL_FORMATS: Vec<&'static str> = {
         let mut all_formats = vec!["ORIGINAL", "7Z", "ZIP"];
         cfg_if! {
             if #[cfg(feature = "chd")] {
@@ -38,13 +49,15 @@ lazy_static! {
         }
         cfg_if! {
             if #[cfg(feature = "rvz")] {
+                all_formats.push("GCZ");
                 all_formats.push("RVZ");
+                all_formats.push("WIA");
             }
         }
         all_formats
     };
 }
-const ARCADE_FORMATS: &[&str] = &["ORIGINAL", "ZIP"];
+pub const ARCADE_FORMATS: &[&str] = &["ORIGINAL", "ZIP"];
 
 pub fn subcommand() -> Command {
     Command::new("convert-roms")
@@ -82,28 +95,67 @@ pub fn subcommand() -> Command {
                 .required(false)
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("SET_DEFAULT")
+                .long("set-default")
+                .help("Save the format as the preferred format of the selected systems")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["NAME", "DIFF", "STRIP_HEADER", "ADD_HEADER"]),
+        )
+        .arg(
+            Arg::new("STRIP_HEADER")
+                .long("strip-header")
+                .help("Remove headers from ROM files")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["FORMAT", "ADD_HEADER"]),
+        )
+        .arg(
+            Arg::new("ADD_HEADER")
+                .long("add-header")
+                .help("Restore headers previously removed from ROM files")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("FORMAT"),
+        )
 }
 
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let systems = prompt_for_systems(connection, None, false, matches.get_flag("ALL")).await?;
     let game_name = matches.get_one::<String>("NAME");
-    let format = match matches.get_one::<String>("FORMAT") {
-        Some(format) => format.as_str().to_owned(),
-        None => ALL_FORMATS
-            .get(select(&ALL_FORMATS, "Please select a format", None, None)?)
-            .map(|&s| s.to_owned())
-            .unwrap(),
-    };
+    let mut format = matches.get_one::<String>("FORMAT").map(String::to_owned);
     let diff = matches.get_flag("DIFF");
+    let set_default = matches.get_flag("SET_DEFAULT");
+    let strip_header = matches.get_flag("STRIP_HEADER");
+    let add_header = matches.get_flag("ADD_HEADER");
 
     for system in systems {
         progress_bar.println(format!("Processing \"{}\"", system.name));
 
-        if system.arcade && !ARCADE_FORMATS.contains(&format.as_str()) {
+        // fall back to the system's preferred format, then prompt once for the remaining systems
+        let system_format = match (&format, &system.preferred_format) {
+            _ if strip_header || add_header => String::new(),
+            (Some(format), _) => format.to_owned(),
+            (None, Some(preferred_format)) if !set_default => preferred_format.to_owned(),
+            (None, _) => {
+                let selected_format = ALL_FORMATS
+                    .get(select(&ALL_FORMATS, "Please select a format", None, None)?)
+                    .map(|&s| s.to_owned())
+                    .unwrap();
+                format = Some(selected_format.clone());
+                selected_format
+            }
+        };
+
+        if system.arcade
+            && !system_format.is_empty()
+            && !ARCADE_FORMATS.contains(&system_format.as_str())
+        {
             progress_bar.println(format!(
                 "Only {:?} are supported for arcade systems",
                 ARCADE_FORMATS
@@ -111,6 +163,13 @@ pub async fn main(
             continue;
         }
 
+        if set_default {
+            update_system_preferred_format(connection, system.id, Some(&system_format)).await;
+            progress_bar.println(format!("Preferred format set to {}", system_format));
+            progress_bar.println("");
+            continue;
+        }
+
         let games = match game_name {
             Some(game_name) => {
                 let games = find_games_with_romfiles_by_name_and_system_id(
@@ -131,136 +190,203 @@ pub async fn main(
             continue;
         }
 
-        let roms = find_roms_with_romfile_by_game_ids(
-            connection,
-            &games.iter().map(|game| game.id).collect::<Vec<i64>>(),
-        )
-        .await;
-        let romfiles = find_romfiles_by_ids(
-            connection,
-            roms.iter()
-                .map(|rom| rom.romfile_id.unwrap())
-                .collect::<Vec<i64>>()
-                .as_slice(),
-        )
-        .await;
+        if strip_header || add_header {
+            let (roms_by_game_id, romfiles_by_id) =
+                find_roms_and_romfiles_by_games(connection, &games).await;
+            convert_headers(
+                connection,
+                progress_bar,
+                &system,
+                roms_by_game_id,
+                romfiles_by_id,
+                diff,
+                strip_header,
+            )
+            .await?;
+        } else {
+            convert_games(
+                connection,
+                progress_bar,
+                &system,
+                games,
+                &system_format,
+                diff,
+            )
+            .await?;
+        }
 
-        let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
-        roms.into_iter().for_each(|rom| {
-            let group = roms_by_game_id.entry(rom.game_id).or_insert_with(Vec::new);
-            group.push(rom);
-        });
-        let games_by_id: HashMap<i64, Game> =
-            games.into_iter().map(|game| (game.id, game)).collect();
-        let romfiles_by_id: HashMap<i64, Romfile> = romfiles
-            .into_iter()
-            .map(|romfile| (romfile.id, romfile))
-            .collect();
+        progress_bar.println("");
+    }
 
-        match format.as_str() {
-            "ORIGINAL" => {
-                to_original(
-                    connection,
-                    progress_bar,
-                    &system,
-                    roms_by_game_id,
-                    romfiles_by_id,
-                )
-                .await?
-            }
-            "7Z" => {
-                let compression_level = get_integer(connection, "SEVENZIP_COMPRESSION_LEVEL").await;
-                let solid: bool = get_bool(connection, "SEVENZIP_SOLID_COMPRESSION").await;
-                to_archive(
-                    connection,
-                    progress_bar,
-                    sevenzip::ArchiveType::Sevenzip,
-                    &system,
-                    roms_by_game_id,
-                    games_by_id,
-                    romfiles_by_id,
-                    diff,
-                    compression_level,
-                    solid,
-                )
-                .await?
-            }
-            "ZIP" => {
-                let compression_level = get_integer(connection, "ZIP_COMPRESSION_LEVEL").await;
-                to_archive(
-                    connection,
-                    progress_bar,
-                    sevenzip::ArchiveType::Zip,
-                    &system,
-                    roms_by_game_id,
-                    games_by_id,
-                    romfiles_by_id,
-                    diff,
-                    compression_level,
-                    false,
-                )
-                .await?
-            }
-            "CHD" => {
-                cfg_if! {
-                    if #[cfg(feature = "chd")] {
-                        to_chd(
-                            connection,
-                            progress_bar,
-                            roms_by_game_id,
-                            romfiles_by_id,
-                            diff,
-                        )
-                        .await?
-                    }
+    Ok(())
+}
+
+pub async fn convert_games(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: &System,
+    games: Vec<Game>,
+    format: &str,
+    diff: bool,
+) -> SimpleResult<()> {
+    let (roms_by_game_id, romfiles_by_id) =
+        find_roms_and_romfiles_by_games(connection, &games).await;
+    let games_by_id: HashMap<i64, Game> = games.into_iter().map(|game| (game.id, game)).collect();
+
+    match format {
+        "ORIGINAL" => {
+            to_original(
+                connection,
+                progress_bar,
+                system,
+                roms_by_game_id,
+                romfiles_by_id,
+            )
+            .await?
+        }
+        "7Z" => {
+            let compression_level =
+                get_integer(connection, "SEVENZIP_COMPRESSION_LEVEL", Some(system.id)).await;
+            let solid: bool =
+                get_bool(connection, "SEVENZIP_SOLID_COMPRESSION", Some(system.id)).await;
+            to_archive(
+                connection,
+                progress_bar,
+                sevenzip::ArchiveType::Sevenzip,
+                system,
+                roms_by_game_id,
+                games_by_id,
+                romfiles_by_id,
+                diff,
+                compression_level,
+                solid,
+            )
+            .await?
+        }
+        "ZIP" => {
+            let compression_level =
+                get_integer(connection, "ZIP_COMPRESSION_LEVEL", Some(system.id)).await;
+            to_archive(
+                connection,
+                progress_bar,
+                sevenzip::ArchiveType::Zip,
+                system,
+                roms_by_game_id,
+                games_by_id,
+                romfiles_by_id,
+                diff,
+                compression_level,
+                false,
+            )
+            .await?
+        }
+        "CHD" => {
+            cfg_if! {
+                if #[cfg(feature = "chd")] {
+                    // converted files don't exist in dry-run mode
+                    let verify = get_bool(connection, "CHD_VERIFY", Some(system.id)).await && !is_dry_run();
+                    let chd_type = chdman::get_system_chd_type(
+                        system,
+                        &ChdType::from_str(&get_string(connection, "CHD_TYPE", Some(system.id)).await).unwrap(),
+                    );
+                    to_chd(
+                        connection,
+                        progress_bar,
+                        roms_by_game_id,
+                        romfiles_by_id,
+                        diff,
+                        verify,
+                        &chd_type,
+                    )
+                    .await?
                 }
             }
-            "CSO" => {
-                cfg_if! {
-                    if #[cfg(feature = "cso")] {
-                        to_cso(
-                            connection,
-                            progress_bar,
-                            roms_by_game_id,
-                            romfiles_by_id,
-                            diff,
-                        )
-                        .await?
-                    }
+        }
+        "CSO" => {
+            cfg_if! {
+                if #[cfg(feature = "cso")] {
+                    let verify = get_bool(connection, "CSO_VERIFY", Some(system.id)).await && !is_dry_run();
+                    to_cso(
+                        connection,
+                        progress_bar,
+                        roms_by_game_id,
+                        romfiles_by_id,
+                        diff,
+                        verify,
+                    )
+                    .await?
                 }
             }
-            "RVZ" => {
-                cfg_if! {
-                    if #[cfg(feature = "rvz")] {
-                        let compression_algorithm = RvzCompressionAlgorithm::from_str(&get_string(connection, "RVZ_COMPRESSION_ALGORITHM").await).unwrap();
-                        let compression_level = get_integer(connection, "RVZ_COMPRESSION_LEVEL").await;
-                        let block_size = get_integer(connection, "RVZ_BLOCK_SIZE").await;
-                        to_rvz(
-                            connection,
-                            progress_bar,
-                            roms_by_game_id,
-                            romfiles_by_id,
-                            diff,
-                            &compression_algorithm,
+        }
+        "GCZ" | "RVZ" | "WIA" => {
+            cfg_if! {
+                if #[cfg(feature = "rvz")] {
+                    let verify = get_bool(connection, "RVZ_VERIFY", Some(system.id)).await && !is_dry_run();
+                    let compression_algorithm = RvzCompressionAlgorithm::from_str(&get_string(connection, "RVZ_COMPRESSION_ALGORITHM", Some(system.id)).await).unwrap();
+                    let compression_level = get_integer(connection, "RVZ_COMPRESSION_LEVEL", Some(system.id)).await;
+                    let block_size = get_integer(connection, "RVZ_BLOCK_SIZE", Some(system.id)).await;
+                    let dolphin_format = match format {
+                        "GCZ" => dolphin::DolphinFormat::Gcz,
+                        "WIA" => dolphin::DolphinFormat::Wia,
+                        _ => dolphin::DolphinFormat::Rvz {
+                            compression_algorithm: &compression_algorithm,
                             compression_level,
                             block_size,
-                        )
-                        .await?
-                    }
+                        },
+                    };
+                    to_dolphin_image(
+                        connection,
+                        progress_bar,
+                        roms_by_game_id,
+                        romfiles_by_id,
+                        diff,
+                        verify,
+                        &dolphin_format,
+                    )
+                    .await?
                 }
             }
-            _ => bail!("Not supported"),
         }
-
-        progress_bar.println("");
+        _ => bail!("Not supported"),
     }
 
     Ok(())
 }
 
+async fn find_roms_and_romfiles_by_games(
+    connection: &mut SqliteConnection,
+    games: &[Game],
+) -> (HashMap<i64, Vec<Rom>>, HashMap<i64, Romfile>) {
+    let roms = find_roms_with_romfile_by_game_ids(
+        connection,
+        &games.iter().map(|game| game.id).collect::<Vec<i64>>(),
+    )
+    .await;
+    let romfiles = find_romfiles_by_ids(
+        connection,
+        roms.iter()
+            .map(|rom| rom.romfile_id.unwrap())
+            .collect::<Vec<i64>>()
+            .as_slice(),
+    )
+    .await;
+
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    roms.into_iter().for_each(|rom| {
+        let group = roms_by_game_id.entry(rom.game_id).or_insert_with(Vec::new);
+        group.push(rom);
+    });
+    let romfiles_by_id: HashMap<i64, Romfile> = romfiles
+        .into_iter()
+        .map(|romfile| (romfile.id, romfile))
+        .collect();
+
+    (roms_by_game_id, romfiles_by_id)
+}
+
 async fn to_archive(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     archive_type: sevenzip::ArchiveType,
     system: &System,
     mut roms_by_game_id: HashMap<i64, Vec<Rom>>,
@@ -271,6 +397,11 @@ async fn to_archive(
     solid: bool,
 ) -> SimpleResult<()> {
     let tmp_directory = create_tmp_directory(connection).await?;
+    let header = find_header_by_system_id(connection, system.id).await;
+    let rules = match &header {
+        Some(header) => find_rules_by_header_id(connection, header.id).await,
+        None => Vec::new(),
+    };
 
     // remove same type archives
     roms_by_game_id.retain(|_, roms| {
@@ -304,11 +435,8 @@ async fn to_archive(
     let (csos, roms_by_game_id): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
         roms_by_game_id.into_iter().partition(|(_, roms)| {
             roms.par_iter().any(|rom| {
-                romfiles_by_id
-                    .get(&rom.romfile_id.unwrap())
-                    .unwrap()
-                    .path
-                    .ends_with(CSO_EXTENSION)
+                let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+                romfile.path.ends_with(CSO_EXTENSION) || romfile.path.ends_with(ZSO_EXTENSION)
             })
         });
     cfg_if! {
@@ -317,20 +445,19 @@ async fn to_archive(
         }
     }
 
-    // partition RVZs
-    let (rvzs, roms_by_game_id): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
+    // partition RVZs, WBFSs, WIAs and GCZs
+    let (dolphin_images, roms_by_game_id): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
         roms_by_game_id.into_iter().partition(|(_, roms)| {
             roms.par_iter().any(|rom| {
-                romfiles_by_id
-                    .get(&rom.romfile_id.unwrap())
-                    .unwrap()
-                    .path
-                    .ends_with(RVZ_EXTENSION)
+                let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+                DOLPHIN_EXTENSIONS
+                    .iter()
+                    .any(|extension| romfile.path.ends_with(extension))
             })
         });
     cfg_if! {
         if #[cfg(not(feature = "rvz"))] {
-            drop(rvzs)
+            drop(dolphin_images)
         }
     }
 
@@ -353,6 +480,7 @@ async fn to_archive(
     cfg_if! {
         if #[cfg(feature = "chd")] {
             for roms in chds.values() {
+                let staging_directory = create_staging_directory(connection).await?;
                 let mut transaction = begin_transaction(connection).await;
 
                 if roms.len() == 1 {
@@ -364,8 +492,9 @@ async fn to_archive(
                         &tmp_directory.path(),
                     )
                     .await?;
-                    let mut archive_path = Path::new(&romfile.path).to_path_buf();
-                    archive_path.set_extension(
+                    let mut staged_path = Path::new(staging_directory.path())
+                        .join(Path::new(&romfile.path).file_name().unwrap());
+                    staged_path.set_extension(
                         match archive_type {
                             sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                             sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
@@ -373,19 +502,32 @@ async fn to_archive(
                     );
                     sevenzip::add_files_to_archive(
                         progress_bar,
-                        &archive_path,
+                        &staged_path,
                         &[bin_path.file_name().unwrap().to_str().unwrap()],
                         &tmp_directory.path(),
                         compression_level,
                         solid,
                     )?;
+                    let archive_path = get_final_path(&staged_path, &romfile.path);
+                    swap_staged_files(
+                        progress_bar,
+                        &staging_directory,
+                        &[&staged_path],
+                        &[&archive_path],
+                        &[&romfile.path],
+                        &[rom],
+                        &header,
+                        &rules,
+                    )
+                    .await?;
                     update_romfile(
                         &mut transaction,
                         romfile.id,
                         archive_path.as_os_str().to_str().unwrap(),
-                        archive_path.metadata().await.unwrap().len(),
+                        get_file_size(&archive_path).await,
                     )
                     .await;
+                    journal_create(&mut transaction, &archive_path, Some(romfile.id)).await;
 
                     if diff {
                         print_diff(
@@ -397,7 +539,16 @@ async fn to_archive(
                         .await?;
                     }
 
-                    remove_file(progress_bar, &romfile.path, false).await?;
+                    journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+
+                    commit_transaction(transaction).await;
+                    remove_original_files(
+                        connection,
+                        progress_bar,
+                        &[&romfile.path],
+                        &[&archive_path],
+                    )
+                    .await?;
                 } else {
                     let (cue_roms, bin_roms): (Vec<&Rom>, Vec<&Rom>) = roms
                         .into_par_iter()
@@ -408,8 +559,9 @@ async fn to_archive(
                         .get(&bin_roms.get(0).unwrap().romfile_id.unwrap())
                         .unwrap();
 
-                    let mut archive_path = Path::new(&chd_romfile.path).to_path_buf();
-                    archive_path.set_extension(match archive_type {
+                    let mut staged_path = Path::new(staging_directory.path())
+                        .join(Path::new(&chd_romfile.path).file_name().unwrap());
+                    staged_path.set_extension(match archive_type {
                         sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                         sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
                     });
@@ -429,9 +581,9 @@ async fn to_archive(
 
                     sevenzip::add_files_to_archive(
                         progress_bar,
-                        &archive_path,
+                        &staged_path,
                         &[&cue_rom.name],
-                        &archive_path.parent().unwrap(),
+                        &Path::new(&cue_romfile.path).parent().unwrap(),
                         compression_level,
                         solid,
                     )?;
@@ -441,19 +593,32 @@ async fn to_archive(
                         .collect();
                     sevenzip::add_files_to_archive(
                         progress_bar,
-                        &archive_path,
+                        &staged_path,
                         &bin_names,
                         &tmp_directory.path(),
                         compression_level,
                         solid,
                     )?;
+                    let archive_path = get_final_path(&staged_path, &chd_romfile.path);
+                    swap_staged_files(
+                        progress_bar,
+                        &staging_directory,
+                        &[&staged_path],
+                        &[&archive_path],
+                        &[&cue_romfile.path, &chd_romfile.path],
+                        &roms.iter().collect::<Vec<&Rom>>(),
+                        &header,
+                        &rules,
+                    )
+                    .await?;
                     update_romfile(
                         &mut transaction,
                         chd_romfile.id,
                         archive_path.as_os_str().to_str().unwrap(),
-                        archive_path.metadata().await.unwrap().len(),
+                        get_file_size(&archive_path).await,
                     )
                     .await;
+                    journal_create(&mut transaction, &archive_path, Some(chd_romfile.id)).await;
                     update_rom_romfile(&mut transaction, cue_rom.id, Some(chd_romfile.id)).await;
                     delete_romfile_by_id(&mut transaction, cue_romfile.id).await;
 
@@ -467,11 +632,18 @@ async fn to_archive(
                         .await?;
                     }
 
-                    remove_file(progress_bar, &cue_romfile.path, false).await?;
-                    remove_file(progress_bar, &chd_romfile.path, false).await?;
-                }
+                    journal_delete(&mut transaction, &cue_romfile.path, None).await;
+                    journal_delete(&mut transaction, &chd_romfile.path, Some(chd_romfile.id)).await;
 
-                commit_transaction(transaction).await;
+                    commit_transaction(transaction).await;
+                    remove_original_files(
+                        connection,
+                        progress_bar,
+                        &[&cue_romfile.path, &chd_romfile.path],
+                        &[&archive_path],
+                    )
+                    .await?;
+                }
             }
         }
     }
@@ -480,13 +652,15 @@ async fn to_archive(
     cfg_if! {
         if #[cfg(feature = "cso")] {
             for roms in csos.values() {
+                let staging_directory = create_staging_directory(connection).await?;
                 let mut transaction = begin_transaction(connection).await;
 
                 let rom = roms.get(0).unwrap();
                 let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-                let iso_path = maxcso::extract_cso(progress_bar, &romfile.path, &tmp_directory.path())?;
-                let mut archive_path = Path::new(&romfile.path).to_path_buf();
-                archive_path.set_extension(
+                let iso_path = cso::extract_cso(progress_bar, &romfile.path, &tmp_directory.path())?;
+                let mut staged_path = Path::new(staging_directory.path())
+                    .join(Path::new(&romfile.path).file_name().unwrap());
+                staged_path.set_extension(
                     match archive_type {
                         sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                         sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
@@ -495,19 +669,32 @@ async fn to_archive(
 
                 sevenzip::add_files_to_archive(
                     progress_bar,
-                    &archive_path,
+                    &staged_path,
                     &[iso_path.file_name().unwrap().to_str().unwrap()],
                     &tmp_directory.path(),
                     compression_level,
                     solid,
                 )?;
+                let archive_path = get_final_path(&staged_path, &romfile.path);
+                swap_staged_files(
+                    progress_bar,
+                    &staging_directory,
+                    &[&staged_path],
+                    &[&archive_path],
+                    &[&romfile.path],
+                    &[rom],
+                    &header,
+                    &rules,
+                )
+                .await?;
                 update_romfile(
                     &mut transaction,
                     romfile.id,
                     archive_path.as_os_str().to_str().unwrap(),
-                    archive_path.metadata().await.unwrap().len(),
+                    get_file_size(&archive_path).await,
                 )
                 .await;
+                journal_create(&mut transaction, &archive_path, Some(romfile.id)).await;
 
                 if diff {
                     print_diff(
@@ -519,24 +706,33 @@ async fn to_archive(
                     .await?;
                 }
 
-                remove_file(progress_bar, &romfile.path, false).await?;
+                journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
 
                 commit_transaction(transaction).await;
+                remove_original_files(
+                    connection,
+                    progress_bar,
+                    &[&romfile.path],
+                    &[&archive_path],
+                )
+                .await?;
             }
         }
     }
 
-    // convert RVZs
+    // convert RVZs, WBFSs, WIAs and GCZs
     cfg_if! {
         if #[cfg(feature = "rvz")] {
-            for roms in rvzs.values() {
+            for roms in dolphin_images.values() {
+                let staging_directory = create_staging_directory(connection).await?;
                 let mut transaction = begin_transaction(connection).await;
 
                 let rom = roms.get(0).unwrap();
                 let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-                let iso_path = dolphin::extract_rvz(progress_bar, &romfile.path, &tmp_directory.path())?;
-                let mut archive_path = Path::new(&romfile.path).to_path_buf();
-                archive_path.set_extension(
+                let iso_path = dolphin::extract_image(progress_bar, &romfile.path, &tmp_directory.path())?;
+                let mut staged_path = Path::new(staging_directory.path())
+                    .join(Path::new(&romfile.path).file_name().unwrap());
+                staged_path.set_extension(
                     match archive_type {
                         sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                         sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
@@ -545,19 +741,32 @@ async fn to_archive(
 
                 sevenzip::add_files_to_archive(
                     progress_bar,
-                    &archive_path,
+                    &staged_path,
                     &[iso_path.file_name().unwrap().to_str().unwrap()],
                     &tmp_directory.path(),
                     compression_level,
                     solid,
                 )?;
+                let archive_path = get_final_path(&staged_path, &romfile.path);
+                swap_staged_files(
+                    progress_bar,
+                    &staging_directory,
+                    &[&staged_path],
+                    &[&archive_path],
+                    &[&romfile.path],
+                    &[rom],
+                    &header,
+                    &rules,
+                )
+                .await?;
                 update_romfile(
                     &mut transaction,
                     romfile.id,
                     archive_path.as_os_str().to_str().unwrap(),
-                    archive_path.metadata().await.unwrap().len(),
+                    get_file_size(&archive_path).await,
                 )
                 .await;
+                journal_create(&mut transaction, &archive_path, Some(romfile.id)).await;
 
                 if diff {
                     print_diff(
@@ -569,48 +778,74 @@ async fn to_archive(
                     .await?;
                 }
 
-                remove_file(progress_bar, &romfile.path, false).await?;
+                journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
 
                 commit_transaction(transaction).await;
+                remove_original_files(
+                    connection,
+                    progress_bar,
+                    &[&romfile.path],
+                    &[&archive_path],
+                )
+                .await?;
             }
         }
     }
 
     // convert archives
     for roms in archives.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
 
         if roms.len() == 1 {
             let rom = roms.get(0).unwrap();
             let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-            let mut archive_path = Path::new(&romfile.path).to_path_buf();
 
             sevenzip::extract_files_from_archive(
                 progress_bar,
-                &archive_path,
+                &romfile.path,
                 &[&rom.name],
                 &tmp_directory.path(),
             )?;
-            remove_file(progress_bar, &archive_path, false).await?;
-            archive_path.set_extension(match archive_type {
+            let mut staged_path = Path::new(staging_directory.path())
+                .join(Path::new(&romfile.path).file_name().unwrap());
+            staged_path.set_extension(match archive_type {
                 sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                 sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
             });
             sevenzip::add_files_to_archive(
                 progress_bar,
-                &archive_path,
+                &staged_path,
                 &[&rom.name],
                 &tmp_directory.path(),
                 compression_level,
                 solid,
             )?;
+            let archive_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&archive_path],
+                &[&romfile.path],
+                &[rom],
+                &header,
+                &rules,
+            )
+            .await?;
             update_romfile(
                 &mut transaction,
                 romfile.id,
                 archive_path.as_os_str().to_str().unwrap(),
-                archive_path.metadata().await.unwrap().len(),
+                get_file_size(&archive_path).await,
             )
             .await;
+            journal_create(&mut transaction, &archive_path, Some(romfile.id)).await;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+
+            commit_transaction(transaction).await;
+            remove_original_files(connection, progress_bar, &[&romfile.path], &[&archive_path])
+                .await?;
         } else {
             let mut romfiles: Vec<&Romfile> = roms
                 .par_iter()
@@ -624,67 +859,98 @@ async fn to_archive(
 
             let rom_names: Vec<&str> = roms.par_iter().map(|rom| rom.name.as_str()).collect();
             let romfile = romfiles.get(0).unwrap();
-            let mut archive_path = Path::new(&romfile.path).to_path_buf();
 
             sevenzip::extract_files_from_archive(
                 progress_bar,
-                &archive_path,
+                &romfile.path,
                 &rom_names,
                 &tmp_directory.path(),
             )?;
-            remove_file(progress_bar, &archive_path, false).await?;
-            archive_path.set_extension(match archive_type {
+            let mut staged_path = Path::new(staging_directory.path())
+                .join(Path::new(&romfile.path).file_name().unwrap());
+            staged_path.set_extension(match archive_type {
                 sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                 sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
             });
             sevenzip::add_files_to_archive(
                 progress_bar,
-                &archive_path,
+                &staged_path,
                 &rom_names,
                 &tmp_directory.path(),
                 compression_level,
                 solid,
             )?;
+            let archive_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&archive_path],
+                &[&romfile.path],
+                &roms.iter().collect::<Vec<&Rom>>(),
+                &header,
+                &rules,
+            )
+            .await?;
             update_romfile(
                 &mut transaction,
                 romfile.id,
                 archive_path.as_os_str().to_str().unwrap(),
-                archive_path.metadata().await.unwrap().len(),
+                get_file_size(&archive_path).await,
             )
             .await;
-        }
+            journal_create(&mut transaction, &archive_path, Some(romfile.id)).await;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
 
-        commit_transaction(transaction).await;
+            commit_transaction(transaction).await;
+            remove_original_files(connection, progress_bar, &[&romfile.path], &[&archive_path])
+                .await?;
+        }
     }
 
     // convert others
     for (game_id, mut roms) in roms_by_game_id {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
 
         if roms.len() == 1 && !system.arcade {
             let rom = roms.get(0).unwrap();
             let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-            let mut archive_path = Path::new(&romfile.path).to_path_buf();
-            archive_path.set_extension(match archive_type {
+            let mut staged_path = Path::new(staging_directory.path())
+                .join(Path::new(&romfile.path).file_name().unwrap());
+            staged_path.set_extension(match archive_type {
                 sevenzip::ArchiveType::Sevenzip => SEVENZIP_EXTENSION,
                 sevenzip::ArchiveType::Zip => ZIP_EXTENSION,
             });
 
             sevenzip::add_files_to_archive(
                 progress_bar,
-                &archive_path,
+                &staged_path,
                 &[&rom.name],
-                &archive_path.parent().unwrap(),
+                &Path::new(&romfile.path).parent().unwrap(),
                 compression_level,
                 solid,
             )?;
+            let archive_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&archive_path],
+                &[&romfile.path],
+                &[rom],
+                &header,
+                &rules,
+            )
+            .await?;
             update_romfile(
                 &mut transaction,
                 romfile.id,
                 archive_path.as_os_str().to_str().unwrap(),
-                archive_path.metadata().await.unwrap().len(),
+                get_file_size(&archive_path).await,
             )
             .await;
+            journal_create(&mut transaction, &archive_path, Some(romfile.id)).await;
 
             if diff {
                 print_diff(
@@ -696,7 +962,11 @@ async fn to_archive(
                 .await?;
             }
 
-            remove_file(progress_bar, &romfile.path, false).await?;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+
+            commit_transaction(transaction).await;
+            remove_original_files(connection, progress_bar, &[&romfile.path], &[&archive_path])
+                .await?;
         } else {
             let game = games_by_id.get(&game_id).unwrap();
             roms = roms
@@ -730,15 +1000,35 @@ async fn to_archive(
                 true => directory.parent().unwrap().join(&archive_name),
                 false => directory.join(&archive_name),
             };
+            let staged_path = Path::new(staging_directory.path()).join(&archive_name);
 
+            // keep the existing content when adding to an archive
+            if archive_path.is_file().await {
+                copy_file(progress_bar, &archive_path, &staged_path, true).await?;
+            }
             sevenzip::add_files_to_archive(
                 progress_bar,
-                &archive_path,
+                &staged_path,
                 &rom_names,
                 &directory,
                 compression_level,
                 solid,
             )?;
+            let old_paths = rom_names
+                .iter()
+                .map(|&rom_name| directory.join(rom_name))
+                .collect::<Vec<PathBuf>>();
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&archive_path],
+                &old_paths,
+                &roms.iter().collect::<Vec<&Rom>>(),
+                &header,
+                &rules,
+            )
+            .await?;
             let archive_romfile_id = match find_romfile_by_path(
                 &mut transaction,
                 archive_path.as_os_str().to_str().unwrap(),
@@ -747,20 +1037,18 @@ async fn to_archive(
             {
                 Some(romfile) => romfile.id,
                 None => {
-                    create_romfile(
+                    let romfile_id = create_romfile(
                         &mut transaction,
                         archive_path.as_os_str().to_str().unwrap(),
-                        archive_path.metadata().await.unwrap().len(),
+                        get_file_size(&archive_path).await,
                     )
-                    .await
+                    .await;
+                    journal_create(&mut transaction, &archive_path, Some(romfile_id)).await;
+                    romfile_id
                 }
             };
 
             if diff {
-                let old_paths = rom_names
-                    .iter()
-                    .map(|&rom_name| directory.join(rom_name))
-                    .collect::<Vec<PathBuf>>();
                 print_diff(
                     progress_bar,
                     &roms.iter().collect::<Vec<&Rom>>(),
@@ -773,17 +1061,17 @@ async fn to_archive(
             for rom in &roms {
                 delete_romfile_by_id(&mut transaction, rom.romfile_id.unwrap()).await;
                 update_rom_romfile(&mut transaction, rom.id, Some(archive_romfile_id)).await;
+                journal_delete(&mut transaction, &directory.join(&rom.name), None).await;
             }
+
+            commit_transaction(transaction).await;
             if system.arcade {
                 remove_directory(progress_bar, &directory, false).await?;
             } else {
-                for rom_name in rom_names {
-                    remove_file(progress_bar, &directory.join(rom_name), false).await?;
-                }
+                remove_original_files(connection, progress_bar, &old_paths, &[&archive_path])
+                    .await?;
             }
         }
-
-        commit_transaction(transaction).await;
     }
 
     Ok(())
@@ -792,10 +1080,12 @@ async fn to_archive(
 #[cfg(feature = "chd")]
 async fn to_chd(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     roms_by_game_id: HashMap<i64, Vec<Rom>>,
     romfiles_by_id: HashMap<i64, Romfile>,
     diff: bool,
+    verify: bool,
+    chd_type: &ChdType,
 ) -> SimpleResult<()> {
     let tmp_directory = create_tmp_directory(connection).await?;
 
@@ -844,11 +1134,8 @@ async fn to_chd(
             let (csos, others): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
                 others.into_iter().partition(|(_, roms)| {
                     roms.par_iter().any(|rom| {
-                        romfiles_by_id
-                            .get(&rom.romfile_id.unwrap())
-                            .unwrap()
-                            .path
-                            .ends_with(CSO_EXTENSION)
+                        let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+                        romfile.path.ends_with(CSO_EXTENSION) || romfile.path.ends_with(ZSO_EXTENSION)
                     })
                 });
         }
@@ -859,6 +1146,7 @@ async fn to_chd(
 
     // convert archives
     for roms in archives.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
 
         let mut romfiles: Vec<&Romfile> = roms
@@ -903,45 +1191,71 @@ async fn to_chd(
                     .ends_with(CUE_EXTENSION)
             });
 
-        let chd_path = match cue_paths.get(0) {
-            Some(cue_path) => chdman::create_chd(
-                progress_bar,
-                cue_path,
-                &Path::new(&romfile.path).parent().unwrap(),
-            )?,
+        let staged_path = match cue_paths.get(0) {
+            Some(cue_path) => {
+                chdman::create_chd(progress_bar, cue_path, &staging_directory.path(), chd_type)?
+            }
             None => chdman::create_chd(
                 progress_bar,
                 extracted_paths.get(0).unwrap(),
-                &Path::new(&romfile.path).parent().unwrap(),
+                &staging_directory.path(),
+                chd_type,
             )?,
         };
+        if verify {
+            try_with!(
+                check_chd(
+                    progress_bar,
+                    &staged_path,
+                    &roms
+                        .iter()
+                        .filter(|rom| !rom.name.ends_with(CUE_EXTENSION))
+                        .collect::<Vec<&Rom>>()
+                ),
+                "Failed to verify {:?}, the original files were left untouched",
+                staged_path.file_name().unwrap()
+            );
+        }
+        let chd_path = get_final_path(&staged_path, &romfile.path);
+        let mut staged_paths = vec![staged_path];
+        let mut new_paths = vec![chd_path.clone()];
+        if let Some(cue_path) = cue_paths.get(0) {
+            let staged_cue_path =
+                Path::new(staging_directory.path()).join(cue_path.file_name().unwrap());
+            copy_file(progress_bar, cue_path, &staged_cue_path, true).await?;
+            new_paths.push(get_final_path(&staged_cue_path, &romfile.path));
+            staged_paths.push(staged_cue_path);
+        }
+        swap_staged_files(
+            progress_bar,
+            &staging_directory,
+            &staged_paths,
+            &new_paths,
+            &[&romfile.path],
+            &roms.iter().collect::<Vec<&Rom>>(),
+            &None,
+            &[],
+        )
+        .await?;
 
         if diff {
-            let mut new_paths = vec![&chd_path];
-            if let Some(cue_path) = cue_paths.get(0) {
-                new_paths.push(cue_path)
-            }
             print_diff(
                 progress_bar,
                 &roms.iter().collect::<Vec<&Rom>>(),
                 &[&romfile.path],
-                &new_paths,
+                &new_paths.iter().collect::<Vec<&PathBuf>>(),
             )
             .await?;
         }
 
-        if let Some(cue_path) = cue_paths.get(0) {
-            let new_cue_path = Path::new(&romfile.path)
-                .parent()
-                .unwrap()
-                .join(cue_path.file_name().unwrap());
-            copy_file(progress_bar, cue_path, &new_cue_path, false).await?;
+        if let Some(new_cue_path) = new_paths.get(1) {
             let cue_romfile_id = create_romfile(
                 &mut transaction,
                 new_cue_path.as_os_str().to_str().unwrap(),
-                new_cue_path.metadata().await.unwrap().len(),
+                get_file_size(&new_cue_path).await,
             )
             .await;
+            journal_create(&mut transaction, &new_cue_path, Some(cue_romfile_id)).await;
             update_rom_romfile(
                 &mut transaction,
                 roms.par_iter()
@@ -957,16 +1271,19 @@ async fn to_chd(
             &mut transaction,
             romfile.id,
             chd_path.as_os_str().to_str().unwrap(),
-            chd_path.metadata().await.unwrap().len(),
+            get_file_size(&chd_path).await,
         )
         .await;
-        remove_file(progress_bar, &romfile.path, false).await?;
+        journal_create(&mut transaction, &chd_path, Some(romfile.id)).await;
+        journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &[&romfile.path], &new_paths).await?;
     }
 
     // convert CUE/BIN
     for roms in cue_bins.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
 
         let (cue_roms, bin_roms): (Vec<&Rom>, Vec<&Rom>) = roms
@@ -975,11 +1292,35 @@ async fn to_chd(
         let cue_romfile = romfiles_by_id
             .get(&cue_roms.get(0).unwrap().romfile_id.unwrap())
             .unwrap();
-        let chd_path = chdman::create_chd(
+        let staged_path = chdman::create_chd(
             progress_bar,
             &cue_romfile.path,
-            &Path::new(&cue_romfile.path).parent().unwrap(),
+            &staging_directory.path(),
+            chd_type,
         )?;
+        if verify {
+            try_with!(
+                check_chd(progress_bar, &staged_path, &bin_roms),
+                "Failed to verify {:?}, the original files were left untouched",
+                staged_path.file_name().unwrap()
+            );
+        }
+        let chd_path = get_final_path(&staged_path, &cue_romfile.path);
+        let bin_romfile_paths = bin_roms
+            .iter()
+            .map(|rom| &romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap().path)
+            .collect::<Vec<&String>>();
+        swap_staged_files(
+            progress_bar,
+            &staging_directory,
+            &[&staged_path],
+            &[&chd_path],
+            &bin_romfile_paths,
+            &[cue_roms.as_slice(), bin_roms.as_slice()].concat(),
+            &None,
+            &[],
+        )
+        .await?;
         if diff {
             let roms = [cue_roms.as_slice(), bin_roms.as_slice()].concat();
             let mut romfile_paths = romfiles_by_id
@@ -993,30 +1334,55 @@ async fn to_chd(
         let chd_romfile_id = create_romfile(
             &mut transaction,
             chd_path.as_os_str().to_str().unwrap(),
-            chd_path.metadata().await.unwrap().len(),
+            get_file_size(&chd_path).await,
         )
         .await;
+        journal_create(&mut transaction, &chd_path, Some(chd_romfile_id)).await;
         for bin_rom in bin_roms {
             let bin_romfile = romfiles_by_id.get(&bin_rom.romfile_id.unwrap()).unwrap();
             update_rom_romfile(&mut transaction, bin_rom.id, Some(chd_romfile_id)).await;
             delete_romfile_by_id(&mut transaction, bin_romfile.id).await;
-            remove_file(progress_bar, &bin_romfile.path, false).await?;
+            journal_delete(&mut transaction, &bin_romfile.path, None).await;
         }
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &bin_romfile_paths, &[&chd_path]).await?;
     }
 
     // convert ISOs
     for roms in isos.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
+        let mut old_paths: Vec<&String> = Vec::new();
+        let mut new_paths: Vec<PathBuf> = Vec::new();
 
         for rom in roms {
             let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-            let chd_path = chdman::create_chd(
+            let staged_path = chdman::create_chd(
                 progress_bar,
                 &romfile.path,
-                &Path::new(&romfile.path).parent().unwrap(),
+                &staging_directory.path(),
+                chd_type,
             )?;
+            if verify {
+                try_with!(
+                    check_chd(progress_bar, &staged_path, &[rom]),
+                    "Failed to verify {:?}, the original files were left untouched",
+                    staged_path.file_name().unwrap()
+                );
+            }
+            let chd_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&chd_path],
+                &[&romfile.path],
+                &[rom],
+                &None,
+                &[],
+            )
+            .await?;
             if diff {
                 print_diff(progress_bar, &[rom], &[&romfile.path], &[&chd_path]).await?;
             }
@@ -1024,29 +1390,51 @@ async fn to_chd(
                 &mut transaction,
                 romfile.id,
                 chd_path.as_os_str().to_str().unwrap(),
-                chd_path.metadata().await.unwrap().len(),
+                get_file_size(&chd_path).await,
             )
             .await;
-            remove_file(progress_bar, &romfile.path, false).await?;
+            journal_create(&mut transaction, &chd_path, Some(romfile.id)).await;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+            old_paths.push(&romfile.path);
+            new_paths.push(chd_path);
         }
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &old_paths, &new_paths).await?;
     }
 
     // convert CSOs
     cfg_if! {
         if #[cfg(feature = "cso")] {
             for roms in csos.values() {
+                let staging_directory = create_staging_directory(connection).await?;
                 let mut transaction = begin_transaction(connection).await;
+                let mut old_paths: Vec<&String> = Vec::new();
+                let mut new_paths: Vec<PathBuf> = Vec::new();
 
                 for rom in roms {
                     let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-                    let iso_path = maxcso::extract_cso(progress_bar, &romfile.path, &tmp_directory.path())?;
-                    let chd_path = chdman::create_chd(
+                    let iso_path = cso::extract_cso(progress_bar, &romfile.path, &tmp_directory.path())?;
+                    let staged_path = chdman::create_chd(progress_bar, &iso_path, &staging_directory.path(), chd_type)?;
+                    if verify {
+                        try_with!(
+                            check_chd(progress_bar, &staged_path, &[rom]),
+                            "Failed to verify {:?}, the original files were left untouched",
+                            staged_path.file_name().unwrap()
+                        );
+                    }
+                    let chd_path = get_final_path(&staged_path, &romfile.path);
+                    swap_staged_files(
                         progress_bar,
-                        &iso_path,
-                        &Path::new(&romfile.path).parent().unwrap(),
-                    )?;
+                        &staging_directory,
+                        &[&staged_path],
+                        &[&chd_path],
+                        &[&romfile.path],
+                        &[rom],
+                        &None,
+                        &[],
+                    )
+                    .await?;
                     if diff {
                         print_diff(progress_bar, &[rom], &[&romfile.path], &[&chd_path]).await?;
                     }
@@ -1054,13 +1442,17 @@ async fn to_chd(
                         &mut transaction,
                         romfile.id,
                         chd_path.as_os_str().to_str().unwrap(),
-                        chd_path.metadata().await.unwrap().len(),
+                        get_file_size(&chd_path).await,
                     )
                     .await;
-                    remove_file(progress_bar, &romfile.path, false).await?;
+                    journal_create(&mut transaction, &chd_path, Some(romfile.id)).await;
+                    journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+                    old_paths.push(&romfile.path);
+                    new_paths.push(chd_path);
                 }
 
                 commit_transaction(transaction).await;
+                remove_original_files(connection, progress_bar, &old_paths, &new_paths).await?;
             }
         }
     }
@@ -1071,10 +1463,11 @@ async fn to_chd(
 #[cfg(feature = "cso")]
 async fn to_cso(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     roms_by_game_id: HashMap<i64, Vec<Rom>>,
     romfiles_by_id: HashMap<i64, Romfile>,
     diff: bool,
+    verify: bool,
 ) -> SimpleResult<()> {
     let tmp_directory = create_tmp_directory(connection).await?;
 
@@ -1121,6 +1514,7 @@ async fn to_cso(
 
     // convert archives
     for roms in archives.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
 
         let mut romfiles: Vec<&Romfile> = roms
@@ -1149,11 +1543,27 @@ async fn to_cso(
         )?;
         let extracted_path = extracted_paths.get(0).unwrap();
 
-        let cso_path = maxcso::create_cso(
+        let staged_path =
+            cso::create_cso(progress_bar, &extracted_path, &staging_directory.path())?;
+        if verify {
+            try_with!(
+                check_cso(progress_bar, &staged_path, rom),
+                "Failed to verify {:?}, the original files were left untouched",
+                staged_path.file_name().unwrap()
+            );
+        }
+        let cso_path = get_final_path(&staged_path, &romfile.path);
+        swap_staged_files(
             progress_bar,
-            &extracted_path,
-            &Path::new(&romfile.path).parent().unwrap(),
-        )?;
+            &staging_directory,
+            &[&staged_path],
+            &[&cso_path],
+            &[&romfile.path],
+            &[rom],
+            &None,
+            &[],
+        )
+        .await?;
 
         if diff {
             print_diff(progress_bar, &[rom], &[&romfile.path], &[&cso_path]).await?;
@@ -1163,25 +1573,46 @@ async fn to_cso(
             &mut transaction,
             romfile.id,
             cso_path.as_os_str().to_str().unwrap(),
-            cso_path.metadata().await.unwrap().len(),
+            get_file_size(&cso_path).await,
         )
         .await;
-        remove_file(progress_bar, &romfile.path, false).await?;
+        journal_create(&mut transaction, &cso_path, Some(romfile.id)).await;
+        journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &[&romfile.path], &[&cso_path]).await?;
     }
 
     // convert ISOs
     for roms in isos.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
+        let mut old_paths: Vec<&String> = Vec::new();
+        let mut new_paths: Vec<PathBuf> = Vec::new();
 
         for rom in roms {
             let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-            let cso_path = maxcso::create_cso(
+            let staged_path =
+                cso::create_cso(progress_bar, &romfile.path, &staging_directory.path())?;
+            if verify {
+                try_with!(
+                    check_cso(progress_bar, &staged_path, rom),
+                    "Failed to verify {:?}, the original files were left untouched",
+                    staged_path.file_name().unwrap()
+                );
+            }
+            let cso_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
                 progress_bar,
-                &romfile.path,
-                &Path::new(&romfile.path).parent().unwrap(),
-            )?;
+                &staging_directory,
+                &[&staged_path],
+                &[&cso_path],
+                &[&romfile.path],
+                &[rom],
+                &None,
+                &[],
+            )
+            .await?;
             if diff {
                 print_diff(progress_bar, &[rom], &[&romfile.path], &[&cso_path]).await?;
             }
@@ -1189,20 +1620,27 @@ async fn to_cso(
                 &mut transaction,
                 romfile.id,
                 cso_path.as_os_str().to_str().unwrap(),
-                cso_path.metadata().await.unwrap().len(),
+                get_file_size(&cso_path).await,
             )
             .await;
-            remove_file(progress_bar, &romfile.path, false).await?;
+            journal_create(&mut transaction, &cso_path, Some(romfile.id)).await;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+            old_paths.push(&romfile.path);
+            new_paths.push(cso_path);
         }
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &old_paths, &new_paths).await?;
     }
 
     // convert CHDs
     cfg_if! {
         if #[cfg(feature = "chd")] {
             for roms in chds.values() {
+                let staging_directory = create_staging_directory(connection).await?;
                 let mut transaction = begin_transaction(connection).await;
+                let mut old_paths: Vec<&String> = Vec::new();
+                let mut new_paths: Vec<PathBuf> = Vec::new();
                 for rom in roms {
                     let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
                     let iso_path = chdman::extract_chd_to_single_track(
@@ -1211,11 +1649,30 @@ async fn to_cso(
                         &tmp_directory.path(),
                     )
                     .await?;
-                    let cso_path = maxcso::create_cso(
+                    let staged_path = cso::create_cso(
                         progress_bar,
                         &iso_path,
-                        &Path::new(&romfile.path).parent().unwrap(),
+                        &staging_directory.path(),
                     )?;
+                    if verify {
+                        try_with!(
+                            check_cso(progress_bar, &staged_path, rom),
+                            "Failed to verify {:?}, the original files were left untouched",
+                            staged_path.file_name().unwrap()
+                        );
+                    }
+                    let cso_path = get_final_path(&staged_path, &romfile.path);
+                    swap_staged_files(
+                        progress_bar,
+                        &staging_directory,
+                        &[&staged_path],
+                        &[&cso_path],
+                        &[&romfile.path],
+                        &[rom],
+                        &None,
+                        &[],
+                    )
+                    .await?;
                     if diff {
                         print_diff(progress_bar, &[rom], &[&romfile.path], &[&cso_path]).await?;
                     }
@@ -1223,13 +1680,17 @@ async fn to_cso(
                         &mut transaction,
                         romfile.id,
                         cso_path.as_os_str().to_str().unwrap(),
-                        cso_path.metadata().await.unwrap().len(),
+                        get_file_size(&cso_path).await,
                     )
                     .await;
-                    remove_file(progress_bar, &romfile.path, false).await?;
+                    journal_create(&mut transaction, &cso_path, Some(romfile.id)).await;
+                    journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+                    old_paths.push(&romfile.path);
+                    new_paths.push(cso_path);
                 }
 
                 commit_transaction(transaction).await;
+                remove_original_files(connection, progress_bar, &old_paths, &new_paths).await?;
             }
         }
     }
@@ -1238,15 +1699,14 @@ async fn to_cso(
 }
 
 #[cfg(feature = "rvz")]
-async fn to_rvz(
+async fn to_dolphin_image(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     roms_by_game_id: HashMap<i64, Vec<Rom>>,
     romfiles_by_id: HashMap<i64, Romfile>,
     diff: bool,
-    compression_algorithm: &RvzCompressionAlgorithm,
-    compression_level: usize,
-    block_size: usize,
+    verify: bool,
+    format: &dolphin::DolphinFormat<'_>,
 ) -> SimpleResult<()> {
     let tmp_directory = create_tmp_directory(connection).await?;
 
@@ -1271,11 +1731,24 @@ async fn to_rvz(
             })
         });
 
+    // partition other dolphin images
+    let (dolphin_images, others): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
+        others.into_iter().partition(|(_, roms)| {
+            roms.par_iter().any(|rom| {
+                let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+                DOLPHIN_EXTENSIONS
+                    .iter()
+                    .filter(|&&extension| extension != format.extension())
+                    .any(|extension| romfile.path.ends_with(extension))
+            })
+        });
+
     // drop others
     drop(others);
 
     // convert archives
     for roms in archives.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
 
         let mut romfiles: Vec<&Romfile> = roms
@@ -1304,59 +1777,156 @@ async fn to_rvz(
         )?;
         let extracted_path = extracted_paths.get(0).unwrap();
 
-        let rvz_path = dolphin::create_rvz(
+        let staged_path = dolphin::create_image(
             progress_bar,
             &extracted_path,
-            &Path::new(&romfile.path).parent().unwrap(),
-            compression_algorithm,
-            compression_level,
-            block_size,
+            &staging_directory.path(),
+            format,
         )?;
+        if verify {
+            try_with!(
+                check_dolphin_image(&mut transaction, progress_bar, &None, &staged_path, rom).await,
+                "Failed to verify {:?}, the original files were left untouched",
+                staged_path.file_name().unwrap()
+            );
+        }
+        let image_path = get_final_path(&staged_path, &romfile.path);
+        swap_staged_files(
+            progress_bar,
+            &staging_directory,
+            &[&staged_path],
+            &[&image_path],
+            &[&romfile.path],
+            &[rom],
+            &None,
+            &[],
+        )
+        .await?;
 
         if diff {
-            print_diff(progress_bar, &[rom], &[&romfile.path], &[&rvz_path]).await?;
+            print_diff(progress_bar, &[rom], &[&romfile.path], &[&image_path]).await?;
         }
 
         update_romfile(
             &mut transaction,
             romfile.id,
-            rvz_path.as_os_str().to_str().unwrap(),
-            rvz_path.metadata().await.unwrap().len(),
+            image_path.as_os_str().to_str().unwrap(),
+            get_file_size(&image_path).await,
         )
         .await;
-        remove_file(progress_bar, &romfile.path, false).await?;
+        journal_create(&mut transaction, &image_path, Some(romfile.id)).await;
+        journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &[&romfile.path], &[&image_path]).await?;
     }
 
     // convert ISOs
     for roms in isos.values() {
+        let staging_directory = create_staging_directory(connection).await?;
         let mut transaction = begin_transaction(connection).await;
+        let mut old_paths: Vec<&String> = Vec::new();
+        let mut new_paths: Vec<PathBuf> = Vec::new();
 
         for rom in roms {
             let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-            let rvz_path = dolphin::create_rvz(
+            let staged_path = dolphin::create_image(
                 progress_bar,
                 &romfile.path,
-                &Path::new(&romfile.path).parent().unwrap(),
-                compression_algorithm,
-                compression_level,
-                block_size,
+                &staging_directory.path(),
+                format,
             )?;
+            if verify {
+                try_with!(
+                    check_dolphin_image(&mut transaction, progress_bar, &None, &staged_path, rom)
+                        .await,
+                    "Failed to verify {:?}, the original files were left untouched",
+                    staged_path.file_name().unwrap()
+                );
+            }
+            let image_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&image_path],
+                &[&romfile.path],
+                &[rom],
+                &None,
+                &[],
+            )
+            .await?;
+            if diff {
+                print_diff(progress_bar, &[rom], &[&romfile.path], &[&image_path]).await?;
+            }
+            update_romfile(
+                &mut transaction,
+                romfile.id,
+                image_path.as_os_str().to_str().unwrap(),
+                get_file_size(&image_path).await,
+            )
+            .await;
+            journal_create(&mut transaction, &image_path, Some(romfile.id)).await;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+            old_paths.push(&romfile.path);
+            new_paths.push(image_path);
+        }
+
+        commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &old_paths, &new_paths).await?;
+    }
+
+    // convert other dolphin images
+    for roms in dolphin_images.values() {
+        let staging_directory = create_staging_directory(connection).await?;
+        let mut transaction = begin_transaction(connection).await;
+        let mut old_paths: Vec<&String> = Vec::new();
+        let mut new_paths: Vec<PathBuf> = Vec::new();
+
+        for rom in roms {
+            let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+            let iso_path =
+                dolphin::extract_image(progress_bar, &romfile.path, &tmp_directory.path())?;
+            let staged_path =
+                dolphin::create_image(progress_bar, &iso_path, &staging_directory.path(), format)?;
+            if verify {
+                try_with!(
+                    check_dolphin_image(&mut transaction, progress_bar, &None, &staged_path, rom)
+                        .await,
+                    "Failed to verify {:?}, the original files were left untouched",
+                    staged_path.file_name().unwrap()
+                );
+            }
+            let image_path = get_final_path(&staged_path, &romfile.path);
+            swap_staged_files(
+                progress_bar,
+                &staging_directory,
+                &[&staged_path],
+                &[&image_path],
+                &[&romfile.path],
+                &[rom],
+                &None,
+                &[],
+            )
+            .await?;
             if diff {
-                print_diff(progress_bar, &[rom], &[&romfile.path], &[&rvz_path]).await?;
+                print_diff(progress_bar, &[rom], &[&romfile.path], &[&image_path]).await?;
             }
             update_romfile(
                 &mut transaction,
                 romfile.id,
-                rvz_path.as_os_str().to_str().unwrap(),
-                rvz_path.metadata().await.unwrap().len(),
+                image_path.as_os_str().to_str().unwrap(),
+                get_file_size(&image_path).await,
             )
             .await;
-            remove_file(progress_bar, &romfile.path, false).await?;
+            journal_create(&mut transaction, &image_path, Some(romfile.id)).await;
+            journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+            old_paths.push(&romfile.path);
+            new_paths.push(image_path);
         }
 
         commit_transaction(transaction).await;
+        remove_original_files(connection, progress_bar, &old_paths, &new_paths).await?;
     }
 
     Ok(())
@@ -1364,7 +1934,7 @@ async fn to_rvz(
 
 async fn to_original(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     roms_by_game_id: HashMap<i64, Vec<Rom>>,
     romfiles_by_id: HashMap<i64, Romfile>,
@@ -1400,27 +1970,23 @@ async fn to_original(
             let (csos, others): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
                 others.into_iter().partition(|(_, roms)| {
                     roms.par_iter().any(|rom| {
-                        romfiles_by_id
-                            .get(&rom.romfile_id.unwrap())
-                            .unwrap()
-                            .path
-                            .ends_with(CSO_EXTENSION)
+                        let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+                        romfile.path.ends_with(CSO_EXTENSION) || romfile.path.ends_with(ZSO_EXTENSION)
                     })
                 });
         }
     }
 
-    // partition RVZs
+    // partition RVZs, WBFSs, WIAs and GCZs
     cfg_if! {
         if #[cfg(feature = "rvz")] {
-            let (rvzs, others): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
+            let (dolphin_images, others): (HashMap<i64, Vec<Rom>>, HashMap<i64, Vec<Rom>>) =
                 others.into_iter().partition(|(_, roms)| {
                     roms.par_iter().any(|rom| {
-                        romfiles_by_id
-                            .get(&rom.romfile_id.unwrap())
-                            .unwrap()
-                            .path
-                            .ends_with(RVZ_EXTENSION)
+                        let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
+                        DOLPHIN_EXTENSIONS
+                            .iter()
+                            .any(|extension| romfile.path.ends_with(extension))
                     })
                 });
         }
@@ -1471,13 +2037,15 @@ async fn to_original(
             let romfile_id = create_romfile(
                 &mut transaction,
                 extracted_path.as_os_str().to_str().unwrap(),
-                extracted_path.metadata().await.unwrap().len(),
+                get_file_size(&extracted_path).await,
             )
             .await;
+            journal_create(&mut transaction, &extracted_path, Some(romfile_id)).await;
             update_rom_romfile(&mut transaction, rom.id, Some(romfile_id)).await;
         }
         delete_romfile_by_id(&mut transaction, romfile.id).await;
-        remove_file(progress_bar, &romfile.path, false).await?;
+        journal_delete(&mut transaction, &romfile.path, None).await;
+        remove_journaled_file(&mut transaction, progress_bar, &romfile.path, false).await?;
 
         commit_transaction(transaction).await;
     }
@@ -1522,13 +2090,15 @@ async fn to_original(
                     let romfile_id = create_romfile(
                         &mut transaction,
                         bin_path.as_os_str().to_str().unwrap(),
-                        bin_path.metadata().await.unwrap().len(),
+                        get_file_size(&bin_path).await,
                     )
                     .await;
+                    journal_create(&mut transaction, &bin_path, Some(romfile_id)).await;
                     update_rom_romfile(&mut transaction, rom.id, Some(romfile_id)).await;
                 }
                 delete_romfile_by_id(&mut transaction, romfile.id).await;
-                remove_file(progress_bar, &romfile.path, false).await?;
+                journal_delete(&mut transaction, &romfile.path, None).await;
+                remove_journaled_file(&mut transaction, progress_bar, &romfile.path, false).await?;
 
                 commit_transaction(transaction).await;
             }
@@ -1543,7 +2113,7 @@ async fn to_original(
 
                 for rom in roms {
                     let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-                    let iso_path = maxcso::extract_cso(
+                    let iso_path = cso::extract_cso(
                         progress_bar,
                         &romfile.path,
                         &Path::new(&romfile.path).parent().unwrap(),
@@ -1552,10 +2122,13 @@ async fn to_original(
                         &mut transaction,
                         romfile.id,
                         iso_path.as_os_str().to_str().unwrap(),
-                        iso_path.metadata().await.unwrap().len(),
+                        get_file_size(&iso_path).await,
                     )
                     .await;
-                    remove_file(progress_bar, &romfile.path, false).await?;
+                    journal_create(&mut transaction, &iso_path, Some(romfile.id)).await;
+                    journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+                    remove_journaled_file(&mut transaction, progress_bar, &romfile.path, false)
+                        .await?;
                 }
 
                 commit_transaction(transaction).await;
@@ -1563,15 +2136,15 @@ async fn to_original(
         }
     }
 
-    // convert RVZs
+    // convert RVZs, WBFSs, WIAs and GCZs
     cfg_if! {
         if #[cfg(feature = "rvz")] {
-            for roms in rvzs.values() {
+            for roms in dolphin_images.values() {
                 let mut transaction = begin_transaction(connection).await;
 
                 for rom in roms {
                     let romfile = romfiles_by_id.get(&rom.romfile_id.unwrap()).unwrap();
-                    let iso_path = dolphin::extract_rvz(
+                    let iso_path = dolphin::extract_image(
                         progress_bar,
                         &romfile.path,
                         &Path::new(&romfile.path).parent().unwrap(),
@@ -1580,10 +2153,13 @@ async fn to_original(
                         &mut transaction,
                         romfile.id,
                         iso_path.as_os_str().to_str().unwrap(),
-                        iso_path.metadata().await.unwrap().len(),
+                        get_file_size(&iso_path).await,
                     )
                     .await;
-                    remove_file(progress_bar, &romfile.path, false).await?;
+                    journal_create(&mut transaction, &iso_path, Some(romfile.id)).await;
+                    journal_delete(&mut transaction, &romfile.path, Some(romfile.id)).await;
+                    remove_journaled_file(&mut transaction, progress_bar, &romfile.path, false)
+                        .await?;
                 }
 
                 commit_transaction(transaction).await;
@@ -1594,12 +2170,230 @@ async fn to_original(
     Ok(())
 }
 
+async fn convert_headers(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: &System,
+    roms_by_game_id: HashMap<i64, Vec<Rom>>,
+    romfiles_by_id: HashMap<i64, Romfile>,
+    diff: bool,
+    strip: bool,
+) -> SimpleResult<()> {
+    let header = match find_header_by_system_id(connection, system.id).await {
+        Some(header) => header,
+        None => {
+            progress_bar.println("System has no header");
+            return Ok(());
+        }
+    };
+    let rules = find_rules_by_header_id(connection, header.id).await;
+
+    // archives can hold several ROMs
+    let mut roms_by_romfile_id: HashMap<i64, Vec<&Rom>> = HashMap::new();
+    for rom in roms_by_game_id.values().flatten() {
+        roms_by_romfile_id
+            .entry(rom.romfile_id.unwrap())
+            .or_default()
+            .push(rom);
+    }
+    let mut romfile_ids: Vec<i64> = roms_by_romfile_id.keys().copied().collect();
+    romfile_ids.sort_unstable();
+
+    for romfile_id in romfile_ids {
+        let roms = roms_by_romfile_id.get(&romfile_id).unwrap();
+        let romfile = romfiles_by_id.get(&romfile_id).unwrap();
+        let file_name = Path::new(&romfile.path)
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let extension = Path::new(&romfile.path)
+            .extension()
+            .unwrap_or_default()
+            .to_str()
+            .unwrap();
+
+        let staging_directory = create_staging_directory(connection).await?;
+        let tmp_directory = create_tmp_directory(connection).await?;
+
+        // archived ROMs are extracted, then archived again along with the other files of the archive
+        let (rom_paths, archive_names) = if roms.len() == 1 && roms[0].name == file_name {
+            (vec![(roms[0], PathBuf::from(&romfile.path))], None)
+        } else if ARCHIVE_EXTENSIONS.contains(&extension) {
+            let archive_names: Vec<String> = sevenzip::parse_archive(progress_bar, &romfile.path)?
+                .into_iter()
+                .map(|archive_info| archive_info.path)
+                .collect();
+            // extracting only reads the archive, so it also runs in dry-run mode
+            sevenzip::extract_files_for_hashing(
+                progress_bar,
+                &romfile.path,
+                &archive_names
+                    .iter()
+                    .map(|archive_name| archive_name.as_str())
+                    .collect::<Vec<&str>>(),
+                &tmp_directory.path(),
+            )?;
+            (
+                roms.iter()
+                    .map(|&rom| (rom, Path::new(tmp_directory.path()).join(&rom.name)))
+                    .collect(),
+                Some(archive_names),
+            )
+        } else {
+            progress_bar.println(format!(
+                "{:?} has to be converted to its original format or to an archive first, skipping",
+                file_name
+            ));
+            continue;
+        };
+
+        let mut existing_headers: Vec<(&Rom, Option<Vec<u8>>)> = Vec::new();
+        for (rom, path) in rom_paths {
+            let mut data: Vec<u8> = Vec::new();
+            try_with!(
+                open_file_sync(&path)?.read_to_end(&mut data),
+                "Failed to read {:?}",
+                path
+            );
+            let stripped_header = find_stripped_header_by_rom_id(connection, rom.id).await;
+            let existing_header = read_header(&mut data.as_slice(), &header, &rules)?;
+            let new_data = match (strip, &existing_header, stripped_header) {
+                (true, Some(_), _) => data.split_off(header.size as usize),
+                (false, None, Some(mut stripped_header)) => {
+                    stripped_header.append(&mut data);
+                    stripped_header
+                }
+                (false, None, None) => {
+                    progress_bar
+                        .println(format!("No stored header for \"{}\", skipping", rom.name));
+                    continue;
+                }
+                _ => continue,
+            };
+
+            // archived ROMs are converted in place
+            let staged_path = match archive_names {
+                Some(_) => path,
+                None => Path::new(staging_directory.path()).join(&rom.name),
+            };
+            progress_bar.println(format!(
+                "{} header {:?}",
+                match strip {
+                    true => "Removing",
+                    false => "Restoring",
+                },
+                staged_path.file_name().unwrap()
+            ));
+            try_with!(
+                fs::write(&staged_path, &new_data),
+                "Failed to write {:?}",
+                staged_path
+            );
+
+            // the header is skipped when hashing so the ROM must still match
+            let (size, hashes) = compute_size_and_hashes(
+                progress_bar,
+                &staged_path,
+                &Some(header.clone()),
+                &rules,
+                1,
+                1,
+            )?;
+            if size != rom.size as u64 || !hashes.matches(rom) {
+                bail!(
+                    "Failed to verify {:?}, the original files were left untouched",
+                    staged_path.file_name().unwrap()
+                );
+            }
+            existing_headers.push((rom, existing_header));
+        }
+        if existing_headers.is_empty() {
+            continue;
+        }
+
+        let staged_path = match &archive_names {
+            Some(archive_names) => {
+                let staged_path = Path::new(staging_directory.path()).join(file_name);
+                let (compression_level, solid) = match extension == SEVENZIP_EXTENSION {
+                    true => (
+                        get_integer(connection, "SEVENZIP_COMPRESSION_LEVEL", Some(system.id))
+                            .await,
+                        get_bool(connection, "SEVENZIP_SOLID_COMPRESSION", Some(system.id)).await,
+                    ),
+                    false => (
+                        get_integer(connection, "ZIP_COMPRESSION_LEVEL", Some(system.id)).await,
+                        false,
+                    ),
+                };
+                sevenzip::add_files_to_archive(
+                    progress_bar,
+                    &staged_path,
+                    &archive_names
+                        .iter()
+                        .map(|archive_name| archive_name.as_str())
+                        .collect::<Vec<&str>>(),
+                    &tmp_directory.path(),
+                    compression_level,
+                    solid,
+                )?;
+                staged_path
+            }
+            None => Path::new(staging_directory.path()).join(file_name),
+        };
+
+        let mut transaction = begin_transaction(connection).await;
+
+        swap_staged_files(
+            progress_bar,
+            &staging_directory,
+            &[&staged_path],
+            &[&romfile.path],
+            &[&romfile.path],
+            roms,
+            &Some(header.clone()),
+            &rules,
+        )
+        .await?;
+        if diff {
+            print_diff(progress_bar, roms, &[&romfile.path], &[&romfile.path]).await?;
+        }
+        update_romfile(
+            &mut transaction,
+            romfile.id,
+            &romfile.path,
+            get_file_size(&romfile.path).await,
+        )
+        .await;
+        for (rom, existing_header) in existing_headers {
+            match strip {
+                true => {
+                    create_or_update_stripped_header(
+                        &mut transaction,
+                        rom.id,
+                        existing_header.as_ref().unwrap(),
+                    )
+                    .await
+                }
+                false => delete_stripped_header_by_rom_id(&mut transaction, rom.id).await,
+            }
+        }
+        commit_transaction(transaction).await;
+    }
+
+    Ok(())
+}
+
 async fn print_diff<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     roms: &[&Rom],
     old_files: &[&P],
     new_files: &[&Q],
 ) -> SimpleResult<()> {
+    // new files don't exist in dry-run mode
+    if is_dry_run() {
+        return Ok(());
+    }
     let original_size = roms.iter().map(|&r| r.size as u64).sum();
     let mut old_size = 0u64;
     for &old_file in old_files {
@@ -1641,7 +2435,11 @@ mod test_cso_to_sevenzip_iso;
 #[cfg(all(test, feature = "chd"))]
 mod test_cue_bin_to_chd;
 #[cfg(all(test, feature = "chd"))]
+mod test_dry_run_iso_to_chd;
+#[cfg(all(test, feature = "chd"))]
 mod test_iso_to_chd;
+#[cfg(all(test, feature = "chd"))]
+mod test_iso_to_chd_with_wrong_hash;
 #[cfg(all(test, feature = "cso"))]
 mod test_iso_to_cso;
 #[cfg(all(test, feature = "chd", feature = "cso"))]
@@ -1656,6 +2454,8 @@ mod test_original_to_zip;
 mod test_original_to_zip_with_correct_name;
 #[cfg(test)]
 mod test_original_to_zip_with_incorrect_name;
+#[cfg(test)]
+mod test_set_default;
 #[cfg(all(test, feature = "chd"))]
 mod test_sevenzip_cue_bin_to_chd;
 #[cfg(all(test, feature = "chd"))]
@@ -1671,6 +2471,10 @@ mod test_single_track_chd_to_cso;
 #[cfg(all(test, feature = "chd"))]
 mod test_single_track_chd_to_sevenzip_iso;
 #[cfg(test)]
+mod test_strip_and_add_header;
+#[cfg(test)]
+mod test_strip_and_add_header_sevenzip;
+#[cfg(test)]
 mod test_zip_to_original;
 #[cfg(test)]
 mod test_zip_to_sevenzip;
@@ -11,7 +11,7 @@ async fn test_cue_bin_to_chd() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -83,6 +83,8 @@ async fn test_cue_bin_to_chd() {
         roms_by_game_id,
         romfiles_by_id,
         true,
+        true,
+        &ChdType::Auto,
     )
     .await
     .unwrap();
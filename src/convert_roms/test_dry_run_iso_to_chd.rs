@@ -0,0 +1,103 @@
+use super::super::database::*;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use std::env;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    // chdman can't be found, dry-run mode must not run it
+    let path = env::var("PATH").unwrap();
+    env::set_var("PATH", "");
+    let progress_bar = Progress::hidden();
+
+    let test_directory = Path::new("tests");
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).iso");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).iso"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
+        .await
+        .unwrap();
+
+    let matches = import_roms::subcommand()
+        .get_matches_from(["import-roms", romfile_path.as_os_str().to_str().unwrap()]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let roms = find_roms_with_romfile_by_system_id(&mut connection, system.id).await;
+    let romfile = find_romfile_by_id(&mut connection, roms[0].romfile_id.unwrap()).await;
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    roms_by_game_id.insert(roms[0].game_id, roms);
+    let mut romfiles_by_id: HashMap<i64, Romfile> = HashMap::new();
+    romfiles_by_id.insert(romfile.id, romfile);
+
+    // when
+    set_dry_run(true);
+    let mut transaction = begin_transaction(&mut connection).await;
+    create_dry_run_journal(&mut transaction).await;
+    let result = to_chd(
+        &mut transaction,
+        &progress_bar,
+        roms_by_game_id,
+        romfiles_by_id,
+        true,
+        false,
+        &ChdType::Auto,
+    )
+    .await;
+    let journal = find_dry_run_journal(&mut transaction).await;
+    rollback_transaction(transaction).await;
+    set_dry_run(false);
+    env::set_var("PATH", path);
+
+    // then
+    result.unwrap();
+    assert!(journal.contains(&(String::from("romfiles"), String::from("UPDATE"), 1)));
+
+    let romfiles = find_romfiles(&mut connection).await;
+    assert_eq!(romfiles.len(), 1);
+    let romfile = &romfiles[0];
+    assert_eq!(
+        romfile.path,
+        system_directory
+            .join("Test Game (USA, Europe).iso")
+            .as_os_str()
+            .to_str()
+            .unwrap(),
+    );
+    assert!(Path::new(&romfile.path).is_file().await);
+    assert!(
+        !system_directory
+            .join("Test Game (USA, Europe).chd")
+            .is_file()
+            .await
+    );
+}
@@ -0,0 +1,122 @@
+use super::super::database::*;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use std::env;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    env::set_var(
+        "PATH",
+        format!(
+            "{}:{}",
+            test_directory.as_os_str().to_str().unwrap(),
+            env::var("PATH").unwrap()
+        ),
+    );
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    // the CRC still matches so the ISO gets imported, only the SHA1 is wrong
+    let dat_path = tmp_directory.join("Test System (20200721).dat");
+    fs::write(
+        &dat_path,
+        fs::read_to_string(test_directory.join("Test System (20200721).dat"))
+            .await
+            .unwrap()
+            .replace(
+                "762a227d4d157c20e671b53041741ba6c22c552b",
+                "0000000000000000000000000000000000000000",
+            ),
+    )
+    .await
+    .unwrap();
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", dat_path.as_os_str().to_str().unwrap()]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).iso");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).iso"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
+        .await
+        .unwrap();
+
+    let matches = import_roms::subcommand()
+        .get_matches_from(["import-roms", romfile_path.as_os_str().to_str().unwrap()]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let roms = find_roms_with_romfile_by_system_id(&mut connection, system.id).await;
+    let romfile = find_romfile_by_id(&mut connection, roms[0].romfile_id.unwrap()).await;
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    roms_by_game_id.insert(roms[0].game_id, roms);
+    let mut romfiles_by_id: HashMap<i64, Romfile> = HashMap::new();
+    romfiles_by_id.insert(romfile.id, romfile);
+
+    // when
+    let result = to_chd(
+        &mut connection,
+        &progress_bar,
+        roms_by_game_id,
+        romfiles_by_id,
+        true,
+        true,
+        &ChdType::Auto,
+    )
+    .await;
+
+    // then
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("Failed to verify \"Test Game (USA, Europe).chd\""));
+
+    let roms = find_roms_with_romfile_by_system_id(&mut connection, system.id).await;
+    assert_eq!(roms.len(), 1);
+    let romfiles = find_romfiles(&mut connection).await;
+    assert_eq!(romfiles.len(), 1);
+
+    let rom = roms.first().unwrap();
+    let romfile = romfiles.first().unwrap();
+    assert_eq!(
+        romfile.path,
+        system_directory
+            .join("Test Game (USA, Europe).iso")
+            .as_os_str()
+            .to_str()
+            .unwrap(),
+    );
+    assert_eq!(romfile.size, 358400);
+    assert!(Path::new(&romfile.path).is_file().await);
+    assert!(
+        !system_directory
+            .join("Test Game (USA, Europe).chd")
+            .is_file()
+            .await
+    );
+    assert_eq!(rom.romfile_id, Some(romfile.id));
+}
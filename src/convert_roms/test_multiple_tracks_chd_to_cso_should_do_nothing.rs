@@ -20,7 +20,7 @@ async fn test() {
             env::var("PATH").unwrap()
         ),
     );
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -31,22 +31,24 @@ async fn test() {
     let tmp_directory = TempDir::new_in(&test_directory).unwrap();
     let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
 
-    let matches = import_dats::subcommand()
-        .get_matches_from(&["import-dats", "tests/Test System (20200721).dat"]);
+    let matches = import_dats::subcommand().get_matches_from(&[
+        "import-dats",
+        "tests/Test System (20231018) (Multiple Tracks).dat",
+    ]);
     import_dats::main(&mut connection, &matches, &progress_bar)
         .await
         .unwrap();
 
-    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue");
+    let romfile_path = tmp_directory.join("Test Game (World) (Multiple Tracks).cue");
     fs::copy(
-        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue"),
+        test_directory.join("Test Game (World) (Multiple Tracks).cue"),
         &romfile_path,
     )
     .await
     .unwrap();
-    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Multiple Tracks).chd");
+    let romfile_path = tmp_directory.join("Test Game (World) (Multiple Tracks).chd");
     fs::copy(
-        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).chd"),
+        test_directory.join("Test Game (World) (Multiple Tracks).chd"),
         &romfile_path,
     )
     .await
@@ -79,6 +81,7 @@ async fn test() {
         roms_by_game_id,
         romfiles_by_id,
         true,
+        true,
     )
     .await
     .unwrap();
@@ -93,7 +96,7 @@ async fn test() {
     assert_eq!(
         romfile.path,
         system_directory
-            .join("Test Game (USA, Europe).chd")
+            .join("Test Game (World).chd")
             .as_os_str()
             .to_str()
             .unwrap(),
@@ -104,7 +107,7 @@ async fn test() {
     assert_eq!(
         romfile.path,
         system_directory
-            .join("Test Game (USA, Europe).cue")
+            .join("Test Game (World).cue")
             .as_os_str()
             .to_str()
             .unwrap(),
@@ -11,7 +11,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -22,22 +22,24 @@ async fn test() {
     let tmp_directory = TempDir::new_in(&test_directory).unwrap();
     let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
 
-    let matches = import_dats::subcommand()
-        .get_matches_from(&["import-dats", "tests/Test System (20200721).dat"]);
+    let matches = import_dats::subcommand().get_matches_from(&[
+        "import-dats",
+        "tests/Test System (20231018) (Multiple Tracks).dat",
+    ]);
     import_dats::main(&mut connection, &matches, &progress_bar)
         .await
         .unwrap();
 
-    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue");
+    let romfile_path = tmp_directory.join("Test Game (World) (Multiple Tracks).cue");
     fs::copy(
-        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue"),
+        test_directory.join("Test Game (World) (Multiple Tracks).cue"),
         &romfile_path,
     )
     .await
     .unwrap();
-    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Multiple Tracks).chd");
+    let romfile_path = tmp_directory.join("Test Game (World) (Multiple Tracks).chd");
     fs::copy(
-        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).chd"),
+        test_directory.join("Test Game (World) (Multiple Tracks).chd"),
         &romfile_path,
     )
     .await
@@ -91,7 +93,7 @@ async fn test() {
     assert_eq!(
         romfile.path,
         system_directory
-            .join("Test Game (USA, Europe).7z")
+            .join("Test Game (World).7z")
             .as_os_str()
             .to_str()
             .unwrap(),
@@ -99,13 +101,13 @@ async fn test() {
     assert!(Path::new(&romfile.path).is_file().await);
 
     let rom = roms.get(0).unwrap();
-    assert_eq!(rom.name, "Test Game (USA, Europe) (Track 01).bin");
+    assert_eq!(rom.name, "Test Game (World) (Track 01).bin");
     assert_eq!(rom.romfile_id, Some(romfile.id));
     let rom = roms.get(1).unwrap();
-    assert_eq!(rom.name, "Test Game (USA, Europe) (Track 02).bin");
+    assert_eq!(rom.name, "Test Game (World) (Track 02).bin");
     assert_eq!(rom.romfile_id, Some(romfile.id));
     let rom = roms.get(2).unwrap();
-    assert_eq!(rom.name, "Test Game (USA, Europe).cue");
+    assert_eq!(rom.name, "Test Game (World).cue");
     assert_eq!(rom.romfile_id, Some(romfile.id));
 
     let sevenzip_infos = sevenzip::parse_archive(&progress_bar, &romfile.path).unwrap();
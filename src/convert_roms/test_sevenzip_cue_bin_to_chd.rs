@@ -11,7 +11,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -63,6 +63,8 @@ async fn test() {
         roms_by_game_id,
         romfiles_by_id,
         true,
+        true,
+        &ChdType::Auto,
     )
     .await
     .unwrap();
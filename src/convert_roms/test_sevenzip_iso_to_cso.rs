@@ -20,7 +20,7 @@ async fn test() {
             env::var("PATH").unwrap()
         ),
     );
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -70,6 +70,7 @@ async fn test() {
         roms_by_game_id,
         romfiles_by_id,
         true,
+        true,
     )
     .await
     .unwrap();
@@ -0,0 +1,132 @@
+use super::super::database::*;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20210402) (Headered).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let original_path = test_directory.join("Test Game (USA, Europe) (Headered).rom");
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe) (Headered).rom.7z");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe) (Headered).rom.7z"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let header = find_header_by_system_id(&mut connection, system.id).await;
+
+    import_roms::import_rom(
+        &mut connection,
+        &progress_bar,
+        Some(&system),
+        &header,
+        &romfile_path,
+        &import_roms::ImportOptions {
+            hash_algorithm: HashAlgorithm::Crc,
+            trash: true,
+            force: false,
+            cache: true,
+        },
+    )
+    .await
+    .unwrap();
+    let header = header.unwrap();
+
+    let roms = find_roms_with_romfile_by_system_id(&mut connection, system.id).await;
+    let romfile = find_romfile_by_id(&mut connection, roms[0].romfile_id.unwrap()).await;
+    let rom_id = roms[0].id;
+    let rom_name = roms[0].name.clone();
+    let rom_size = roms[0].size as u64;
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    roms_by_game_id.insert(roms[0].game_id, roms);
+    let mut romfiles_by_id: HashMap<i64, Romfile> = HashMap::new();
+    romfiles_by_id.insert(romfile.id, romfile);
+
+    // when
+    convert_headers(
+        &mut connection,
+        &progress_bar,
+        &system,
+        roms_by_game_id,
+        romfiles_by_id,
+        false,
+        true,
+    )
+    .await
+    .unwrap();
+
+    // then
+    let romfile = find_romfiles(&mut connection).await.remove(0);
+    assert!(romfile.path.ends_with(".7z"));
+    assert_eq!(get_file_size(&romfile.path).await, romfile.size as u64);
+    let archive_infos = sevenzip::parse_archive(&progress_bar, &romfile.path).unwrap();
+    assert_eq!(archive_infos.len(), 1);
+    assert_eq!(archive_infos[0].path, rom_name);
+    assert_eq!(archive_infos[0].size, rom_size);
+    assert!(find_stripped_header_by_rom_id(&mut connection, rom_id)
+        .await
+        .is_some());
+
+    // when
+    let roms = find_roms_with_romfile_by_system_id(&mut connection, system.id).await;
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    roms_by_game_id.insert(roms[0].game_id, roms);
+    let mut romfiles_by_id: HashMap<i64, Romfile> = HashMap::new();
+    romfiles_by_id.insert(romfile.id, romfile);
+    convert_headers(
+        &mut connection,
+        &progress_bar,
+        &system,
+        roms_by_game_id,
+        romfiles_by_id,
+        false,
+        false,
+    )
+    .await
+    .unwrap();
+
+    // then
+    let romfile = find_romfiles(&mut connection).await.remove(0);
+    let archive_infos = sevenzip::parse_archive(&progress_bar, &romfile.path).unwrap();
+    assert_eq!(archive_infos.len(), 1);
+    assert_eq!(archive_infos[0].size, rom_size + header.size as u64);
+    sevenzip::extract_files_from_archive(
+        &progress_bar,
+        &romfile.path,
+        &[&rom_name],
+        &tmp_directory,
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read(tmp_directory.join(&rom_name)).await.unwrap(),
+        fs::read(&original_path).await.unwrap()
+    );
+    assert!(find_stripped_header_by_rom_id(&mut connection, rom_id)
+        .await
+        .is_none());
+}
@@ -0,0 +1,348 @@
+use super::checksum::*;
+use super::config::*;
+use super::progress::*;
+use super::util::*;
+use super::SimpleResult;
+use async_std::path::{Path, PathBuf};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+
+const CSO_MAGIC: &[u8; 4] = b"CISO";
+const ZSO_MAGIC: &[u8; 4] = b"ZISO";
+const HEADER_SIZE: u32 = 0x18;
+const BLOCK_SIZE: u32 = 2048;
+const VERSION: u8 = 1;
+const PLAIN_FLAG: u32 = 0x8000_0000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BlockCompression {
+    Deflate,
+    Lz4,
+}
+
+// streams the decompressed ISO out of a CSO or ZSO file
+pub struct CsoReader {
+    file: fs::File,
+    compression: BlockCompression,
+    total_bytes: u64,
+    block_size: u32,
+    align: u8,
+    index: Vec<u32>,
+    block: usize,
+    buffer: Vec<u8>,
+    buffer_position: usize,
+}
+
+impl CsoReader {
+    pub fn open<P: AsRef<Path>>(path: &P) -> SimpleResult<Self> {
+        let mut file = open_file_sync(path)?;
+
+        let mut header = [0u8; HEADER_SIZE as usize];
+        try_with!(
+            file.read_exact(&mut header),
+            "Failed to read {:?} header",
+            path.as_ref()
+        );
+        let compression = match &header[0..4] {
+            magic if magic == CSO_MAGIC => BlockCompression::Deflate,
+            magic if magic == ZSO_MAGIC => BlockCompression::Lz4,
+            _ => bail!("{:?} is not a valid CSO or ZSO file", path.as_ref()),
+        };
+        let header_size = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let total_bytes = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let block_size = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let version = header[20];
+        let align = header[21];
+        if version > VERSION || block_size == 0 {
+            bail!("Unsupported CSO version {} in {:?}", version, path.as_ref());
+        }
+
+        // some writers declare a header size of 0
+        if header_size > HEADER_SIZE {
+            try_with!(
+                file.seek(SeekFrom::Start(header_size as u64)),
+                "Failed to seek {:?}",
+                path.as_ref()
+            );
+        }
+        let block_count = total_bytes.div_ceil(block_size as u64) as usize;
+        let mut index_bytes = vec![0u8; (block_count + 1) * 4];
+        try_with!(
+            file.read_exact(&mut index_bytes),
+            "Failed to read {:?} index",
+            path.as_ref()
+        );
+        let index = index_bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        Ok(Self {
+            file,
+            compression,
+            total_bytes,
+            block_size,
+            align,
+            index,
+            block: 0,
+            buffer: Vec::new(),
+            buffer_position: 0,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.total_bytes
+    }
+
+    fn read_block(&mut self) -> io::Result<()> {
+        let start = ((self.index[self.block] & !PLAIN_FLAG) as u64) << self.align;
+        let end = ((self.index[self.block + 1] & !PLAIN_FLAG) as u64) << self.align;
+        let plain = self.index[self.block] & PLAIN_FLAG != 0;
+        let expected_size = (self.total_bytes - self.block as u64 * self.block_size as u64)
+            .min(self.block_size as u64) as usize;
+
+        let mut data = vec![0u8; end.saturating_sub(start) as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut data)?;
+
+        self.buffer.clear();
+        self.buffer.resize(expected_size, 0);
+        if plain {
+            // aligned blocks are padded
+            if data.len() < expected_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Block {} is truncated", self.block),
+                ));
+            }
+            self.buffer.copy_from_slice(&data[..expected_size]);
+        } else {
+            match self.compression {
+                BlockCompression::Deflate => {
+                    DeflateDecoder::new(data.as_slice()).read_exact(&mut self.buffer)?;
+                }
+                BlockCompression::Lz4 => {
+                    let size = lz4_flex::block::decompress_into(&data, &mut self.buffer).map_err(
+                        |error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+                    )?;
+                    if size != expected_size {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Block {} has an invalid size", self.block),
+                        ));
+                    }
+                }
+            }
+        }
+
+        self.block += 1;
+        self.buffer_position = 0;
+        Ok(())
+    }
+}
+
+impl Read for CsoReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffer_position == self.buffer.len() {
+            if self.block + 1 >= self.index.len() {
+                return Ok(0);
+            }
+            self.read_block()?;
+        }
+        let size = buf.len().min(self.buffer.len() - self.buffer_position);
+        buf[..size]
+            .copy_from_slice(&self.buffer[self.buffer_position..self.buffer_position + size]);
+        self.buffer_position += size;
+        Ok(size)
+    }
+}
+
+pub fn get_cso_size_and_hashes<P: AsRef<Path>>(
+    progress_bar: &Progress,
+    cso_path: &P,
+    position: usize,
+    total: usize,
+) -> SimpleResult<(u64, Hashes)> {
+    let mut reader = CsoReader::open(cso_path)?;
+    let size = reader.size();
+    compute_reader_size_and_hashes(progress_bar, &mut reader, size, position, total)
+}
+
+pub fn create_cso<P: AsRef<Path>, Q: AsRef<Path>>(
+    progress_bar: &Progress,
+    iso_path: &P,
+    directory: &Q,
+) -> SimpleResult<PathBuf> {
+    let mut cso_path = directory
+        .as_ref()
+        .join(iso_path.as_ref().file_name().unwrap());
+    cso_path.set_extension(CSO_EXTENSION);
+
+    if is_dry_run() {
+        progress_bar.println(format!("Would create {:?}", cso_path.as_os_str()));
+        return Ok(cso_path);
+    }
+
+    progress_bar.println(format!("Creating {:?}", cso_path.file_name().unwrap()));
+
+    let total_bytes = try_with!(
+        std::fs::metadata(iso_path.as_ref()),
+        "Failed to read {:?} metadata",
+        iso_path.as_ref()
+    )
+    .len();
+    let mut iso_file = get_reader_sync(iso_path)?;
+    let block_count = total_bytes.div_ceil(BLOCK_SIZE as u64) as usize;
+    let index_size = (block_count as u64 + 1) * 4;
+
+    // offsets are stored on 31 bits, shift them for images that don't fit
+    let mut align = 0u8;
+    while (HEADER_SIZE as u64 + index_size + total_bytes + ((block_count as u64) << align)) >> align
+        > !PLAIN_FLAG as u64
+    {
+        align += 1;
+    }
+
+    progress_bar.reset();
+    progress_bar.set_message("Creating CSO");
+    progress_bar.set_style(get_bytes_progress_style());
+    progress_bar.set_length(total_bytes);
+
+    let mut cso_file = io::BufWriter::new(try_with!(
+        fs::File::create(&cso_path),
+        "Failed to create {:?}",
+        cso_path
+    ));
+    let mut header: Vec<u8> = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(CSO_MAGIC);
+    header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    header.extend_from_slice(&total_bytes.to_le_bytes());
+    header.extend_from_slice(&BLOCK_SIZE.to_le_bytes());
+    header.extend_from_slice(&[VERSION, align, 0, 0]);
+    try_with!(cso_file.write_all(&header), "Failed to write CSO header");
+    // the index is written once all block offsets are known
+    try_with!(
+        cso_file.write_all(&vec![0u8; index_size as usize]),
+        "Failed to write CSO index"
+    );
+
+    let mut index: Vec<u32> = Vec::with_capacity(block_count + 1);
+    let mut position = HEADER_SIZE as u64 + index_size;
+    let mut block = vec![0u8; BLOCK_SIZE as usize];
+    for i in 0..block_count {
+        let block_size = (total_bytes - i as u64 * BLOCK_SIZE as u64).min(BLOCK_SIZE as u64);
+        let block = &mut block[..block_size as usize];
+        try_with!(
+            iso_file.read_exact(block),
+            "Failed to read {:?}",
+            iso_path.as_ref()
+        );
+
+        let padding = align_position(position, align) - position;
+        try_with!(
+            cso_file.write_all(&vec![0u8; padding as usize]),
+            "Failed to write CSO padding"
+        );
+        position += padding;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        try_with!(encoder.write_all(block), "Failed to compress block");
+        let compressed = try_with!(encoder.finish(), "Failed to compress block");
+        // keep blocks that don't compress as they are
+        let (data, flag) = match compressed.len() < block.len() {
+            true => (compressed.as_slice(), 0),
+            false => (&block[..], PLAIN_FLAG),
+        };
+        index.push((position >> align) as u32 | flag);
+        try_with!(cso_file.write_all(data), "Failed to write CSO block");
+        position += data.len() as u64;
+
+        progress_bar.inc(block_size);
+    }
+    let padding = align_position(position, align) - position;
+    try_with!(
+        cso_file.write_all(&vec![0u8; padding as usize]),
+        "Failed to write CSO padding"
+    );
+    position += padding;
+    index.push((position >> align) as u32);
+
+    let index_bytes: Vec<u8> = index
+        .iter()
+        .flat_map(|offset| offset.to_le_bytes())
+        .collect();
+    try_with!(
+        cso_file.seek(SeekFrom::Start(HEADER_SIZE as u64)),
+        "Failed to seek CSO index"
+    );
+    try_with!(
+        cso_file.write_all(&index_bytes),
+        "Failed to write CSO index"
+    );
+    try_with!(cso_file.flush(), "Failed to write {:?}", cso_path);
+
+    progress_bar.set_message("");
+    progress_bar.set_style(get_none_progress_style());
+
+    Ok(cso_path)
+}
+
+pub fn extract_cso<P: AsRef<Path>, Q: AsRef<Path>>(
+    progress_bar: &Progress,
+    cso_path: &P,
+    directory: &Q,
+) -> SimpleResult<PathBuf> {
+    progress_bar.println(format!(
+        "Extracting {:?}",
+        cso_path.as_ref().file_name().unwrap()
+    ));
+
+    let mut iso_path = directory
+        .as_ref()
+        .join(cso_path.as_ref().file_name().unwrap());
+    iso_path.set_extension(ISO_EXTENSION);
+
+    if is_dry_run() {
+        progress_bar.println(format!("Would create {:?}", iso_path.as_os_str()));
+        return Ok(iso_path);
+    }
+
+    let mut reader = CsoReader::open(cso_path)?;
+
+    progress_bar.reset();
+    progress_bar.set_message("Extracting CSO");
+    progress_bar.set_style(get_bytes_progress_style());
+    progress_bar.set_length(reader.size());
+
+    let mut iso_file = try_with!(
+        fs::File::create(&iso_path),
+        "Failed to create {:?}",
+        iso_path
+    );
+    try_with!(
+        io::copy(&mut reader, &mut progress_bar.wrap_write(&mut iso_file)),
+        "Failed to extract {:?}",
+        cso_path.as_ref()
+    );
+
+    progress_bar.set_message("");
+    progress_bar.set_style(get_none_progress_style());
+
+    Ok(iso_path)
+}
+
+fn align_position(position: u64, align: u8) -> u64 {
+    let alignment = 1u64 << align;
+    position.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod test_cso;
+#[cfg(test)]
+mod test_zso;
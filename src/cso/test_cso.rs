@@ -0,0 +1,40 @@
+use super::*;
+use tempfile::TempDir;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+    let iso_path = test_directory.join("Test Game (USA, Europe).iso");
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = Path::new(tmp_directory.path());
+
+    // when
+    let cso_path = create_cso(&progress_bar, &iso_path, &tmp_directory).unwrap();
+
+    // then
+    let (iso_size, iso_hashes) =
+        compute_size_and_hashes(&progress_bar, &iso_path, &None, &[], 1, 1).unwrap();
+    let (size, hashes) = get_cso_size_and_hashes(&progress_bar, &cso_path, 1, 1).unwrap();
+    assert_eq!(size, iso_size);
+    assert_eq!(hashes.sha1, iso_hashes.sha1);
+
+    let (size, hashes) = get_cso_size_and_hashes(
+        &progress_bar,
+        &test_directory.join("Test Game (USA, Europe).cso"),
+        1,
+        1,
+    )
+    .unwrap();
+    assert_eq!(size, iso_size);
+    assert_eq!(hashes.sha1, iso_hashes.sha1);
+
+    let extracted_directory = tmp_directory.join("extracted");
+    std::fs::create_dir(&extracted_directory).unwrap();
+    let extracted_path = extract_cso(&progress_bar, &cso_path, &extracted_directory).unwrap();
+    let (size, hashes) =
+        compute_size_and_hashes(&progress_bar, &extracted_path, &None, &[], 1, 1).unwrap();
+    assert_eq!(size, iso_size);
+    assert_eq!(hashes.sha1, iso_hashes.sha1);
+}
@@ -0,0 +1,48 @@
+use super::*;
+use tempfile::TempDir;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+    let iso_path = test_directory.join("Test Game (USA, Europe).iso");
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let zso_path = Path::new(tmp_directory.path()).join("Test Game (USA, Europe).zso");
+
+    let iso = std::fs::read(&iso_path).unwrap();
+    let blocks: Vec<&[u8]> = iso.chunks(BLOCK_SIZE as usize).collect();
+    let mut header: Vec<u8> = Vec::new();
+    header.extend_from_slice(ZSO_MAGIC);
+    header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    header.extend_from_slice(&(iso.len() as u64).to_le_bytes());
+    header.extend_from_slice(&BLOCK_SIZE.to_le_bytes());
+    header.extend_from_slice(&[VERSION, 0, 0, 0]);
+    let mut data: Vec<u8> = Vec::new();
+    let mut index: Vec<u32> = Vec::new();
+    let data_offset = HEADER_SIZE as usize + (blocks.len() + 1) * 4;
+    for (i, block) in blocks.iter().enumerate() {
+        // store every other block uncompressed
+        if i % 2 == 0 {
+            index.push((data_offset + data.len()) as u32);
+            data.extend(lz4_flex::block::compress(block));
+        } else {
+            index.push((data_offset + data.len()) as u32 | PLAIN_FLAG);
+            data.extend_from_slice(block);
+        }
+    }
+    index.push((data_offset + data.len()) as u32);
+    let mut zso = header;
+    zso.extend(index.iter().flat_map(|offset| offset.to_le_bytes()));
+    zso.extend(data);
+    std::fs::write(&zso_path, zso).unwrap();
+
+    // when
+    let (size, hashes) = get_cso_size_and_hashes(&progress_bar, &zso_path, 1, 1).unwrap();
+
+    // then
+    let (iso_size, iso_hashes) =
+        compute_size_and_hashes(&progress_bar, &iso_path, &None, &[], 1, 1).unwrap();
+    assert_eq!(size, iso_size);
+    assert_eq!(hashes.sha1, iso_hashes.sha1);
+}
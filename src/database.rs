@@ -89,6 +89,59 @@ pub async fn close_connection(pool: &SqlitePool) {
     .expect("Failed to optimize the database");
 }
 
+pub async fn create_dry_run_journal(connection: &mut SqliteConnection) {
+    let table_names: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT name
+        FROM sqlite_master
+        WHERE type = 'table'
+        AND name NOT LIKE 'sqlite_%'
+        AND name NOT LIKE '_sqlx_%'
+        ORDER BY name
+        ",
+    )
+    .fetch_all(&mut *connection)
+    .await
+    .expect("Error while finding tables");
+
+    // temporary objects only live as long as the connection and are rolled back with the changes
+    let mut sql = String::from(
+        "CREATE TEMP TABLE dry_run_journal (table_name TEXT NOT NULL, operation TEXT NOT NULL);",
+    );
+    for table_name in table_names {
+        for operation in ["INSERT", "UPDATE", "DELETE"] {
+            sql.push_str(&format!(
+                "
+                CREATE TEMP TRIGGER dry_run_{table_name}_{operation}
+                AFTER {operation} ON main.{table_name}
+                BEGIN
+                    INSERT INTO dry_run_journal (table_name, operation)
+                    VALUES ('{table_name}', '{operation}');
+                END;
+                "
+            ));
+        }
+    }
+    connection
+        .execute(sql.as_str())
+        .await
+        .expect("Failed to create the dry-run journal");
+}
+
+pub async fn find_dry_run_journal(connection: &mut SqliteConnection) -> Vec<(String, String, i64)> {
+    sqlx::query_as::<_, (String, String, i64)>(
+        "
+        SELECT table_name, operation, COUNT(*)
+        FROM dry_run_journal
+        GROUP BY table_name, operation
+        ORDER BY table_name, operation
+        ",
+    )
+    .fetch_all(connection)
+    .await
+    .expect("Error while finding the dry-run journal")
+}
+
 pub async fn create_system_from_xml(
     connection: &mut SqliteConnection,
     system_xml: &SystemXml,
@@ -197,6 +250,30 @@ pub async fn update_system_merging(connection: &mut SqliteConnection, id: i64, m
     .unwrap_or_else(|_| panic!("Error while updating system with id {} merging", merging));
 }
 
+pub async fn update_system_preferred_format(
+    connection: &mut SqliteConnection,
+    id: i64,
+    preferred_format: Option<&str>,
+) {
+    sqlx::query!(
+        "
+        UPDATE systems
+        SET preferred_format = ?
+        WHERE id = ?
+        ",
+        preferred_format,
+        id,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while updating system with id {} preferred format",
+            id
+        )
+    });
+}
+
 pub async fn find_systems(connection: &mut SqliteConnection) -> Vec<System> {
     sqlx::query_as!(
         System,
@@ -681,7 +758,147 @@ pub async fn find_games_by_system_id(
     .unwrap_or_else(|_| panic!("Error while finding games with system id {}", system_id))
 }
 
-#[cfg(feature = "ird")]
+// escape the LIKE wildcards so that names are matched literally
+#[cfg(feature = "server")]
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// the name is bound by the caller, the other values are safe to inline
+#[cfg(feature = "server")]
+fn get_game_filter_conditions(filter: &GameFilter) -> String {
+    let mut conditions = String::new();
+    if filter.name.is_some() {
+        conditions.push_str(" AND name LIKE '%' || ? || '%' ESCAPE '\\'");
+    }
+    if let Some(complete) = filter.complete {
+        conditions.push_str(&format!(" AND complete = {}", complete as i64));
+    }
+    if let Some(ignored) = filter.ignored {
+        conditions.push_str(&format!(
+            " AND sorting {} {}",
+            if ignored { "=" } else { "!=" },
+            Sorting::Ignored as i64
+        ));
+    }
+    if let Some(one_region) = filter.one_region {
+        conditions.push_str(&format!(
+            " AND sorting {} {}",
+            if one_region { "=" } else { "!=" },
+            Sorting::OneRegion as i64
+        ));
+    }
+    conditions
+}
+
+#[cfg(feature = "server")]
+pub async fn find_games_by_system_id_and_filter(
+    connection: &mut SqliteConnection,
+    system_id: i64,
+    filter: &GameFilter,
+    offset: i64,
+    limit: i64,
+) -> Vec<Game> {
+    let sql = format!(
+        "
+        SELECT *
+        FROM games
+        WHERE system_id = ?{}
+        ORDER BY name
+        LIMIT ?
+        OFFSET ?
+        ",
+        get_game_filter_conditions(filter)
+    );
+    let mut query = sqlx::query_as::<_, Game>(&sql).bind(system_id);
+    if let Some(name) = &filter.name {
+        query = query.bind(escape_like(name));
+    }
+    query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(connection)
+        .await
+        .unwrap_or_else(|_| panic!("Error while finding games with system id {}", system_id))
+}
+
+#[cfg(feature = "server")]
+pub async fn count_games_by_system_id_and_filter(
+    connection: &mut SqliteConnection,
+    system_id: i64,
+    filter: &GameFilter,
+) -> i64 {
+    let sql = format!(
+        "
+        SELECT COUNT(*)
+        FROM games
+        WHERE system_id = ?{}
+        ",
+        get_game_filter_conditions(filter)
+    );
+    let mut query = sqlx::query_as::<_, (i64,)>(&sql).bind(system_id);
+    if let Some(name) = &filter.name {
+        query = query.bind(escape_like(name));
+    }
+    query
+        .fetch_one(connection)
+        .await
+        .unwrap_or_else(|_| panic!("Error while counting games with system id {}", system_id))
+        .0
+}
+
+#[cfg(feature = "server")]
+pub async fn find_games_by_name_or_hash(
+    connection: &mut SqliteConnection,
+    name: Option<&str>,
+    hash: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> Vec<Game> {
+    let mut conditions = String::new();
+    if name.is_some() {
+        conditions.push_str(" AND name LIKE '%' || ? || '%' ESCAPE '\\'");
+    }
+    if hash.is_some() {
+        conditions.push_str(
+            "
+        AND id IN (
+            SELECT game_id
+            FROM roms
+            WHERE crc = ? OR md5 = ? OR sha1 = ?
+        )",
+        );
+    }
+    let sql = format!(
+        "
+        SELECT *
+        FROM games
+        WHERE 1 = 1{}
+        ORDER BY name
+        LIMIT ?
+        OFFSET ?
+        ",
+        conditions
+    );
+    let mut query = sqlx::query_as::<_, Game>(&sql);
+    if let Some(name) = name {
+        query = query.bind(escape_like(name));
+    }
+    if let Some(hash) = hash {
+        let hash = hash.to_lowercase();
+        query = query.bind(hash.clone()).bind(hash.clone()).bind(hash);
+    }
+    query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(connection)
+        .await
+        .expect("Error while finding games")
+}
+
 pub async fn find_wanted_games_by_system_id(
     connection: &mut SqliteConnection,
     system_id: i64,
@@ -702,7 +919,6 @@ pub async fn find_wanted_games_by_system_id(
     .unwrap_or_else(|_| panic!("Error while finding games with system id {}", system_id))
 }
 
-#[cfg(test)]
 pub async fn find_games_by_ids(connection: &mut SqliteConnection, ids: &[i64]) -> Vec<Game> {
     let sql = format!(
         "
@@ -921,7 +1137,7 @@ pub async fn create_rom_from_xml(
     game_id: i64,
     parent_id: Option<i64>,
 ) -> i64 {
-    let crc = rom_xml.crc.as_ref().unwrap().to_lowercase();
+    let crc = rom_xml.crc.as_ref().map(|crc| crc.to_lowercase());
     let md5 = rom_xml.md5.as_ref().map(|md5| md5.to_lowercase());
     let sha1 = rom_xml.sha1.as_ref().map(|sha1| sha1.to_lowercase());
     sqlx::query!(
@@ -981,7 +1197,7 @@ pub async fn update_rom_from_xml(
     game_id: i64,
     parent_id: Option<i64>,
 ) {
-    let crc = rom_xml.crc.as_ref().unwrap().to_lowercase();
+    let crc = rom_xml.crc.as_ref().map(|crc| crc.to_lowercase());
     let md5 = rom_xml.md5.as_ref().map(|md5| md5.to_lowercase());
     let sha1 = rom_xml.sha1.as_ref().map(|sha1| sha1.to_lowercase());
     sqlx::query!(
@@ -1222,6 +1438,96 @@ pub async fn find_roms_with_romfile_by_game_ids(
         .expect("Error while finding roms with romfile")
 }
 
+pub async fn find_roms_with_romfile_by_size_and_crc(
+    connection: &mut SqliteConnection,
+    size: u64,
+    crc: &str,
+) -> Vec<Rom> {
+    let size = i64::try_from(size).unwrap();
+    let crc = crc.to_lowercase();
+    sqlx::query_as!(
+        Rom,
+        "
+        SELECT *
+        FROM roms
+        WHERE romfile_id IS NOT NULL
+        AND size = ?
+        AND crc = ?
+        ORDER BY name
+        ",
+        size,
+        crc,
+    )
+    .fetch_all(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while finding roms with romfile with size {} and CRC {}",
+            size, crc
+        )
+    })
+}
+
+pub async fn find_roms_with_romfile_by_size_and_md5(
+    connection: &mut SqliteConnection,
+    size: u64,
+    md5: &str,
+) -> Vec<Rom> {
+    let size = i64::try_from(size).unwrap();
+    let md5 = md5.to_lowercase();
+    sqlx::query_as!(
+        Rom,
+        "
+        SELECT *
+        FROM roms
+        WHERE romfile_id IS NOT NULL
+        AND size = ?
+        AND md5 = ?
+        ORDER BY name
+        ",
+        size,
+        md5,
+    )
+    .fetch_all(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while finding roms with romfile with size {} and MD5 {}",
+            size, md5
+        )
+    })
+}
+
+pub async fn find_roms_with_romfile_by_size_and_sha1(
+    connection: &mut SqliteConnection,
+    size: u64,
+    sha1: &str,
+) -> Vec<Rom> {
+    let size = i64::try_from(size).unwrap();
+    let sha1 = sha1.to_lowercase();
+    sqlx::query_as!(
+        Rom,
+        "
+        SELECT *
+        FROM roms
+        WHERE romfile_id IS NOT NULL
+        AND size = ?
+        AND sha1 = ?
+        ORDER BY name
+        ",
+        size,
+        sha1,
+    )
+    .fetch_all(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while finding roms with romfile with size {} and SHA1 {}",
+            size, sha1
+        )
+    })
+}
+
 pub async fn find_roms_with_romfile_by_size_and_crc_and_system_id(
     connection: &mut SqliteConnection,
     size: i64,
@@ -2531,6 +2837,20 @@ pub async fn update_header_from_xml(
     .unwrap_or_else(|_| panic!("Error while updating header with id {}", id));
 }
 
+pub async fn find_headers(connection: &mut SqliteConnection) -> Vec<Header> {
+    sqlx::query_as!(
+        Header,
+        "
+        SELECT *
+        FROM headers
+        ORDER BY id
+        ",
+    )
+    .fetch_all(connection)
+    .await
+    .expect("Error while finding headers")
+}
+
 pub async fn find_header_by_system_id(
     connection: &mut SqliteConnection,
     system_id: i64,
@@ -2601,6 +2921,299 @@ pub async fn delete_rules_by_header_id(connection: &mut SqliteConnection, header
     .unwrap_or_else(|_| panic!("Error while deleting rules with header_id {}", header_id));
 }
 
+pub async fn create_or_update_cached_hashes(
+    connection: &mut SqliteConnection,
+    path: &str,
+    entry: &str,
+    cached_hashes: &CachedHashes,
+) {
+    sqlx::query!(
+        "
+        INSERT INTO cached_hashes (path, entry, file_size, file_mtime, header_id, size, crc, md5, sha1)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(path, entry) DO UPDATE
+        SET file_size = excluded.file_size,
+        file_mtime = excluded.file_mtime,
+        header_id = excluded.header_id,
+        size = excluded.size,
+        crc = excluded.crc,
+        md5 = excluded.md5,
+        sha1 = excluded.sha1
+        ",
+        path,
+        entry,
+        cached_hashes.file_size,
+        cached_hashes.file_mtime,
+        cached_hashes.header_id,
+        cached_hashes.size,
+        cached_hashes.crc,
+        cached_hashes.md5,
+        cached_hashes.sha1,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while creating or updating cached hashes for {}", path));
+}
+
+pub async fn find_cached_hashes_by_path_and_entry(
+    connection: &mut SqliteConnection,
+    path: &str,
+    entry: &str,
+) -> Option<CachedHashes> {
+    sqlx::query_as!(
+        CachedHashes,
+        "
+        SELECT file_size, file_mtime, header_id, size, crc, md5, sha1
+        FROM cached_hashes
+        WHERE path = ?
+        AND entry = ?
+        ",
+        path,
+        entry,
+    )
+    .fetch_optional(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while finding cached hashes for {}", path))
+}
+
+pub async fn find_cached_hashes_paths(connection: &mut SqliteConnection) -> Vec<String> {
+    sqlx::query!(
+        "
+        SELECT DISTINCT path
+        FROM cached_hashes
+        ",
+    )
+    .fetch_all(connection)
+    .await
+    .expect("Error while finding cached hashes paths")
+    .into_iter()
+    .map(|row| row.path)
+    .collect()
+}
+
+pub async fn update_cached_hashes_path(
+    connection: &mut SqliteConnection,
+    old_path: &str,
+    new_path: &str,
+) {
+    delete_cached_hashes_by_path(connection, new_path).await;
+    sqlx::query!(
+        "
+        UPDATE cached_hashes
+        SET path = ?
+        WHERE path = ?
+        ",
+        new_path,
+        old_path,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while updating cached hashes for {}", old_path));
+}
+
+pub async fn delete_cached_hashes_by_path(connection: &mut SqliteConnection, path: &str) {
+    sqlx::query!(
+        "
+        DELETE FROM cached_hashes
+        WHERE path = ?
+        ",
+        path,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while deleting cached hashes for {}", path));
+}
+
+pub async fn create_or_update_stripped_header(
+    connection: &mut SqliteConnection,
+    rom_id: i64,
+    data: &[u8],
+) {
+    sqlx::query!(
+        "
+        INSERT INTO stripped_headers (rom_id, data)
+        VALUES (?, ?)
+        ON CONFLICT(rom_id) DO UPDATE
+        SET data = excluded.data
+        ",
+        rom_id,
+        data,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while creating or updating stripped header for rom id {}",
+            rom_id
+        )
+    });
+}
+
+pub async fn find_stripped_header_by_rom_id(
+    connection: &mut SqliteConnection,
+    rom_id: i64,
+) -> Option<Vec<u8>> {
+    sqlx::query!(
+        "
+        SELECT data
+        FROM stripped_headers
+        WHERE rom_id = ?
+        ",
+        rom_id,
+    )
+    .fetch_optional(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while finding stripped header for rom id {}", rom_id))
+    .map(|row| row.data)
+}
+
+pub async fn delete_stripped_header_by_rom_id(connection: &mut SqliteConnection, rom_id: i64) {
+    sqlx::query!(
+        "
+        DELETE FROM stripped_headers
+        WHERE rom_id = ?
+        ",
+        rom_id,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while deleting stripped header for rom id {}", rom_id));
+}
+
+pub async fn create_operation(
+    connection: &mut SqliteConnection,
+    run_id: i64,
+    operation: OperationType,
+    old_path: Option<&str>,
+    new_path: Option<&str>,
+    romfile_id: Option<i64>,
+) {
+    let operation = operation as i8;
+    sqlx::query!(
+        "
+        INSERT INTO operations (run_id, operation, old_path, new_path, romfile_id)
+        VALUES (?, ?, ?, ?, ?)
+        ",
+        run_id,
+        operation,
+        old_path,
+        new_path,
+        romfile_id,
+    )
+    .execute(connection)
+    .await
+    .expect("Error while creating operation");
+}
+
+pub async fn find_last_run_id(connection: &mut SqliteConnection) -> Option<i64> {
+    sqlx::query!(
+        "
+        SELECT MAX(run_id) AS run_id
+        FROM operations
+        ",
+    )
+    .fetch_one(connection)
+    .await
+    .expect("Error while finding last run id")
+    .run_id
+}
+
+// runs are tied to the connection, so that every command and server job gets its own
+pub async fn create_run(connection: &mut SqliteConnection) -> i64 {
+    // ids are never reused, even when concurrent connections create runs
+    let run_id = sqlx::query!(
+        "
+        INSERT INTO runs DEFAULT VALUES
+        ",
+    )
+    .execute(&mut *connection)
+    .await
+    .expect("Error while creating run")
+    .last_insert_rowid();
+    connection
+        .execute(
+            format!(
+                "
+                CREATE TEMP TABLE IF NOT EXISTS current_run (run_id INTEGER NOT NULL);
+                DELETE FROM current_run;
+                INSERT INTO current_run (run_id) VALUES ({run_id});
+                "
+            )
+            .as_str(),
+        )
+        .await
+        .expect("Error while creating run");
+    run_id
+}
+
+pub async fn find_current_run_id(connection: &mut SqliteConnection) -> Option<i64> {
+    // the table only exists once a run was created on this connection
+    sqlx::query_scalar(
+        "
+        SELECT run_id
+        FROM current_run
+        ",
+    )
+    .fetch_optional(connection)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn find_operations_by_run_id(
+    connection: &mut SqliteConnection,
+    run_id: i64,
+) -> Vec<Operation> {
+    sqlx::query_as!(
+        Operation,
+        "
+        SELECT operation, old_path, new_path, romfile_id
+        FROM operations
+        WHERE run_id = ?
+        ORDER BY id DESC
+        ",
+        run_id,
+    )
+    .fetch_all(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while finding operations with run id {}", run_id))
+}
+
+pub async fn find_last_backup_path_by_path(
+    connection: &mut SqliteConnection,
+    path: &str,
+) -> Option<String> {
+    let operation = OperationType::Delete as i8;
+    sqlx::query!(
+        "
+        SELECT new_path
+        FROM operations
+        WHERE operation = ?
+        AND old_path = ?
+        ORDER BY id DESC
+        LIMIT 1
+        ",
+        operation,
+        path,
+    )
+    .fetch_optional(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while finding backup path for {}", path))
+    .and_then(|row| row.new_path)
+}
+
+pub async fn delete_operations_by_run_id(connection: &mut SqliteConnection, run_id: i64) {
+    sqlx::query!(
+        "
+        DELETE FROM operations
+        WHERE run_id = ?
+        ",
+        run_id,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while deleting operations with run id {}", run_id));
+}
+
 pub async fn create_setting(connection: &mut SqliteConnection, key: &str, value: Option<String>) {
     sqlx::query!(
         "
@@ -2658,3 +3271,105 @@ pub async fn find_setting_by_key(connection: &mut SqliteConnection, key: &str) -
     .await
     .unwrap_or_else(|_| panic!("Error while finding setting with key {}", key))
 }
+
+pub async fn create_system_setting(
+    connection: &mut SqliteConnection,
+    system_id: i64,
+    key: &str,
+    value: Option<String>,
+) {
+    sqlx::query!(
+        "
+        INSERT INTO system_settings (system_id, key, value)
+        VALUES (?, ?, ?)
+        ",
+        system_id,
+        key,
+        value,
+    )
+    .execute(connection)
+    .await
+    .expect("Error while creating system setting");
+}
+
+pub async fn update_system_setting(
+    connection: &mut SqliteConnection,
+    id: i64,
+    value: Option<String>,
+) {
+    sqlx::query!(
+        "
+        UPDATE system_settings
+        SET value = ?
+        WHERE id = ?
+        ",
+        value,
+        id,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while updating system setting with id {}", id));
+}
+
+pub async fn find_system_settings_by_system_id(
+    connection: &mut SqliteConnection,
+    system_id: i64,
+) -> Vec<SystemSetting> {
+    sqlx::query_as!(
+        SystemSetting,
+        "
+        SELECT *
+        FROM system_settings
+        WHERE system_id = ?
+        ORDER BY key
+        ",
+        system_id,
+    )
+    .fetch_all(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while finding system settings with system id {}",
+            system_id
+        )
+    })
+}
+
+pub async fn find_system_setting_by_key_and_system_id(
+    connection: &mut SqliteConnection,
+    key: &str,
+    system_id: i64,
+) -> Option<SystemSetting> {
+    sqlx::query_as!(
+        SystemSetting,
+        "
+        SELECT *
+        FROM system_settings
+        WHERE key = ?
+        AND system_id = ?
+        ",
+        key,
+        system_id,
+    )
+    .fetch_optional(connection)
+    .await
+    .unwrap_or_else(|_| {
+        panic!(
+            "Error while finding system setting with key {} and system id {}",
+            key, system_id
+        )
+    })
+}
+
+pub async fn delete_system_setting_by_id(connection: &mut SqliteConnection, id: i64) {
+    sqlx::query!(
+        "
+        DELETE FROM system_settings
+        WHERE id = ?
+        ",
+        id,
+    )
+    .execute(connection)
+    .await
+    .unwrap_or_else(|_| panic!("Error while deleting system setting with id {}", id));
+}
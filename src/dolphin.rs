@@ -1,49 +1,107 @@
 use super::config::*;
 use super::progress::*;
+use super::util::*;
 use super::SimpleResult;
 use async_std::path::{Path, PathBuf};
-use indicatif::ProgressBar;
+use std::io::prelude::*;
+use std::io::SeekFrom;
 use std::process::Command;
 use std::time::Duration;
 
 pub const RVZ_BLOCK_SIZE_RANGE: [usize; 2] = [32, 2048];
 pub const RVZ_COMPRESSION_LEVEL_RANGE: [usize; 2] = [1, 22];
 
-pub fn create_rvz<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+// dolphin's defaults
+const GCZ_BLOCK_SIZE: usize = 32 * 1024;
+const WIA_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+const WIA_COMPRESSION_ALGORITHM: &str = "lzma2";
+const WIA_COMPRESSION_LEVEL: usize = 5;
+
+const NKIT_MAGIC: &[u8; 4] = b"NKIT";
+const NKIT_MAGIC_OFFSET: u64 = 0x200;
+
+// WBFS can be read but not written by dolphin-tool
+pub enum DolphinFormat<'a> {
+    Gcz,
+    Rvz {
+        compression_algorithm: &'a RvzCompressionAlgorithm,
+        compression_level: usize,
+        block_size: usize,
+    },
+    Wia,
+}
+
+impl<'a> DolphinFormat<'a> {
+    pub fn extension(&self) -> &str {
+        match self {
+            DolphinFormat::Gcz => GCZ_EXTENSION,
+            DolphinFormat::Rvz { .. } => RVZ_EXTENSION,
+            DolphinFormat::Wia => WIA_EXTENSION,
+        }
+    }
+}
+
+pub fn create_image<P: AsRef<Path>, Q: AsRef<Path>>(
+    progress_bar: &Progress,
     iso_path: &P,
     directory: &Q,
-    compression_algorithm: &RvzCompressionAlgorithm,
-    compression_level: usize,
-    block_size: usize,
+    format: &DolphinFormat,
 ) -> SimpleResult<PathBuf> {
-    progress_bar.set_message("Creating RVZ");
+    let extension = format.extension();
+    progress_bar.set_message(format!("Creating {}", extension.to_uppercase()));
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
 
-    let mut rvz_path = directory
+    let mut image_path = directory
         .as_ref()
         .join(iso_path.as_ref().file_name().unwrap());
-    rvz_path.set_extension(RVZ_EXTENSION);
+    image_path.set_extension(extension);
 
-    progress_bar.println(format!("Creating {:?}", rvz_path.file_name().unwrap()));
+    if is_dry_run() {
+        progress_bar.println(format!("Would create {:?}", image_path.as_os_str()));
+        progress_bar.set_message("");
+        progress_bar.disable_steady_tick();
+        return Ok(image_path);
+    }
 
-    let output = Command::new("dolphin-tool")
-        .arg("convert")
-        .arg("-f")
-        .arg("rvz")
-        .arg("-c")
-        .arg(compression_algorithm.to_string())
-        .arg("-l")
-        .arg(compression_level.to_string())
-        .arg("-b")
-        .arg((block_size * 1024).to_string())
+    progress_bar.println(format!("Creating {:?}", image_path.file_name().unwrap()));
+
+    let mut command = Command::new("dolphin-tool");
+    command.arg("convert").arg("-f").arg(extension);
+    match format {
+        DolphinFormat::Gcz => {
+            command.arg("-b").arg(GCZ_BLOCK_SIZE.to_string());
+        }
+        DolphinFormat::Rvz {
+            compression_algorithm,
+            compression_level,
+            block_size,
+        } => {
+            command
+                .arg("-c")
+                .arg(compression_algorithm.to_string())
+                .arg("-l")
+                .arg(compression_level.to_string())
+                .arg("-b")
+                .arg((block_size * 1024).to_string());
+        }
+        DolphinFormat::Wia => {
+            command
+                .arg("-c")
+                .arg(WIA_COMPRESSION_ALGORITHM)
+                .arg("-l")
+                .arg(WIA_COMPRESSION_LEVEL.to_string())
+                .arg("-b")
+                .arg(WIA_BLOCK_SIZE.to_string());
+        }
+    }
+    let output = command
         .arg("-i")
         .arg(iso_path.as_ref())
         .arg("-o")
-        .arg(&rvz_path)
+        .arg(&image_path)
         .output()
-        .expect("Failed to create RVZ");
+        .expect("Failed to create image");
 
     if !output.status.success() {
         bail!(String::from_utf8(output.stderr).unwrap().as_str())
@@ -52,26 +110,56 @@ pub fn create_rvz<P: AsRef<Path>, Q: AsRef<Path>>(
     progress_bar.set_message("");
     progress_bar.disable_steady_tick();
 
-    Ok(rvz_path)
+    Ok(image_path)
+}
+
+pub fn extract_image<P: AsRef<Path>, Q: AsRef<Path>>(
+    progress_bar: &Progress,
+    image_path: &P,
+    directory: &Q,
+) -> SimpleResult<PathBuf> {
+    if is_dry_run() {
+        let mut iso_path = directory
+            .as_ref()
+            .join(image_path.as_ref().file_name().unwrap());
+        iso_path.set_extension(ISO_EXTENSION);
+        progress_bar.println(format!(
+            "Would extract {:?} to {:?}",
+            image_path.as_ref().as_os_str(),
+            directory.as_ref().as_os_str()
+        ));
+        return Ok(iso_path);
+    }
+    extract_image_for_hashing(progress_bar, image_path, directory)
 }
 
-pub fn extract_rvz<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
-    rvz_path: &P,
+// hashing only reads the image, so it also runs in dry-run mode
+pub fn extract_image_for_hashing<P: AsRef<Path>, Q: AsRef<Path>>(
+    progress_bar: &Progress,
+    image_path: &P,
     directory: &Q,
 ) -> SimpleResult<PathBuf> {
-    progress_bar.set_message("Extracting RVZ");
+    progress_bar.set_message(format!(
+        "Extracting {}",
+        image_path
+            .as_ref()
+            .extension()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_uppercase()
+    ));
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
 
     progress_bar.println(format!(
         "Extracting {:?}",
-        rvz_path.as_ref().file_name().unwrap()
+        image_path.as_ref().file_name().unwrap()
     ));
 
     let mut iso_path = directory
         .as_ref()
-        .join(rvz_path.as_ref().file_name().unwrap());
+        .join(image_path.as_ref().file_name().unwrap());
     iso_path.set_extension(ISO_EXTENSION);
 
     let output = Command::new("dolphin-tool")
@@ -79,11 +167,11 @@ pub fn extract_rvz<P: AsRef<Path>, Q: AsRef<Path>>(
         .arg("-f")
         .arg("iso")
         .arg("-i")
-        .arg(rvz_path.as_ref())
+        .arg(image_path.as_ref())
         .arg("-o")
         .arg(&iso_path)
         .output()
-        .expect("Failed to extract RVZ");
+        .expect("Failed to extract image");
 
     if !output.status.success() {
         bail!(String::from_utf8(output.stderr).unwrap().as_str())
@@ -94,3 +182,21 @@ pub fn extract_rvz<P: AsRef<Path>, Q: AsRef<Path>>(
 
     Ok(iso_path)
 }
+
+// NKit images are trimmed and never match the DAT until they are restored
+pub fn is_nkit<P: AsRef<Path>>(iso_path: &P) -> SimpleResult<bool> {
+    let mut iso_file = open_file_sync(iso_path)?;
+    let mut magic = [0u8; 4];
+    try_with!(
+        iso_file.seek(SeekFrom::Start(NKIT_MAGIC_OFFSET)),
+        "Failed to seek {:?}",
+        iso_path.as_ref()
+    );
+    if iso_file.read_exact(&mut magic).is_err() {
+        return Ok(false);
+    }
+    Ok(&magic == NKIT_MAGIC)
+}
+
+#[cfg(test)]
+mod test_nkit;
@@ -0,0 +1,23 @@
+use super::*;
+use tempfile::TempDir;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = Path::new(tmp_directory.path());
+    let iso_path = test_directory.join("Test Game (USA, Europe).iso");
+    let mut nkit_data = vec![0u8; NKIT_MAGIC_OFFSET as usize];
+    nkit_data.extend_from_slice(NKIT_MAGIC);
+    let nkit_path = tmp_directory.join("Test Game (USA, Europe).nkit.iso");
+    std::fs::write(&nkit_path, nkit_data).unwrap();
+
+    // when
+    let iso_is_nkit = is_nkit(&iso_path).unwrap();
+    let nkit_is_nkit = is_nkit(&nkit_path).unwrap();
+
+    // then
+    assert!(!iso_is_nkit);
+    assert!(nkit_is_nkit);
+}
@@ -1,13 +1,13 @@
 use super::database::*;
 use super::import_dats::{import_dat, parse_dat};
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::util::*;
 use super::SimpleResult;
 use async_std::task;
 use cfg_if::cfg_if;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use phf::phf_map;
 use quick_xml::de;
 use rayon::prelude::*;
@@ -139,7 +139,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     if matches.get_flag("NOINTRO") {
         if matches.get_flag("UPDATE") {
@@ -178,7 +178,7 @@ pub async fn main(
 
 async fn update_nointro_dats(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     base_url: &str,
     all: bool,
 ) -> SimpleResult<()> {
@@ -207,7 +207,7 @@ async fn update_nointro_dats(
 
 async fn download_redump_dats(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     base_url: &str,
     all: bool,
 ) -> SimpleResult<()> {
@@ -242,7 +242,7 @@ async fn download_redump_dats(
 
 async fn update_redump_dats(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     base_url: &str,
     all: bool,
     force: bool,
@@ -256,7 +256,7 @@ async fn update_redump_dats(
 
 async fn download_redump_dat(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     base_url: &str,
     system_name: &str,
     force: bool,
@@ -0,0 +1,200 @@
+use super::config::{is_dry_run, CHD_EXTENSION};
+use super::database::*;
+use super::model::*;
+use super::progress::*;
+use super::prompt::*;
+use super::util::*;
+use super::SimpleResult;
+use async_std::fs;
+use async_std::path::{Path, PathBuf};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use quick_xml::se::Serializer;
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnection;
+use std::collections::HashMap;
+
+const DAT_PROLOG: &str = "<?xml version=\"1.0\"?>\n<!DOCTYPE datafile PUBLIC \"-//Logiqx//DTD ROM Management Datafile//EN\" \"http://www.logiqx.com/Dats/datafile.dtd\">\n";
+
+pub fn subcommand() -> Command {
+    Command::new("export-dats")
+        .about("Export Logiqx fixdats of missing games")
+        .arg(
+            Arg::new("ALL")
+                .short('a')
+                .long("all")
+                .help("Export fixdats for all systems")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("DIRECTORY")
+                .short('d')
+                .long("directory")
+                .help("Set the output directory")
+                .required(false)
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+}
+
+pub async fn main(
+    connection: &mut SqliteConnection,
+    matches: &ArgMatches,
+    progress_bar: &Progress,
+) -> SimpleResult<()> {
+    let systems = prompt_for_systems(connection, None, false, matches.get_flag("ALL")).await?;
+    let directory = match matches.get_one::<PathBuf>("DIRECTORY") {
+        Some(directory) => directory.clone(),
+        None => PathBuf::from("."),
+    };
+    if !directory.is_dir().await {
+        create_directory(progress_bar, &directory, true).await?;
+    }
+    for system in systems {
+        progress_bar.println(format!("Processing \"{}\"", system.name));
+        export_system(connection, progress_bar, &system, &directory).await?;
+        progress_bar.println("");
+    }
+    Ok(())
+}
+
+async fn export_system(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: &System,
+    directory: &Path,
+) -> SimpleResult<PathBuf> {
+    let datfile_xml = build_fixdat(connection, system).await;
+
+    let dat_path = directory.join(format!("{} (Fixdat).dat", system.name));
+    let mut dat = String::from(DAT_PROLOG);
+    let mut serializer = try_with!(
+        Serializer::with_root(&mut dat, Some("datafile")),
+        "Failed to create serializer"
+    );
+    serializer.indent('\t', 1);
+    try_with!(
+        datfile_xml.serialize(serializer),
+        "Failed to serialize fixdat"
+    );
+    dat.push('\n');
+    if is_dry_run() {
+        progress_bar.println(format!("Would create {:?}", dat_path.as_os_str()));
+        return Ok(dat_path);
+    }
+    try_with!(
+        fs::write(&dat_path, dat).await,
+        "Failed to write {:?}",
+        &dat_path
+    );
+
+    progress_bar.println(format!(
+        "Exported {} missing game(s) to \"{}\"",
+        datfile_xml.games.len(),
+        dat_path.as_os_str().to_str().unwrap()
+    ));
+
+    Ok(dat_path)
+}
+
+async fn build_fixdat(connection: &mut SqliteConnection, system: &System) -> DatfileXml {
+    let games = find_wanted_games_by_system_id(connection, system.id).await;
+    // parents and BIOSes may be complete or unwanted, look their names up in all games
+    let game_names: HashMap<i64, String> = find_games_by_system_id(connection, system.id)
+        .await
+        .into_iter()
+        .map(|game| (game.id, game.name))
+        .collect();
+
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    find_roms_without_romfile_by_game_ids(
+        connection,
+        games
+            .iter()
+            .map(|game| game.id)
+            .collect::<Vec<i64>>()
+            .as_slice(),
+    )
+    .await
+    .into_iter()
+    .for_each(|rom| {
+        let group = roms_by_game_id.entry(rom.game_id).or_default();
+        group.push(rom);
+    });
+
+    let games_xml: Vec<GameXml> = games
+        .iter()
+        .filter_map(|game| {
+            roms_by_game_id.remove(&game.id).map(|roms| {
+                // disks are stored as roms that only have a SHA1
+                let (disks, roms): (Vec<Rom>, Vec<Rom>) = roms
+                    .into_iter()
+                    .partition(|rom| rom.crc.is_none() && rom.sha1.is_some());
+                let parent_name = game
+                    .parent_id
+                    .and_then(|parent_id| game_names.get(&parent_id))
+                    .map(|name| name.to_string());
+                let bios_name = game
+                    .bios_id
+                    .and_then(|bios_id| game_names.get(&bios_id))
+                    .map(|name| name.to_string());
+                GameXml {
+                    name: game.name.clone(),
+                    cloneof: parent_name.clone(),
+                    romof: parent_name.or(bios_name),
+                    isbios: if game.bios {
+                        Some(String::from("yes"))
+                    } else {
+                        None
+                    },
+                    isdevice: None,
+                    runnable: None,
+                    comment: game.comment.clone(),
+                    description: game.description.clone(),
+                    roms: roms
+                        .into_iter()
+                        .map(|rom| RomXml {
+                            name: rom.name,
+                            merge: None,
+                            size: rom.size,
+                            crc: rom.crc,
+                            md5: rom.md5,
+                            sha1: rom.sha1,
+                            status: rom.rom_status,
+                        })
+                        .collect(),
+                    disks: disks
+                        .into_iter()
+                        .map(|disk| DiskXml {
+                            name: disk
+                                .name
+                                .strip_suffix(&format!(".{}", CHD_EXTENSION))
+                                .unwrap_or(&disk.name)
+                                .to_string(),
+                            merge: None,
+                            sha1: disk.sha1,
+                            status: disk.rom_status,
+                        })
+                        .collect(),
+                    driver: None,
+                }
+            })
+        })
+        .collect();
+
+    DatfileXml {
+        system: SystemXml {
+            name: format!("{} (Fixdat)", system.name),
+            description: format!("{} (Fixdat)", system.description),
+            version: system.version.clone(),
+            url: system.url.clone(),
+            clrmamepros: Vec::new(),
+        },
+        games: games_xml,
+    }
+}
+
+#[cfg(test)]
+mod test_fixdat;
+#[cfg(test)]
+mod test_fixdat_parent_clone;
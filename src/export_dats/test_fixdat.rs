@@ -0,0 +1,106 @@
+use super::super::config::*;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use std::env;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    env::set_var(
+        "PATH",
+        format!(
+            "{}:{}",
+            test_directory.as_os_str().to_str().unwrap(),
+            env::var("PATH").unwrap()
+        ),
+    );
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+    let export_directory = TempDir::new_in(test_directory).unwrap();
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).rom");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).rom"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+    let matches = import_roms::subcommand()
+        .get_matches_from(["import-roms", romfile_path.as_os_str().to_str().unwrap()]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+
+    // when
+    let dat_path = export_system(
+        &mut connection,
+        &progress_bar,
+        &system,
+        &PathBuf::from(export_directory.path()),
+    )
+    .await
+    .unwrap();
+
+    // then
+    assert!(dat_path.is_file().await);
+    assert!(dat_path.ends_with("Test System (Fixdat).dat"));
+
+    let (datfile_xml, detector_xml) = import_dats::parse_dat(&progress_bar, &dat_path, false)
+        .await
+        .unwrap();
+    assert!(detector_xml.is_none());
+    assert_eq!(datfile_xml.system.name, "Test System (Fixdat)");
+    assert_eq!(datfile_xml.system.version, "20200721");
+    assert_eq!(datfile_xml.games.len(), 5);
+    assert!(datfile_xml
+        .games
+        .iter()
+        .all(|game| game.name != "Test Game (USA, Europe)"));
+
+    let game_xml = datfile_xml
+        .games
+        .iter()
+        .find(|game| game.name == "Test Game (USA, Europe) (CUE BIN)")
+        .unwrap();
+    assert_eq!(game_xml.description, "Test Game (USA, Europe)");
+    assert_eq!(game_xml.roms.len(), 3);
+
+    let rom_xml = game_xml
+        .roms
+        .iter()
+        .find(|rom| rom.name == "Test Game (USA, Europe).cue")
+        .unwrap();
+    assert_eq!(rom_xml.size, 233);
+    assert_eq!(rom_xml.crc.as_deref(), Some("94789b9d"));
+    assert_eq!(
+        rom_xml.md5.as_deref(),
+        Some("6e6423ddeeeb2d1557c49fa9064c9963")
+    );
+    assert_eq!(
+        rom_xml.sha1.as_deref(),
+        Some("bac6d2b0bdd8be39c4a69dec0da0df7757cf9a26")
+    );
+    assert_eq!(rom_xml.status.as_deref(), Some("verified"));
+}
@@ -0,0 +1,68 @@
+use super::super::config::*;
+use super::super::import_dats;
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+    let export_directory = TempDir::new_in(test_directory).unwrap();
+
+    let matches = import_dats::subcommand().get_matches_from([
+        "import-dats",
+        "tests/Test System (20200721) (Parent-Clone).dat",
+    ]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let parent_id = find_games_by_system_id(&mut connection, system.id)
+        .await
+        .into_iter()
+        .find(|game| game.name == "Test Game (USA, Europe)")
+        .unwrap()
+        .id;
+    update_games_sorting(&mut connection, &[parent_id], Sorting::Ignored).await;
+
+    // when
+    let dat_path = export_system(
+        &mut connection,
+        &progress_bar,
+        &system,
+        &PathBuf::from(export_directory.path()),
+    )
+    .await
+    .unwrap();
+
+    // then
+    assert!(dat_path.is_file().await);
+    assert!(dat_path.ends_with("Test System (Fixdat).dat"));
+
+    let (datfile_xml, _) = import_dats::parse_dat(&progress_bar, &dat_path, false)
+        .await
+        .unwrap();
+    assert_eq!(datfile_xml.games.len(), 5);
+
+    // the parent is ignored but still referenced by its clones
+    let game_xml = datfile_xml
+        .games
+        .iter()
+        .find(|game| game.name == "Test Game (Japan)")
+        .unwrap();
+    assert_eq!(game_xml.cloneof.as_deref(), Some("Test Game (USA, Europe)"));
+    assert_eq!(game_xml.romof.as_deref(), Some("Test Game (USA, Europe)"));
+}
@@ -2,6 +2,7 @@ use super::config::*;
 use super::database::*;
 use super::download_dats::REDUMP_SYSTEM_URL;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::util::*;
 use super::SimpleResult;
@@ -9,7 +10,6 @@ use async_std::fs::File;
 use async_std::io::WriteExt;
 use async_std::path::PathBuf;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use regex::Regex;
 use sqlx::sqlite::SqliteConnection;
 use std::collections::HashMap;
@@ -34,7 +34,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let systems = prompt_for_systems(
         connection,
@@ -53,7 +53,7 @@ pub async fn main(
 
 async fn process_system(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) -> SimpleResult<()> {
     let mut grouped_games: HashMap<String, Vec<Game>> = HashMap::new();
@@ -92,8 +92,12 @@ async fn process_system(
             CHD_EXTENSION,
             CSO_EXTENSION,
             CUE_EXTENSION,
+            GCZ_EXTENSION,
             ISO_EXTENSION,
             RVZ_EXTENSION,
+            WBFS_EXTENSION,
+            WIA_EXTENSION,
+            ZSO_EXTENSION,
         ] {
             existing_romfiles = romfiles
                 .iter()
@@ -110,24 +114,28 @@ async fn process_system(
 
         let mut playlist_path = PathBuf::from(&existing_romfiles.get(0).unwrap().path);
         playlist_path.set_file_name(&playlist_name);
-        let mut playlist_file = File::create(&playlist_path)
-            .await
-            .expect("Failed to create M3U file");
+        if is_dry_run() {
+            progress_bar.println(format!("Would create {:?}", playlist_path.as_os_str()));
+        } else {
+            let mut playlist_file = File::create(&playlist_path)
+                .await
+                .expect("Failed to create M3U file");
 
-        progress_bar.println(format!("Creating \"{}\"", &playlist_name));
+            progress_bar.println(format!("Creating \"{}\"", &playlist_name));
 
-        for romfile in existing_romfiles {
-            writeln!(
-                &mut playlist_file,
-                "{}",
-                PathBuf::from(&romfile.path)
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-            )
-            .await
-            .expect("Failed to write to M3U file");
+            for romfile in existing_romfiles {
+                writeln!(
+                    &mut playlist_file,
+                    "{}",
+                    PathBuf::from(&romfile.path)
+                        .file_name()
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                )
+                .await
+                .expect("Failed to write to M3U file");
+            }
         }
 
         let playlist_id =
@@ -139,7 +147,7 @@ async fn process_system(
                         connection,
                         playlist.id,
                         playlist_path.as_os_str().to_str().unwrap(),
-                        playlist_path.metadata().await.unwrap().len(),
+                        get_file_size(&playlist_path).await,
                     )
                     .await;
                     if playlist.path != playlist_path.as_os_str().to_str().unwrap() {
@@ -151,7 +159,7 @@ async fn process_system(
                     create_romfile(
                         connection,
                         playlist_path.as_os_str().to_str().unwrap(),
-                        playlist_path.metadata().await.unwrap().len(),
+                        get_file_size(&playlist_path).await,
                     )
                     .await
                 }
@@ -1,17 +1,20 @@
-use super::config::HashAlgorithm;
+use super::clrmamepro::*;
+use super::config::{HashAlgorithm, CHD_EXTENSION};
 use super::database::*;
-use super::import_roms::import_rom;
+use super::import_roms::{import_rom, ImportOptions};
 use super::model::*;
 use super::progress::*;
 use super::util::*;
 use super::SimpleResult;
 use async_std::path::Path;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use quick_xml::de;
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use rayon::prelude::*;
 use rust_embed::RustEmbed;
 use shiratsu_naming::naming::nointro::{NoIntroName, NoIntroToken};
+use shiratsu_naming::naming::tosec::{TOSECName, TOSECToken};
 use shiratsu_naming::naming::TokenizedName;
 use shiratsu_naming::region::Region;
 use sqlx::sqlite::SqliteConnection;
@@ -26,7 +29,7 @@ struct Assets;
 
 pub fn subcommand() -> Command {
     Command::new("import-dats")
-        .about("Parse and import Logiqx DAT files into oxyromon")
+        .about("Parse and import Logiqx and ClrMamePro DAT files into oxyromon")
         .arg(
             Arg::new("DATS")
                 .help("Set the DAT files to import")
@@ -72,7 +75,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let dat_paths: Vec<&PathBuf> = matches.get_many::<PathBuf>("DATS").unwrap().collect();
 
@@ -105,14 +108,36 @@ pub async fn main(
 }
 
 pub async fn parse_dat<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     dat_path: &P,
     skip_header: bool,
 ) -> SimpleResult<(DatfileXml, Option<DetectorXml>)> {
-    let datfile_xml: DatfileXml = try_with!(
-        de::from_reader(&mut get_reader_sync(dat_path)?),
-        "Failed to parse DAT file"
-    );
+    let mut reader = get_reader_sync(dat_path)?;
+    let mut datfile_xml: DatfileXml = if is_clrmamepro_dat(&mut reader)? {
+        parse_clrmamepro_dat(reader)?
+    } else {
+        match get_root_element_name(dat_path)?.as_str() {
+            "mame" => {
+                let mame_xml: MameXml =
+                    try_with!(de::from_reader(&mut reader), "Failed to parse DAT file");
+                get_datfile_from_mame(mame_xml)
+            }
+            "softwarelist" => {
+                let software_list_xml: SoftwareListXml =
+                    try_with!(de::from_reader(&mut reader), "Failed to parse DAT file");
+                get_datfile_from_software_list(software_list_xml)
+            }
+            _ => try_with!(de::from_reader(&mut reader), "Failed to parse DAT file"),
+        }
+    };
+
+    // disks are stored as roms that only have a SHA1
+    for game_xml in datfile_xml.games.iter_mut() {
+        let disks_xml = std::mem::take(&mut game_xml.disks);
+        game_xml
+            .roms
+            .extend(disks_xml.into_iter().map(get_rom_from_disk));
+    }
 
     // print information
     progress_bar.println(format!("System: {}", datfile_xml.system.name));
@@ -146,9 +171,130 @@ pub async fn parse_dat<P: AsRef<Path>>(
     Ok((datfile_xml, detector_xml))
 }
 
+fn get_root_element_name<P: AsRef<Path>>(dat_path: &P) -> SimpleResult<String> {
+    let mut reader = Reader::from_reader(get_reader_sync(dat_path)?);
+    let mut buffer = Vec::new();
+    loop {
+        match try_with!(
+            reader.read_event_into(&mut buffer),
+            "Failed to parse DAT file"
+        ) {
+            Event::Start(element) | Event::Empty(element) => {
+                return Ok(String::from_utf8_lossy(element.name().as_ref()).to_string())
+            }
+            Event::Eof => bail!("Failed to parse DAT file"),
+            _ => buffer.clear(),
+        }
+    }
+}
+
+fn get_datfile_from_mame(mame_xml: MameXml) -> DatfileXml {
+    let version = mame_xml.build.unwrap_or_default();
+    DatfileXml {
+        system: SystemXml {
+            name: String::from("MAME"),
+            description: format!("MAME {}", &version).trim().to_string(),
+            version,
+            url: None,
+            clrmamepros: Vec::new(),
+        },
+        games: mame_xml
+            .machines
+            .into_iter()
+            // skip devices that don't have anything to dump
+            .filter(|machine_xml| {
+                machine_xml.runnable.as_deref() != Some("no")
+                    || !machine_xml.roms.is_empty()
+                    || !machine_xml.disks.is_empty()
+            })
+            .map(|mut machine_xml| {
+                if machine_xml.comment.is_none() {
+                    machine_xml.comment = machine_xml
+                        .driver
+                        .as_ref()
+                        .filter(|driver_xml| driver_xml.status != "good")
+                        .map(|driver_xml| format!("Driver status: {}", driver_xml.status));
+                }
+                machine_xml
+            })
+            .collect(),
+    }
+}
+
+fn get_datfile_from_software_list(software_list_xml: SoftwareListXml) -> DatfileXml {
+    DatfileXml {
+        system: SystemXml {
+            name: software_list_xml
+                .description
+                .unwrap_or_else(|| software_list_xml.name.clone()),
+            description: software_list_xml.name,
+            version: String::new(),
+            url: None,
+            clrmamepros: Vec::new(),
+        },
+        games: software_list_xml
+            .softwares
+            .into_iter()
+            .map(|software_xml| {
+                let mut roms_xml: Vec<RomXml> = Vec::new();
+                let mut disks_xml: Vec<DiskXml> = Vec::new();
+                for part_xml in software_xml.parts {
+                    for dataarea_xml in part_xml.dataareas {
+                        // skip continuation entries that don't have a name
+                        roms_xml.extend(dataarea_xml.roms.into_iter().filter_map(
+                            |rom_xml| match (rom_xml.name, rom_xml.size) {
+                                (Some(name), Some(size)) => Some(RomXml {
+                                    name,
+                                    merge: None,
+                                    size,
+                                    crc: rom_xml.crc,
+                                    md5: None,
+                                    sha1: rom_xml.sha1,
+                                    status: rom_xml.status,
+                                }),
+                                _ => None,
+                            },
+                        ));
+                    }
+                    for diskarea_xml in part_xml.diskareas {
+                        disks_xml.extend(diskarea_xml.disks);
+                    }
+                }
+                GameXml {
+                    name: software_xml.name,
+                    cloneof: software_xml.cloneof,
+                    romof: None,
+                    isbios: None,
+                    isdevice: None,
+                    runnable: None,
+                    comment: None,
+                    description: software_xml.description,
+                    roms: roms_xml,
+                    disks: disks_xml,
+                    driver: None,
+                }
+            })
+            .collect(),
+    }
+}
+
+fn get_rom_from_disk(disk_xml: DiskXml) -> RomXml {
+    RomXml {
+        name: format!("{}.{}", disk_xml.name, CHD_EXTENSION),
+        merge: disk_xml
+            .merge
+            .map(|merge| format!("{}.{}", merge, CHD_EXTENSION)),
+        size: 0,
+        crc: None,
+        md5: None,
+        sha1: disk_xml.sha1,
+        status: disk_xml.status,
+    }
+}
+
 pub async fn import_dat(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     datfile_xml: &DatfileXml,
     detector_xml: &Option<DetectorXml>,
     arcade: bool,
@@ -233,13 +379,22 @@ pub async fn import_dat(
 }
 
 fn get_regions_from_game_name(name: &str) -> SimpleResult<String> {
-    let name = try_with!(
-        NoIntroName::try_parse(name),
-        "Failed to parse no-intro name"
-    );
-    for token in name.iter() {
-        if let NoIntroToken::Region(_, regions) = token {
-            return Ok(Region::to_normalized_region_string(regions));
+    // fall back to TOSEC names for collections that only exist as TOSEC
+    if let Ok(name) = NoIntroName::try_parse(name) {
+        for token in name.iter() {
+            if let NoIntroToken::Region(_, regions) = token {
+                return Ok(Region::to_normalized_region_string(regions));
+            }
+        }
+    } else {
+        let name = try_with!(
+            TOSECName::try_parse(name),
+            "Failed to parse no-intro or TOSEC name"
+        );
+        for token in name.iter() {
+            if let TOSECToken::Region(_, regions) = token {
+                return Ok(Region::to_normalized_region_string(regions));
+            }
         }
     }
     Ok(String::from(""))
@@ -247,7 +402,7 @@ fn get_regions_from_game_name(name: &str) -> SimpleResult<String> {
 
 async fn create_or_update_system(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system_xml: &SystemXml,
     arcade: bool,
     force: bool,
@@ -288,7 +443,7 @@ async fn create_or_update_games(
     games_xml: &[GameXml],
     system_id: i64,
     arcade: bool,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<Vec<i64>> {
     let mut orphan_romfile_ids: Vec<i64> = Vec::new();
     let (mut parent_games_xml, mut child_games_xml): (Vec<&GameXml>, Vec<&GameXml>) = games_xml
@@ -438,7 +593,7 @@ async fn create_or_update_games(
 
 async fn create_or_update_roms(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     roms_xml: &[RomXml],
     mut bios: bool,
     game_id: i64,
@@ -449,8 +604,8 @@ async fn create_or_update_roms(
         if rom_xml.status.is_some() && rom_xml.status.as_ref().unwrap() == "nodump" {
             continue;
         }
-        // skip roms without CRC
-        if rom_xml.crc.is_none() {
+        // skip roms without CRC, disks only have a SHA1
+        if rom_xml.crc.is_none() && rom_xml.sha1.is_none() {
             progress_bar.println(format!(
                 "Skipping \"{}\" because it has no CRC",
                 &rom_xml.name
@@ -462,22 +617,15 @@ async fn create_or_update_roms(
         if rom_xml.merge.is_some() {
             let game = find_game_by_id(connection, game_id).await;
             // try cloneof first, or romof if there is no cloneof
-            let mut parent_rom = find_rom_by_size_and_crc_and_game_id(
+            let mut parent_rom = find_parent_rom(
                 connection,
-                rom_xml.size,
-                rom_xml.crc.as_ref().unwrap(),
+                rom_xml,
                 game.parent_id.or(game.bios_id).unwrap(),
             )
             .await;
             // try romof next
             if parent_rom.is_none() && game.bios_id.is_some() {
-                parent_rom = find_rom_by_size_and_crc_and_game_id(
-                    connection,
-                    rom_xml.size,
-                    rom_xml.crc.as_ref().unwrap(),
-                    game.bios_id.unwrap(),
-                )
-                .await;
+                parent_rom = find_parent_rom(connection, rom_xml, game.bios_id.unwrap()).await;
             }
             if parent_rom.is_some() {
                 let parent_rom = parent_rom.unwrap();
@@ -494,7 +642,8 @@ async fn create_or_update_roms(
             Some(rom) => {
                 update_rom_from_xml(connection, rom.id, rom_xml, bios, game_id, parent_id).await;
                 if rom_xml.size != rom.size
-                    || rom_xml.crc.as_ref().unwrap() != rom.crc.as_ref().unwrap()
+                    || rom_xml.crc != rom.crc
+                    || (rom_xml.crc.is_none() && rom_xml.sha1 != rom.sha1)
                 {
                     if let Some(romfile_id) = rom.romfile_id {
                         orphan_romfile_ids.push(romfile_id);
@@ -509,6 +658,22 @@ async fn create_or_update_roms(
     orphan_romfile_ids
 }
 
+async fn find_parent_rom(
+    connection: &mut SqliteConnection,
+    rom_xml: &RomXml,
+    game_id: i64,
+) -> Option<Rom> {
+    match rom_xml.crc.as_ref() {
+        Some(crc) => {
+            find_rom_by_size_and_crc_and_game_id(connection, rom_xml.size, crc, game_id).await
+        }
+        // disks don't have a CRC, use their merge name instead
+        None => {
+            find_rom_by_name_and_game_id(connection, rom_xml.merge.as_ref().unwrap(), game_id).await
+        }
+    }
+}
+
 async fn delete_old_games(
     connection: &mut SqliteConnection,
     games_xml: &[GameXml],
@@ -558,13 +723,19 @@ async fn delete_old_roms(
 
 pub async fn reimport_orphan_romfiles(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system_id: i64,
     orphan_romfile_ids: Vec<i64>,
     hash_algorithm: &HashAlgorithm,
 ) -> SimpleResult<()> {
     let system = Some(find_system_by_id(connection, system_id).await);
     let header = find_header_by_system_id(connection, system_id).await;
+    let options = ImportOptions {
+        hash_algorithm: hash_algorithm.clone(),
+        trash: true,
+        force: false,
+        cache: true,
+    };
     for romfile_id in orphan_romfile_ids {
         let romfile = find_romfile_by_id(connection, romfile_id).await;
         delete_romfile_by_id(connection, romfile_id).await;
@@ -574,9 +745,7 @@ pub async fn reimport_orphan_romfiles(
             system.as_ref(),
             &header,
             &Path::new(&romfile.path),
-            hash_algorithm,
-            true,
-            false,
+            &options,
         )
         .await?;
     }
@@ -586,6 +755,8 @@ pub async fn reimport_orphan_romfiles(
 #[cfg(test)]
 mod test_dat;
 #[cfg(test)]
+mod test_dat_clrmamepro;
+#[cfg(test)]
 mod test_dat_headered;
 #[cfg(test)]
 mod test_dat_headered_duplicate_clrmamepro;
@@ -594,12 +765,16 @@ mod test_dat_headered_embedded;
 #[cfg(test)]
 mod test_dat_headered_skipped_header;
 #[cfg(test)]
+mod test_dat_mame_listxml;
+#[cfg(test)]
 mod test_dat_outdated_forced;
 #[cfg(test)]
 mod test_dat_outdated_should_do_nothing;
 #[cfg(test)]
 mod test_dat_parent_clone;
 #[cfg(test)]
+mod test_dat_software_list;
+#[cfg(test)]
 mod test_dat_updated;
 #[cfg(test)]
 mod test_regions_france_germany;
@@ -0,0 +1,65 @@
+use super::super::config::*;
+use super::super::database::*;
+use super::*;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let dat_path = test_directory.join("Test System (20200721) (ClrMamePro).dat");
+    let (datfile_xml, detector_xml) = parse_dat(&progress_bar, &dat_path, false).await.unwrap();
+
+    // when
+    import_dat(
+        &mut connection,
+        &progress_bar,
+        &datfile_xml,
+        &detector_xml,
+        false,
+        false,
+    )
+    .await
+    .unwrap();
+
+    // then
+    let systems = find_systems(&mut connection).await;
+    assert_eq!(systems.len(), 1);
+
+    let system = systems.first().unwrap();
+    assert_eq!(system.name, "Test System");
+    assert_eq!(system.version, "20200721");
+    assert_eq!(system.url.as_deref(), Some("www.no-intro.org"));
+
+    assert_eq!(find_games(&mut connection).await.len(), 6);
+
+    let roms = find_roms(&mut connection).await;
+    assert_eq!(roms.len(), 8);
+
+    let rom = roms
+        .iter()
+        .find(|rom| rom.name == "Test Game (USA, Europe).rom")
+        .unwrap();
+    assert_eq!(rom.size, 256);
+    assert_eq!(rom.crc.as_deref(), Some("cc721e14"));
+    assert_eq!(rom.md5.as_deref(), Some("c1fe7a7ed317bd069b0cb7bdbc40be01"));
+    assert_eq!(
+        rom.sha1.as_deref(),
+        Some("4b3e49f0f22c7ce5f1eb7c30a2bbd6fb4fdc6f3d")
+    );
+    assert_eq!(rom.rom_status.as_deref(), Some("verified"));
+}
@@ -0,0 +1,85 @@
+use super::super::config::*;
+use super::super::database::*;
+use super::*;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let dat_path = test_directory.join("MAME (0.251).xml");
+    let (datfile_xml, detector_xml) = parse_dat(&progress_bar, &dat_path, false).await.unwrap();
+
+    // when
+    import_dat(
+        &mut connection,
+        &progress_bar,
+        &datfile_xml,
+        &detector_xml,
+        true,
+        false,
+    )
+    .await
+    .unwrap();
+
+    // then
+    let systems = find_systems(&mut connection).await;
+    assert_eq!(systems.len(), 1);
+
+    let system = systems.first().unwrap();
+    assert_eq!(system.name, "MAME");
+    assert_eq!(system.version, "0.251 (mame0251)");
+    assert!(system.arcade);
+
+    // devices without roms are skipped
+    assert_eq!(find_games(&mut connection).await.len(), 3);
+
+    // nodump disks are skipped
+    let roms = find_roms(&mut connection).await;
+    assert_eq!(roms.len(), 7);
+
+    let parent_game =
+        find_game_by_name_and_bios_and_system_id(&mut connection, "testgame", false, system.id)
+            .await
+            .unwrap();
+    let parent_disk = roms
+        .iter()
+        .find(|rom| rom.name == "testdisk.chd" && rom.game_id == parent_game.id)
+        .unwrap();
+    assert_eq!(parent_disk.size, 0);
+    assert!(parent_disk.crc.is_none());
+    assert_eq!(
+        parent_disk.sha1.as_deref(),
+        Some("bb16a67828e684668e24093414ff786cf02149b8")
+    );
+    assert!(parent_disk.parent_id.is_none());
+
+    let child_game =
+        find_game_by_name_and_bios_and_system_id(&mut connection, "testgamea", false, system.id)
+            .await
+            .unwrap();
+    assert_eq!(child_game.parent_id, Some(parent_game.id));
+    assert_eq!(
+        child_game.comment.as_deref(),
+        Some("Driver status: preliminary")
+    );
+    let child_disk = roms
+        .iter()
+        .find(|rom| rom.name == "testdisk.chd" && rom.game_id == child_game.id)
+        .unwrap();
+    assert_eq!(child_disk.parent_id, Some(parent_disk.id));
+}
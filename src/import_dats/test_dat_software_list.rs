@@ -0,0 +1,72 @@
+use super::super::config::*;
+use super::super::database::*;
+use super::*;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let dat_path = test_directory.join("Test Software List.xml");
+    let (datfile_xml, detector_xml) = parse_dat(&progress_bar, &dat_path, false).await.unwrap();
+
+    // when
+    import_dat(
+        &mut connection,
+        &progress_bar,
+        &datfile_xml,
+        &detector_xml,
+        true,
+        false,
+    )
+    .await
+    .unwrap();
+
+    // then
+    let systems = find_systems(&mut connection).await;
+    assert_eq!(systems.len(), 1);
+
+    let system = systems.first().unwrap();
+    assert_eq!(system.name, "Test Software List");
+    assert_eq!(system.description, "testlist");
+
+    assert_eq!(find_games(&mut connection).await.len(), 2);
+
+    // continuation entries are skipped
+    let roms = find_roms(&mut connection).await;
+    assert_eq!(roms.len(), 2);
+
+    let parent_game =
+        find_game_by_name_and_bios_and_system_id(&mut connection, "testsoft", false, system.id)
+            .await
+            .unwrap();
+    let child_game =
+        find_game_by_name_and_bios_and_system_id(&mut connection, "testsofta", false, system.id)
+            .await
+            .unwrap();
+    assert_eq!(child_game.parent_id, Some(parent_game.id));
+
+    let disk = roms
+        .iter()
+        .find(|rom| rom.game_id == child_game.id)
+        .unwrap();
+    assert_eq!(disk.name, "testdisk.chd");
+    assert_eq!(
+        disk.sha1.as_deref(),
+        Some("bb16a67828e684668e24093414ff786cf02149b8")
+    );
+}
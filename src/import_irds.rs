@@ -3,12 +3,12 @@ use super::database::*;
 use super::import_dats::reimport_orphan_romfiles;
 use super::isoinfo;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::util::*;
 use super::SimpleResult;
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use flate2::read::GzDecoder;
-use indicatif::ProgressBar;
 use sqlx::sqlite::SqliteConnection;
 use std::collections::HashMap;
 use std::io;
@@ -54,7 +54,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let ird_paths: Vec<&PathBuf> = matches.get_many::<PathBuf>("IRDS").unwrap().collect();
     let system = prompt_for_system_like(
@@ -221,7 +221,7 @@ pub async fn parse_ird<R: io::Read>(reader: &mut R) -> SimpleResult<(Irdfile, Ve
 
 pub async fn import_ird(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     game: &Game,
     irdfile: &Irdfile,
     header: &mut [u8],
@@ -2,29 +2,42 @@
 use super::chdman;
 use super::checksum::*;
 use super::config::*;
+use super::convert_roms;
+#[cfg(feature = "cso")]
+use super::cso;
 use super::database::*;
 #[cfg(feature = "rvz")]
 use super::dolphin;
-#[cfg(feature = "cso")]
-use super::maxcso;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::sevenzip;
 use super::util::*;
 use super::SimpleResult;
+use async_std::channel;
 use async_std::path::Path;
 use cfg_if::cfg_if;
 use clap::builder::PossibleValuesParser;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 use sqlx::sqlite::SqliteConnection;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::path::PathBuf;
+use std::sync::Arc;
 use strum::VariantNames;
 use walkdir::WalkDir;
 
+pub const IMPORT_THREADS_RANGE: [usize; 2] = [0, 256];
+
+pub struct ImportOptions {
+    pub hash_algorithm: HashAlgorithm,
+    pub trash: bool,
+    pub force: bool,
+    pub cache: bool,
+}
+
 pub fn subcommand() -> Command {
     Command::new("import-roms")
         .about("Validate and import ROM files or directories into oxyromon")
@@ -60,6 +73,21 @@ pub fn subcommand() -> Command {
                 .required(false)
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("NO_CACHE")
+                .long("no-cache")
+                .help("Ignore cached hashes")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("CONVERT")
+                .short('c')
+                .long("convert")
+                .help("Convert new games to the preferred format of their system")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("HASH")
                 .short('a')
@@ -74,7 +102,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let romfile_paths: Vec<&PathBuf> = matches.get_many::<PathBuf>("ROMS").unwrap().collect();
     let system = if matches.get_flag("SYSTEM") {
@@ -91,6 +119,7 @@ pub async fn main(
     let hash_algorithm = match matches.get_one::<String>("HASH").map(String::as_str) {
         Some("crc") => HashAlgorithm::Crc,
         Some("md5") => HashAlgorithm::Md5,
+        Some("sha1") => HashAlgorithm::Sha1,
         Some(&_) | None => {
             match find_setting_by_key(connection, "HASH_ALGORITHM")
                 .await
@@ -100,15 +129,32 @@ pub async fn main(
             {
                 Some("crc") => HashAlgorithm::Crc,
                 Some("md5") => HashAlgorithm::Md5,
+                Some("sha1") => HashAlgorithm::Sha1,
                 Some(&_) | None => bail!("Not possible"),
             }
         }
     };
 
-    let trash = matches.get_flag("TRASH");
-    let force = matches.get_flag("FORCE");
+    let options = ImportOptions {
+        hash_algorithm,
+        trash: matches.get_flag("TRASH"),
+        force: matches.get_flag("FORCE"),
+        cache: !matches.get_flag("NO_CACHE"),
+    };
+    let convert = matches.get_flag("CONVERT");
+
+    // new games are the ones that ended up with a new romfile
+    let existing_romfile_ids: HashSet<i64> = match convert {
+        true => find_romfiles(connection)
+            .await
+            .into_iter()
+            .map(|romfile| romfile.id)
+            .collect(),
+        false => HashSet::new(),
+    };
 
     let mut system_ids: HashSet<i64> = HashSet::new();
+    let mut file_paths: Vec<async_std::path::PathBuf> = Vec::new();
 
     for romfile_path in romfile_paths {
         let romfile_path = get_canonicalized_path(&romfile_path).await?;
@@ -121,7 +167,7 @@ pub async fn main(
                             &romfile_path.file_name().unwrap().to_str().unwrap()
                         ));
                         match system.as_ref() {
-                            Some(system) => import_jbfolder(connection, progress_bar, system, &romfile_path, trash).await?,
+                            Some(system) => import_jbfolder(connection, progress_bar, system, &romfile_path, options.trash).await?,
                             None => {
                                 let system = prompt_for_system_like(
                                     connection,
@@ -129,68 +175,37 @@ pub async fn main(
                                     "%PlayStation 3%",
                                 )
                                 .await?;
-                                import_jbfolder(connection, progress_bar, &system, &romfile_path, trash).await?;
-                            }
-                        }
-                    } else {
-                        let walker = WalkDir::new(&romfile_path).into_iter();
-                        for entry in walker.filter_map(|e| e.ok()) {
-                            if entry.path().is_file() {
-                                system_ids.extend(
-                                    import_rom(
-                                        connection,
-                                        progress_bar,
-                                        system.as_ref(),
-                                        &header,
-                                        &entry.path(),
-                                        &hash_algorithm,
-                                        trash,
-                                        force,
-                                    )
-                                    .await?
-                                );
+                                import_jbfolder(connection, progress_bar, &system, &romfile_path, options.trash).await?;
                             }
                         }
-                    }
-                } else {
-                    let walker = WalkDir::new(&romfile_path).into_iter();
-                    for entry in walker.filter_map(|e| e.ok()) {
-                        if entry.path().is_file() {
-                            system_ids.extend(
-                                import_rom(
-                                    connection,
-                                    progress_bar,
-                                    system.as_ref(),
-                                    &header,
-                                    &entry.path(),
-                                    &hash_algorithm,
-                                    trash,
-                                    force,
-                                )
-                                .await?
-                            );
-                        }
+                        progress_bar.println("");
+                        continue;
                     }
                 }
             }
+            let walker = WalkDir::new(&romfile_path).into_iter();
+            for entry in walker.filter_map(|e| e.ok()) {
+                if entry.path().is_file() {
+                    file_paths.push(async_std::path::PathBuf::from(entry.path()));
+                }
+            }
         } else {
-            system_ids.extend(
-                import_rom(
-                    connection,
-                    progress_bar,
-                    system.as_ref(),
-                    &header,
-                    &romfile_path,
-                    &hash_algorithm,
-                    trash,
-                    force,
-                )
-                .await?,
-            );
+            file_paths.push(romfile_path);
         }
-        progress_bar.println("");
     }
 
+    system_ids.extend(
+        import_roms(
+            connection,
+            progress_bar,
+            system.as_ref(),
+            &header,
+            &file_paths,
+            &options,
+        )
+        .await?,
+    );
+
     for system_id in system_ids {
         let system = find_system_by_id(connection, system_id).await;
         if system.arcade {
@@ -198,20 +213,187 @@ pub async fn main(
         } else {
             compute_system_completion(connection, progress_bar, &system).await;
         }
+        if convert {
+            convert_new_games(connection, progress_bar, &system, &existing_romfile_ids).await?;
+        }
     }
 
     Ok(())
 }
 
+async fn convert_new_games(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: &System,
+    existing_romfile_ids: &HashSet<i64>,
+) -> SimpleResult<()> {
+    let preferred_format = match &system.preferred_format {
+        Some(preferred_format) => preferred_format,
+        None => return Ok(()),
+    };
+    let mut game_ids: Vec<i64> = find_roms_with_romfile_by_system_id(connection, system.id)
+        .await
+        .into_iter()
+        .filter(|rom| !existing_romfile_ids.contains(&rom.romfile_id.unwrap()))
+        .map(|rom| rom.game_id)
+        .collect();
+    game_ids.sort_unstable();
+    game_ids.dedup();
+    if game_ids.is_empty() {
+        return Ok(());
+    }
+    progress_bar.println(format!(
+        "Converting new games of \"{}\" to {}",
+        system.name, preferred_format
+    ));
+    let games = find_games_by_ids(connection, &game_ids).await;
+    convert_roms::convert_games(
+        connection,
+        progress_bar,
+        system,
+        games,
+        preferred_format,
+        false,
+    )
+    .await
+}
+
+async fn import_roms(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: Option<&System>,
+    header: &Option<Header>,
+    romfile_paths: &[async_std::path::PathBuf],
+    options: &ImportOptions,
+) -> SimpleResult<HashSet<i64>> {
+    let mut system_ids: HashSet<i64> = HashSet::new();
+
+    let threads = get_integer(connection, "IMPORT_THREADS", None).await;
+    let pool = try_with!(
+        ThreadPoolBuilder::new().num_threads(threads).build(),
+        "Failed to build thread pool"
+    );
+    let rules = Arc::new(match header {
+        Some(header) => find_rules_by_header_id(connection, header.id).await,
+        None => Vec::new(),
+    });
+    let header = Arc::new(header.clone());
+    let threads = pool.current_num_threads();
+    let (sender, receiver) = channel::bounded(threads);
+
+    // hash plain files concurrently, other formats need to be extracted first
+    let mut pending: Vec<usize> = Vec::new();
+    let mut cached: HashMap<usize, (u64, Hashes)> = HashMap::new();
+    for (i, romfile_path) in romfile_paths.iter().enumerate() {
+        let romfile_extension = romfile_path
+            .extension()
+            .unwrap_or(&OsString::new())
+            .to_str()
+            .unwrap()
+            .to_lowercase();
+        if ARCHIVE_EXTENSIONS.contains(&romfile_extension.as_str())
+            || DOLPHIN_EXTENSIONS.contains(&romfile_extension.as_str())
+            || [CHD_EXTENSION, CSO_EXTENSION, ZSO_EXTENSION].contains(&romfile_extension.as_str())
+            || (!options.force
+                && find_romfile_by_path(connection, romfile_path.as_os_str().to_str().unwrap())
+                    .await
+                    .is_some())
+        {
+            continue;
+        }
+        if options.cache {
+            if let Some(size_and_hashes) =
+                find_cached_size_and_hashes(connection, romfile_path, "", &header).await
+            {
+                cached.insert(i, size_and_hashes);
+                continue;
+            }
+        }
+        pending.push(i);
+    }
+
+    // apply the results in order from a single writer, hashing at most two jobs per thread ahead
+    let mut spawned = 0;
+    let mut received = 0;
+    let mut results: HashMap<usize, SimpleResult<(u64, Hashes)>> = HashMap::new();
+    for (i, romfile_path) in romfile_paths.iter().enumerate() {
+        while spawned < pending.len() && spawned - received < 2 * threads {
+            let j = pending[spawned];
+            let sender = sender.clone();
+            let romfile_path = romfile_paths[j].clone();
+            let header = Arc::clone(&header);
+            let rules = Arc::clone(&rules);
+            pool.spawn(move || {
+                let size_and_hashes = compute_size_and_hashes(
+                    &Progress::hidden(),
+                    &romfile_path,
+                    &header,
+                    &rules,
+                    1,
+                    1,
+                );
+                sender.send_blocking((j, size_and_hashes)).ok();
+            });
+            spawned += 1;
+        }
+        let size_and_hashes = if let Some(size_and_hashes) = cached.remove(&i) {
+            Some(size_and_hashes)
+        } else if pending.get(received) == Some(&i) {
+            received += 1;
+            while !results.contains_key(&i) {
+                let (j, result) = try_with!(receiver.recv().await, "Failed to receive hash");
+                results.insert(j, result);
+            }
+            Some(results.remove(&i).unwrap()?)
+        } else {
+            None
+        };
+        system_ids.extend(
+            import_rom_with_size_and_hashes(
+                connection,
+                progress_bar,
+                system,
+                &header,
+                romfile_path,
+                options,
+                size_and_hashes,
+            )
+            .await?,
+        );
+    }
+    progress_bar.println("");
+
+    Ok(system_ids)
+}
+
 pub async fn import_rom<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
     header: &Option<Header>,
     romfile_path: &P,
-    hash_algorithm: &HashAlgorithm,
-    trash: bool,
-    force: bool,
+    options: &ImportOptions,
+) -> SimpleResult<HashSet<i64>> {
+    import_rom_with_size_and_hashes(
+        connection,
+        progress_bar,
+        system,
+        header,
+        romfile_path,
+        options,
+        None,
+    )
+    .await
+}
+
+async fn import_rom_with_size_and_hashes<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: Option<&System>,
+    header: &Option<Header>,
+    romfile_path: &P,
+    options: &ImportOptions,
+    size_and_hashes: Option<(u64, Hashes)>,
 ) -> SimpleResult<HashSet<i64>> {
     progress_bar.println(format!(
         "Processing \"{}\"",
@@ -222,7 +404,7 @@ pub async fn import_rom<P: AsRef<Path>>(
     let mut system_ids: HashSet<i64> = HashSet::new();
 
     // abort if the romfile is already in the database
-    if !force
+    if !options.force
         && find_romfile_by_path(
             &mut transaction,
             romfile_path.as_ref().as_os_str().to_str().unwrap(),
@@ -251,8 +433,7 @@ pub async fn import_rom<P: AsRef<Path>>(
                 header,
                 &romfile_path,
                 &romfile_extension,
-                hash_algorithm,
-                trash,
+                options,
             )
             .await?,
         );
@@ -265,8 +446,8 @@ pub async fn import_rom<P: AsRef<Path>>(
                     system,
                     header,
                     &romfile_path,
-                    hash_algorithm,
-                    trash,
+                    &options.hash_algorithm,
+                    options.trash,
                 )
                 .await?
                 {
@@ -276,17 +457,16 @@ pub async fn import_rom<P: AsRef<Path>>(
                 progress_bar.println("Please rebuild with the CHD feature enabled");
             }
         }
-    } else if CSO_EXTENSION == romfile_extension {
+    } else if CSO_EXTENSION == romfile_extension || ZSO_EXTENSION == romfile_extension {
         cfg_if! {
             if #[cfg(feature = "cso")] {
                 if let Some(system_id) = import_cso(
                     &mut transaction,
                     progress_bar,
                     system,
-                    header,
                     &romfile_path,
-                    hash_algorithm,
-                    trash,
+                    &options.hash_algorithm,
+                    options.trash,
                 )
                 .await?
                 {
@@ -296,17 +476,17 @@ pub async fn import_rom<P: AsRef<Path>>(
                 progress_bar.println("Please rebuild with the CSO feature enabled");
             }
         }
-    } else if RVZ_EXTENSION == romfile_extension {
+    } else if DOLPHIN_EXTENSIONS.contains(&romfile_extension.as_str()) {
         cfg_if! {
             if #[cfg(feature = "rvz")] {
-                if let Some(system_id) = import_rvz(
+                if let Some(system_id) = import_dolphin_image(
                     &mut transaction,
                     progress_bar,
                     system,
                     header,
                     &romfile_path,
-                    hash_algorithm,
-                    trash,
+                    &options.hash_algorithm,
+                    options.trash,
                 )
                 .await?
                 {
@@ -323,8 +503,8 @@ pub async fn import_rom<P: AsRef<Path>>(
         header,
         &romfile_path,
         &romfile_extension,
-        hash_algorithm,
-        trash,
+        options,
+        size_and_hashes,
     )
     .await?
     {
@@ -339,7 +519,7 @@ pub async fn import_rom<P: AsRef<Path>>(
 #[cfg(feature = "ird")]
 async fn import_jbfolder<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     folder_path: &P,
     trash: bool,
@@ -358,16 +538,16 @@ async fn import_jbfolder<P: AsRef<Path>>(
     let mut transaction = begin_transaction(connection).await;
 
     // find the correct game
-    let (size, md5) = get_size_and_hash(
+    let (size, hashes) = get_size_and_hashes(
         &mut transaction,
         progress_bar,
         &sfb_romfile_path,
         &None,
         1,
         1,
-        &HashAlgorithm::Md5,
     )
     .await?;
+    let md5 = hashes.md5.unwrap();
     if let Some((sfb_rom, game)) =
         find_sfb_rom_by_md5(&mut transaction, size, &md5, system, progress_bar).await?
     {
@@ -381,16 +561,10 @@ async fn import_jbfolder<P: AsRef<Path>>(
                     &entry.path().as_os_str().to_str().unwrap()
                 ));
                 // force MD5 as IRD files only provide those
-                let (size, md5) = get_size_and_hash(
-                    &mut transaction,
-                    progress_bar,
-                    &entry.path(),
-                    &None,
-                    1,
-                    1,
-                    &HashAlgorithm::Md5,
-                )
-                .await?;
+                let (size, hashes) =
+                    get_size_and_hashes(&mut transaction, progress_bar, &entry.path(), &None, 1, 1)
+                        .await?;
+                let md5 = hashes.md5.unwrap();
 
                 let rom: Option<Rom>;
                 let mut roms = find_roms_without_romfile_by_size_and_md5_and_parent_id(
@@ -453,7 +627,14 @@ async fn import_jbfolder<P: AsRef<Path>>(
                     let new_path = system_directory.join(&game.name).join(&rom.name);
 
                     // move file if needed
-                    rename_file(progress_bar, &entry.path(), &new_path, false).await?;
+                    rename_cached_file(
+                        &mut transaction,
+                        progress_bar,
+                        &entry.path(),
+                        &new_path,
+                        false,
+                    )
+                    .await?;
 
                     // persist in database
                     create_or_update_romfile(&mut transaction, &new_path, &[rom]).await;
@@ -484,16 +665,16 @@ async fn import_jbfolder<P: AsRef<Path>>(
 
 async fn import_archive<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
     header: &Option<Header>,
     romfile_path: &P,
     romfile_extension: &str,
-    hash_algorithm: &HashAlgorithm,
-    trash: bool,
+    options: &ImportOptions,
 ) -> SimpleResult<HashSet<i64>> {
     let tmp_directory = create_tmp_directory(connection).await?;
     let sevenzip_infos = sevenzip::parse_archive(progress_bar, romfile_path)?;
+    let mut full_hashes_by_path: HashMap<&str, (u64, Hashes)> = HashMap::new();
 
     let mut roms_games_systems_sevenzip_infos: Vec<(Rom, Game, System, &sevenzip::ArchiveInfo)> =
         Vec::new();
@@ -508,34 +689,37 @@ async fn import_archive<P: AsRef<Path>>(
         ));
 
         let size: u64;
-        let hash: String;
+        let hashes: Hashes;
 
+        let cached_size_and_hashes = if options.cache {
+            find_cached_size_and_hashes(connection, romfile_path, &sevenzip_info.path, header).await
+        } else {
+            None
+        };
+
+        if let Some(size_and_hashes) = cached_size_and_hashes {
+            (size, hashes) = size_and_hashes;
+            full_hashes_by_path.insert(&sevenzip_info.path, (size, hashes.clone()));
         // system has a header, crc is absent, or selected checksum is not crc
-        if header.is_some() || sevenzip_info.crc.is_empty() || hash_algorithm != &HashAlgorithm::Crc
+        } else if header.is_some()
+            || sevenzip_info.crc.is_empty()
+            || options.hash_algorithm != HashAlgorithm::Crc
         {
-            let extracted_path = sevenzip::extract_files_from_archive(
+            let extracted_path = sevenzip::extract_files_for_hashing(
                 progress_bar,
                 romfile_path,
                 &[&sevenzip_info.path],
                 &tmp_directory.path(),
             )?
             .remove(0);
-            let size_hash = get_size_and_hash(
-                connection,
-                progress_bar,
-                &extracted_path,
-                header,
-                1,
-                1,
-                hash_algorithm,
-            )
-            .await?;
+            (size, hashes) =
+                get_size_and_hashes(connection, progress_bar, &extracted_path, header, 1, 1)
+                    .await?;
             remove_file(progress_bar, &extracted_path, true).await?;
-            size = size_hash.0;
-            hash = size_hash.1;
+            full_hashes_by_path.insert(&sevenzip_info.path, (size, hashes.clone()));
         } else {
             size = sevenzip_info.size;
-            hash = sevenzip_info.crc.clone();
+            hashes = Hashes::from_crc(&sevenzip_info.crc);
         }
 
         let path = Path::new(&sevenzip_info.path);
@@ -553,11 +737,11 @@ async fn import_archive<P: AsRef<Path>>(
             connection,
             progress_bar,
             size,
-            &hash,
+            &hashes,
             &system,
             game_names,
             rom_name,
-            hash_algorithm,
+            &options.hash_algorithm,
         )
         .await?
         {
@@ -566,7 +750,7 @@ async fn import_archive<P: AsRef<Path>>(
                 roms_games_systems_sevenzip_infos.push((rom, game, system, sevenzip_info));
             }
             None => {
-                if trash && sevenzip_infos.len() == 1 {
+                if options.trash && sevenzip_infos.len() == 1 {
                     move_to_trash(connection, progress_bar, romfile_path).await?;
                 }
             }
@@ -630,9 +814,15 @@ async fn import_archive<P: AsRef<Path>>(
             };
 
             // move file
-            rename_file(progress_bar, romfile_path, &new_path, false).await?;
+            rename_cached_file(connection, progress_bar, romfile_path, &new_path, false).await?;
 
             // persist in database
+            for (rom, _, _, sevenzip_info) in &roms_games_systems_sevenzip_infos {
+                if let Some((size, hashes)) = full_hashes_by_path.get(sevenzip_info.path.as_str()) {
+                    cache_size_and_hashes(connection, &new_path, &rom.name, header, *size, hashes)
+                        .await?;
+                }
+            }
             create_or_update_romfile(
                 connection,
                 &new_path,
@@ -677,6 +867,9 @@ async fn import_archive<P: AsRef<Path>>(
 
         // persist in database
         create_or_update_romfile(connection, &new_path, &[rom]).await;
+        if let Some((size, hashes)) = full_hashes_by_path.get(sevenzip_info.path.as_str()) {
+            cache_size_and_hashes(connection, &new_path, "", header, *size, hashes).await?;
+        }
     }
 
     Ok(system_ids)
@@ -685,35 +878,39 @@ async fn import_archive<P: AsRef<Path>>(
 #[cfg(feature = "chd")]
 async fn import_chd<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
     header: &Option<Header>,
     romfile_path: &P,
     hash_algorithm: &HashAlgorithm,
     trash: bool,
 ) -> SimpleResult<Option<i64>> {
-    let tmp_directory = create_tmp_directory(connection).await?;
+    // disks are matched using the SHA1 stored in the CHD header
+    let chd_sha1 = chdman::get_chd_sha1(romfile_path)?;
+    if is_disk(connection, &chd_sha1, &system).await {
+        return import_disk(
+            connection,
+            progress_bar,
+            system,
+            romfile_path,
+            &chd_sha1,
+            trash,
+        )
+        .await;
+    }
 
     let mut cue_path = romfile_path.as_ref().to_path_buf();
     cue_path.set_extension(CUE_EXTENSION);
 
     if cue_path.is_file().await {
         progress_bar.println("CUE file found, using multiple tracks mode");
-        let (size, hash) = get_size_and_hash(
-            connection,
-            progress_bar,
-            &cue_path,
-            header,
-            1,
-            1,
-            hash_algorithm,
-        )
-        .await?;
+        let (size, hashes) =
+            get_size_and_hashes(connection, progress_bar, &cue_path, header, 1, 1).await?;
         if let Some((cue_rom, _game, system)) = find_rom_by_size_and_hash(
             connection,
             progress_bar,
             size,
-            &hash,
+            &hashes,
             &system,
             Vec::new(),
             None,
@@ -727,40 +924,15 @@ async fn import_chd<P: AsRef<Path>>(
                 .filter(|rom| rom.id != cue_rom.id)
                 .collect();
 
-            let names_sizes: Vec<(&str, u64)> = roms
-                .iter()
-                .map(|rom| (rom.name.as_str(), rom.size as u64))
-                .collect();
-            let bin_paths = chdman::extract_chd_to_multiple_tracks(
-                progress_bar,
-                romfile_path,
-                &tmp_directory.path(),
-                &names_sizes,
-                true,
-            )
-            .await?;
-            let mut hashes: Vec<String> = Vec::new();
-            for (i, bin_path) in bin_paths.iter().enumerate() {
-                let (_, hash) = get_size_and_hash(
-                    connection,
-                    progress_bar,
-                    &bin_path,
-                    header,
-                    i,
-                    bin_paths.len(),
-                    hash_algorithm,
-                )
-                .await?;
-                hashes.push(hash);
-                remove_file(progress_bar, &bin_path, true).await?;
-            }
-
-            if roms
-                .iter()
-                .enumerate()
-                .any(|(i, rom)| &hashes[i] != rom.crc.as_ref().unwrap())
+            let tracks_sizes_and_hashes =
+                chdman::get_chd_tracks_size_and_hashes(progress_bar, romfile_path)?;
+            if tracks_sizes_and_hashes.len() != roms.len()
+                || roms
+                    .iter()
+                    .zip(&tracks_sizes_and_hashes)
+                    .any(|(rom, (_, hashes))| !hashes.matches(rom))
             {
-                progress_bar.println("CRC mismatch");
+                progress_bar.println("Checksum mismatch");
                 if trash {
                     move_to_trash(connection, progress_bar, romfile_path).await?;
                 }
@@ -774,8 +946,9 @@ async fn import_chd<P: AsRef<Path>>(
             new_chd_path.set_extension(CHD_EXTENSION);
 
             // move cue and chd if needed
-            rename_file(progress_bar, &cue_path, &new_cue_path, false).await?;
-            rename_file(progress_bar, romfile_path, &new_chd_path, false).await?;
+            rename_cached_file(connection, progress_bar, &cue_path, &new_cue_path, false).await?;
+            rename_cached_file(connection, progress_bar, romfile_path, &new_chd_path, false)
+                .await?;
 
             // persist in database
             create_or_update_romfile(connection, &new_cue_path, &[cue_rom]).await;
@@ -790,25 +963,12 @@ async fn import_chd<P: AsRef<Path>>(
         }
     } else {
         progress_bar.println("CUE file not found, using single track mode");
-        let bin_path =
-            chdman::extract_chd_to_single_track(progress_bar, romfile_path, &tmp_directory.path())
-                .await?;
-        let (size, hash) = get_size_and_hash(
-            connection,
-            progress_bar,
-            &bin_path,
-            header,
-            1,
-            1,
-            hash_algorithm,
-        )
-        .await?;
-        remove_file(progress_bar, &bin_path, true).await?;
+        let (size, hashes) = chdman::get_chd_size_and_hashes(progress_bar, romfile_path)?;
         if let Some((rom, _game, system)) = find_rom_by_size_and_hash(
             connection,
             progress_bar,
             size,
-            &hash,
+            &hashes,
             &system,
             Vec::new(),
             None,
@@ -822,7 +982,8 @@ async fn import_chd<P: AsRef<Path>>(
             new_chd_path.set_extension(CHD_EXTENSION);
 
             // move CHD if needed
-            rename_file(progress_bar, romfile_path, &new_chd_path, false).await?;
+            rename_cached_file(connection, progress_bar, romfile_path, &new_chd_path, false)
+                .await?;
 
             // persist in database
             create_or_update_romfile(connection, &new_chd_path, &[rom]).await;
@@ -837,34 +998,112 @@ async fn import_chd<P: AsRef<Path>>(
     }
 }
 
+#[cfg(feature = "chd")]
+async fn is_disk(
+    connection: &mut SqliteConnection,
+    chd_sha1: &str,
+    system: &Option<&System>,
+) -> bool {
+    !find_roms_by_size_and_hash(
+        connection,
+        0,
+        chd_sha1,
+        system,
+        &Vec::new(),
+        None,
+        &HashAlgorithm::Sha1,
+    )
+    .await
+    .is_empty()
+        || count_roms_by_size_and_hash(connection, 0, chd_sha1, system, &HashAlgorithm::Sha1).await
+            > 0
+}
+
+#[cfg(feature = "chd")]
+async fn import_disk<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    system: Option<&System>,
+    romfile_path: &P,
+    chd_sha1: &str,
+    trash: bool,
+) -> SimpleResult<Option<i64>> {
+    let mut roms = find_roms_by_size_and_hash(
+        connection,
+        0,
+        chd_sha1,
+        &system,
+        &Vec::new(),
+        None,
+        &HashAlgorithm::Sha1,
+    )
+    .await;
+
+    // merged disks are shared with the parent, prefer the parent's one
+    if roms.iter().any(|rom| rom.parent_id.is_none()) {
+        roms.retain(|rom| rom.parent_id.is_none());
+    }
+
+    let rom_game_system = match roms.len() {
+        0 => {
+            progress_bar.println("Already imported");
+            None
+        }
+        1 => {
+            let rom = roms.remove(0);
+            let game = find_game_by_id(connection, rom.game_id).await;
+            let system = find_system_by_id(connection, game.system_id).await;
+            progress_bar.println(format!("Matches \"{}\"", &rom.name));
+            Some((rom, game, system))
+        }
+        _ => {
+            let mut roms_games_systems: Vec<(Rom, Game, System)> = vec![];
+            for rom in roms {
+                let game = find_game_by_id(connection, rom.game_id).await;
+                let system = find_system_by_id(connection, game.system_id).await;
+                roms_games_systems.push((rom, game, system));
+            }
+            prompt_for_rom_game_system(&mut roms_games_systems)?
+        }
+    };
+
+    if let Some((rom, game, system)) = rom_game_system {
+        // put disks in subdirectories like MAME does
+        let new_path = get_system_directory(connection, progress_bar, &system)
+            .await?
+            .join(&game.name)
+            .join(&rom.name);
+
+        // move CHD if needed
+        rename_cached_file(connection, progress_bar, romfile_path, &new_path, false).await?;
+
+        // persist in database
+        create_or_update_romfile(connection, &new_path, &[rom]).await;
+
+        Ok(Some(system.id))
+    } else {
+        if trash {
+            move_to_trash(connection, progress_bar, romfile_path).await?;
+        }
+        Ok(None)
+    }
+}
+
 #[cfg(feature = "cso")]
 async fn import_cso<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
-    header: &Option<Header>,
     romfile_path: &P,
     hash_algorithm: &HashAlgorithm,
     trash: bool,
 ) -> SimpleResult<Option<i64>> {
-    let tmp_directory = create_tmp_directory(connection).await?;
-    let iso_path = maxcso::extract_cso(progress_bar, romfile_path, &tmp_directory.path())?;
-    let (size, hash) = get_size_and_hash(
-        connection,
-        progress_bar,
-        &iso_path,
-        header,
-        1,
-        1,
-        hash_algorithm,
-    )
-    .await?;
-    remove_file(progress_bar, &iso_path, true).await?;
+    let (size, hashes) = cso::get_cso_size_and_hashes(progress_bar, romfile_path, 1, 1)?;
     if let Some((rom, _game, system)) = find_rom_by_size_and_hash(
         connection,
         progress_bar,
         size,
-        &hash,
+        &hashes,
         &system,
         Vec::new(),
         None,
@@ -874,11 +1113,12 @@ async fn import_cso<P: AsRef<Path>>(
     {
         let system_directory = get_system_directory(connection, progress_bar, &system).await?;
 
+        // ZSOs keep their extension
         let mut new_cso_path = system_directory.join(&rom.name);
-        new_cso_path.set_extension(CSO_EXTENSION);
+        new_cso_path.set_extension(romfile_path.as_ref().extension().unwrap());
 
         // move CSO if needed
-        rename_file(progress_bar, romfile_path, &new_cso_path, false).await?;
+        rename_cached_file(connection, progress_bar, romfile_path, &new_cso_path, false).await?;
 
         // persist in database
         create_or_update_romfile(connection, &new_cso_path, &[rom]).await;
@@ -893,9 +1133,9 @@ async fn import_cso<P: AsRef<Path>>(
 }
 
 #[cfg(feature = "rvz")]
-async fn import_rvz<P: AsRef<Path>>(
+async fn import_dolphin_image<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
     header: &Option<Header>,
     romfile_path: &P,
@@ -903,23 +1143,17 @@ async fn import_rvz<P: AsRef<Path>>(
     trash: bool,
 ) -> SimpleResult<Option<i64>> {
     let tmp_directory = create_tmp_directory(connection).await?;
-    let iso_path = dolphin::extract_rvz(progress_bar, romfile_path, &tmp_directory.path())?;
-    let (size, hash) = get_size_and_hash(
-        connection,
-        progress_bar,
-        &iso_path,
-        header,
-        1,
-        1,
-        hash_algorithm,
-    )
-    .await?;
+    let iso_path =
+        dolphin::extract_image_for_hashing(progress_bar, romfile_path, &tmp_directory.path())?;
+    let (size, hashes) =
+        get_size_and_hashes(connection, progress_bar, &iso_path, header, 1, 1).await?;
+    let nkit = dolphin::is_nkit(&iso_path)?;
     remove_file(progress_bar, &iso_path, true).await?;
     if let Some((rom, _game, system)) = find_rom_by_size_and_hash(
         connection,
         progress_bar,
         size,
-        &hash,
+        &hashes,
         &system,
         Vec::new(),
         None,
@@ -929,17 +1163,27 @@ async fn import_rvz<P: AsRef<Path>>(
     {
         let system_directory = get_system_directory(connection, progress_bar, &system).await?;
 
-        let mut new_rvz_path = system_directory.join(&rom.name);
-        new_rvz_path.set_extension(RVZ_EXTENSION);
+        let mut new_image_path = system_directory.join(&rom.name);
+        new_image_path.set_extension(romfile_path.as_ref().extension().unwrap());
 
-        // move RVZ if needed
-        rename_file(progress_bar, romfile_path, &new_rvz_path, false).await?;
+        // move image if needed
+        rename_cached_file(
+            connection,
+            progress_bar,
+            romfile_path,
+            &new_image_path,
+            false,
+        )
+        .await?;
 
         // persist in database
-        create_or_update_romfile(connection, &new_rvz_path, &[rom]).await;
+        create_or_update_romfile(connection, &new_image_path, &[rom]).await;
 
         Ok(Some(system.id))
     } else {
+        if nkit {
+            progress_bar.println("NKit image detected, restore it to a full image first");
+        }
         if trash {
             move_to_trash(connection, progress_bar, romfile_path).await?;
         }
@@ -949,33 +1193,34 @@ async fn import_rvz<P: AsRef<Path>>(
 
 async fn import_other<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
     header: &Option<Header>,
     romfile_path: &P,
     romfile_extension: &str,
-    hash_algorithm: &HashAlgorithm,
-    trash: bool,
+    options: &ImportOptions,
+    size_and_hashes: Option<(u64, Hashes)>,
 ) -> SimpleResult<Option<i64>> {
-    let (size, hash) = get_size_and_hash(
-        connection,
-        progress_bar,
-        romfile_path,
-        header,
-        1,
-        1,
-        hash_algorithm,
-    )
-    .await?;
+    let cached_size_and_hashes = match size_and_hashes {
+        Some(_) => size_and_hashes,
+        None if options.cache => {
+            find_cached_size_and_hashes(connection, romfile_path, "", header).await
+        }
+        None => None,
+    };
+    let (size, hashes) = match cached_size_and_hashes {
+        Some(size_and_hashes) => size_and_hashes,
+        None => get_size_and_hashes(connection, progress_bar, romfile_path, header, 1, 1).await?,
+    };
     if let Some((rom, game, system)) = find_rom_by_size_and_hash(
         connection,
         progress_bar,
         size,
-        &hash,
+        &hashes,
         &system,
         Vec::new(),
         None,
-        hash_algorithm,
+        &options.hash_algorithm,
     )
     .await?
     {
@@ -994,14 +1239,22 @@ async fn import_other<P: AsRef<Path>>(
         }
 
         // move file if needed
-        rename_file(progress_bar, romfile_path, &new_path, false).await?;
+        rename_cached_file(connection, progress_bar, romfile_path, &new_path, false).await?;
 
         // persist in database
         create_or_update_romfile(connection, &new_path, &[rom]).await;
+        cache_size_and_hashes(connection, &new_path, "", header, size, &hashes).await?;
 
         Ok(Some(system.id))
     } else {
-        if trash {
+        cfg_if! {
+            if #[cfg(feature = "rvz")] {
+                if romfile_extension == ISO_EXTENSION && dolphin::is_nkit(romfile_path)? {
+                    progress_bar.println("NKit image detected, restore it to a full image first");
+                }
+            }
+        }
+        if options.trash {
             move_to_trash(connection, progress_bar, romfile_path).await?;
         }
         Ok(None)
@@ -1010,9 +1263,9 @@ async fn import_other<P: AsRef<Path>>(
 
 async fn find_rom_by_size_and_hash(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     size: u64,
-    hash: &str,
+    hashes: &Hashes,
     system: &Option<&System>,
     game_names: Vec<&str>,
     rom_name: Option<&str>,
@@ -1021,6 +1274,90 @@ async fn find_rom_by_size_and_hash(
     let mut rom_game_system: Option<(Rom, Game, System)> = None;
     let mut roms: Vec<Rom> = Vec::new();
 
+    // try the selected algorithm first, then fall back to the other hashes we already have
+    for (hash_algorithm, hash) in hashes.prioritized(hash_algorithm) {
+        roms = find_roms_by_size_and_hash(
+            connection,
+            size,
+            hash,
+            system,
+            &game_names,
+            rom_name,
+            &hash_algorithm,
+        )
+        .await;
+        if !roms.is_empty() {
+            break;
+        }
+    }
+
+    // abort if no match
+    if roms.is_empty() {
+        let mut rom_count = 0;
+        for (hash_algorithm, hash) in hashes.prioritized(hash_algorithm) {
+            rom_count +=
+                count_roms_by_size_and_hash(connection, size, hash, system, &hash_algorithm).await;
+        }
+        if rom_count > 0 {
+            progress_bar.println("Already imported");
+        } else {
+            progress_bar.println("No match");
+        }
+        return Ok(None);
+    }
+
+    // let user choose the rom if there are multiple matches
+    if roms.len() == 1 {
+        let rom = roms.remove(0);
+        let game = find_game_by_id(connection, rom.game_id).await;
+        let system = find_system_by_id(connection, game.system_id).await;
+        progress_bar.println(format!("Matches \"{}\"", &rom.name));
+        rom_game_system = Some((rom, game, system));
+    } else if system.is_some() {
+        let mut roms_games: Vec<(Rom, Game)> = vec![];
+        for rom in roms {
+            let game = find_game_by_id(connection, rom.game_id).await;
+            roms_games.push((rom, game));
+        }
+        if let Some((rom, game)) = prompt_for_rom_game(&mut roms_games)? {
+            let system = find_system_by_id(connection, game.system_id).await;
+            rom_game_system = Some((rom, game, system));
+        };
+    } else {
+        let mut roms_games_systems: Vec<(Rom, Game, System)> = vec![];
+        for rom in roms {
+            let game = find_game_by_id(connection, rom.game_id).await;
+            let system = find_system_by_id(connection, game.system_id).await;
+            roms_games_systems.push((rom, game, system));
+        }
+        rom_game_system = prompt_for_rom_game_system(&mut roms_games_systems)?;
+    }
+
+    // abort if rom already has a file
+    if rom_game_system.is_some() && rom_game_system.as_ref().unwrap().0.romfile_id.is_some() {
+        let romfile = find_romfile_by_id(
+            connection,
+            rom_game_system.as_ref().unwrap().0.romfile_id.unwrap(),
+        )
+        .await;
+        progress_bar.println(format!("Duplicate of \"{}\"", romfile.path));
+        return Ok(None);
+    }
+
+    Ok(rom_game_system)
+}
+
+async fn find_roms_by_size_and_hash(
+    connection: &mut SqliteConnection,
+    size: u64,
+    hash: &str,
+    system: &Option<&System>,
+    game_names: &Vec<&str>,
+    rom_name: Option<&str>,
+    hash_algorithm: &HashAlgorithm,
+) -> Vec<Rom> {
+    let mut roms: Vec<Rom> = Vec::new();
+
     // first try matching with game and rom names
     if !game_names.is_empty() && rom_name.is_some() {
         match hash_algorithm {
@@ -1031,7 +1368,7 @@ async fn find_rom_by_size_and_hash(
                         rom_name.unwrap(),
                         size,
                         hash,
-                        &game_names,
+                        game_names,
                         system.id,
                     )
                     .await
@@ -1043,7 +1380,7 @@ async fn find_rom_by_size_and_hash(
                         rom_name.unwrap(),
                         size,
                         hash,
-                        &game_names,
+                        game_names,
                     )
                     .await
                     .into_iter()
@@ -1057,7 +1394,7 @@ async fn find_rom_by_size_and_hash(
                         rom_name.unwrap(),
                         size,
                         hash,
-                        &game_names,
+                        game_names,
                         system.id,
                     )
                     .await
@@ -1069,7 +1406,7 @@ async fn find_rom_by_size_and_hash(
                         rom_name.unwrap(),
                         size,
                         hash,
-                        &game_names,
+                        game_names,
                     )
                     .await
                     .into_iter()
@@ -1083,7 +1420,7 @@ async fn find_rom_by_size_and_hash(
                     rom_name.unwrap(),
                     size,
                     hash,
-                    &game_names,
+                    game_names,
                     system.id,
                 )
                 .await
@@ -1095,7 +1432,7 @@ async fn find_rom_by_size_and_hash(
                         rom_name.unwrap(),
                         size,
                         hash,
-                        &game_names,
+                        game_names,
                     )
                     .await
                     .into_iter()
@@ -1111,21 +1448,14 @@ async fn find_rom_by_size_and_hash(
             HashAlgorithm::Crc => {
                 if let Some(system) = system {
                     find_roms_without_romfile_by_size_and_crc_and_game_names_and_system_id(
-                        connection,
-                        size,
-                        hash,
-                        &game_names,
-                        system.id,
+                        connection, size, hash, game_names, system.id,
                     )
                     .await
                     .into_iter()
                     .for_each(|rom| roms.push(rom))
                 } else {
                     find_roms_without_romfile_by_size_and_crc_and_game_names(
-                        connection,
-                        size,
-                        hash,
-                        &game_names,
+                        connection, size, hash, game_names,
                     )
                     .await
                     .into_iter()
@@ -1135,21 +1465,14 @@ async fn find_rom_by_size_and_hash(
             HashAlgorithm::Md5 => {
                 if let Some(system) = system {
                     find_roms_without_romfile_by_size_and_md5_and_game_names_and_system_id(
-                        connection,
-                        size,
-                        hash,
-                        &game_names,
-                        system.id,
+                        connection, size, hash, game_names, system.id,
                     )
                     .await
                     .into_iter()
                     .for_each(|rom| roms.push(rom))
                 } else {
                     find_roms_without_romfile_by_size_and_md5_and_game_names(
-                        connection,
-                        size,
-                        hash,
-                        &game_names,
+                        connection, size, hash, game_names,
                     )
                     .await
                     .into_iter()
@@ -1159,21 +1482,14 @@ async fn find_rom_by_size_and_hash(
             HashAlgorithm::Sha1 => {
                 if let Some(system) = system {
                     find_roms_without_romfile_by_size_and_sha1_and_game_names_and_system_id(
-                        connection,
-                        size,
-                        hash,
-                        &game_names,
-                        system.id,
+                        connection, size, hash, game_names, system.id,
                     )
                     .await
                     .into_iter()
                     .for_each(|rom| roms.push(rom))
                 } else {
                     find_roms_without_romfile_by_size_and_sha1_and_game_names(
-                        connection,
-                        size,
-                        hash,
-                        &game_names,
+                        connection, size, hash, game_names,
                     )
                     .await
                     .into_iter()
@@ -1234,84 +1550,45 @@ async fn find_rom_by_size_and_hash(
         };
     }
 
-    // abort if no match
-    if roms.is_empty() {
-        let rom_count = match hash_algorithm {
-            HashAlgorithm::Crc => match system {
-                Some(system) => {
-                    count_roms_with_romfile_by_size_and_crc_and_system_id(
-                        connection, size, hash, system.id,
-                    )
-                    .await
-                }
-                None => count_roms_with_romfile_by_size_and_crc(connection, size, hash).await,
-            },
-            HashAlgorithm::Md5 => match system {
-                Some(system) => {
-                    count_roms_with_romfile_by_size_and_md5_and_system_id(
-                        connection, size, hash, system.id,
-                    )
-                    .await
-                }
-                None => count_roms_with_romfile_by_size_and_md5(connection, size, hash).await,
-            },
-            HashAlgorithm::Sha1 => match system {
-                Some(system) => {
-                    count_roms_with_romfile_by_size_and_sha1_and_system_id(
-                        connection, size, hash, system.id,
-                    )
-                    .await
-                }
-                None => count_roms_with_romfile_by_size_and_sha1(connection, size, hash).await,
-            },
-        };
-        if rom_count > 0 {
-            progress_bar.println("Already imported");
-        } else {
-            progress_bar.println("No match");
-        }
-        return Ok(None);
-    }
-
-    // let user choose the rom if there are multiple matches
-    if roms.len() == 1 {
-        let rom = roms.remove(0);
-        let game = find_game_by_id(connection, rom.game_id).await;
-        let system = find_system_by_id(connection, game.system_id).await;
-        progress_bar.println(format!("Matches \"{}\"", &rom.name));
-        rom_game_system = Some((rom, game, system));
-    } else if system.is_some() {
-        let mut roms_games: Vec<(Rom, Game)> = vec![];
-        for rom in roms {
-            let game = find_game_by_id(connection, rom.game_id).await;
-            roms_games.push((rom, game));
-        }
-        if let Some((rom, game)) = prompt_for_rom_game(&mut roms_games)? {
-            let system = find_system_by_id(connection, game.system_id).await;
-            rom_game_system = Some((rom, game, system));
-        };
-    } else {
-        let mut roms_games_systems: Vec<(Rom, Game, System)> = vec![];
-        for rom in roms {
-            let game = find_game_by_id(connection, rom.game_id).await;
-            let system = find_system_by_id(connection, game.system_id).await;
-            roms_games_systems.push((rom, game, system));
-        }
-        rom_game_system = prompt_for_rom_game_system(&mut roms_games_systems)?;
-    }
+    roms
+}
 
-    // abort if rom already has a file
-    if rom_game_system.is_some() && rom_game_system.as_ref().unwrap().0.romfile_id.is_some() {
-        let romfile = find_romfile_by_id(
-            connection,
-            rom_game_system.as_ref().unwrap().0.romfile_id.unwrap(),
-        )
-        .await;
-        progress_bar.println(format!("Duplicate of \"{}\"", romfile.path));
-        return Ok(None);
+async fn count_roms_by_size_and_hash(
+    connection: &mut SqliteConnection,
+    size: u64,
+    hash: &str,
+    system: &Option<&System>,
+    hash_algorithm: &HashAlgorithm,
+) -> i32 {
+    match hash_algorithm {
+        HashAlgorithm::Crc => match system {
+            Some(system) => {
+                count_roms_with_romfile_by_size_and_crc_and_system_id(
+                    connection, size, hash, system.id,
+                )
+                .await
+            }
+            None => count_roms_with_romfile_by_size_and_crc(connection, size, hash).await,
+        },
+        HashAlgorithm::Md5 => match system {
+            Some(system) => {
+                count_roms_with_romfile_by_size_and_md5_and_system_id(
+                    connection, size, hash, system.id,
+                )
+                .await
+            }
+            None => count_roms_with_romfile_by_size_and_md5(connection, size, hash).await,
+        },
+        HashAlgorithm::Sha1 => match system {
+            Some(system) => {
+                count_roms_with_romfile_by_size_and_sha1_and_system_id(
+                    connection, size, hash, system.id,
+                )
+                .await
+            }
+            None => count_roms_with_romfile_by_size_and_sha1(connection, size, hash).await,
+        },
     }
-
-    Ok(rom_game_system)
 }
 
 #[cfg(feature = "ird")]
@@ -1320,7 +1597,7 @@ async fn find_sfb_rom_by_md5(
     size: u64,
     md5: &str,
     system: &System,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<Option<(Rom, Game)>> {
     let rom_game: Option<(Rom, Game)>;
     let mut roms = find_roms_without_romfile_by_name_and_size_and_md5_and_system_id(
@@ -1393,7 +1670,7 @@ async fn create_or_update_romfile<P: AsRef<Path>>(
                 connection,
                 romfile.id,
                 &romfile.path,
-                romfile_path.as_ref().metadata().await.unwrap().len(),
+                get_file_size(romfile_path).await,
             )
             .await;
             romfile.id
@@ -1402,7 +1679,7 @@ async fn create_or_update_romfile<P: AsRef<Path>>(
             create_romfile(
                 connection,
                 romfile_path.as_ref().as_os_str().to_str().unwrap(),
-                romfile_path.as_ref().metadata().await.unwrap().len(),
+                get_file_size(romfile_path).await,
             )
             .await
         }
@@ -1414,21 +1691,21 @@ async fn create_or_update_romfile<P: AsRef<Path>>(
 
 async fn move_to_trash<P: AsRef<Path>>(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     romfile_path: &P,
 ) -> SimpleResult<()> {
     let new_path = get_rom_directory(connection)
         .await
         .join("Trash")
         .join(romfile_path.as_ref().file_name().unwrap());
-    rename_file(progress_bar, romfile_path, &new_path, false).await?;
+    rename_cached_file(connection, progress_bar, romfile_path, &new_path, false).await?;
     match find_romfile_by_path(connection, new_path.as_os_str().to_str().unwrap()).await {
         Some(romfile) => {
             update_romfile(
                 connection,
                 romfile.id,
                 new_path.as_os_str().to_str().unwrap(),
-                new_path.metadata().await.unwrap().len(),
+                get_file_size(&new_path).await,
             )
             .await;
         }
@@ -1436,7 +1713,7 @@ async fn move_to_trash<P: AsRef<Path>>(
             create_romfile(
                 connection,
                 new_path.as_os_str().to_str().unwrap(),
-                new_path.metadata().await.unwrap().len(),
+                get_file_size(&new_path).await,
             )
             .await;
         }
@@ -1444,6 +1721,8 @@ async fn move_to_trash<P: AsRef<Path>>(
     Ok(())
 }
 
+#[cfg(all(test, feature = "chd"))]
+mod test_chd_disk;
 #[cfg(all(test, feature = "chd"))]
 mod test_chd_multiple_tracks;
 #[cfg(all(test, feature = "chd"))]
@@ -1453,10 +1732,18 @@ mod test_chd_single_track;
 #[cfg(all(test, feature = "cso"))]
 mod test_cso;
 #[cfg(test)]
+mod test_dry_run;
+#[cfg(test)]
 mod test_original;
 #[cfg(test)]
+mod test_original_convert_to_zip;
+#[cfg(test)]
 mod test_original_headered;
 #[cfg(test)]
+mod test_original_multiple_files;
+#[cfg(test)]
+mod test_original_sha1;
+#[cfg(test)]
 mod test_sevenzip_multiple_files_full_game;
 #[cfg(test)]
 mod test_sevenzip_multiple_files_headered_mixed_games;
@@ -0,0 +1,82 @@
+use super::super::database::*;
+use super::super::import_dats;
+use super::*;
+use async_std::fs;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).rom");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).rom"),
+        &romfile_path.as_os_str().to_str().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
+        .await
+        .unwrap();
+
+    // when
+    set_dry_run(true);
+    let mut transaction = begin_transaction(&mut connection).await;
+    create_dry_run_journal(&mut transaction).await;
+    let result = import_other(
+        &mut transaction,
+        &progress_bar,
+        Some(&system),
+        &None,
+        &romfile_path,
+        romfile_path.extension().unwrap().to_str().unwrap(),
+        &ImportOptions {
+            hash_algorithm: HashAlgorithm::Crc,
+            trash: true,
+            force: false,
+            cache: true,
+        },
+        None,
+    )
+    .await;
+    let journal = find_dry_run_journal(&mut transaction).await;
+    rollback_transaction(transaction).await;
+    set_dry_run(false);
+
+    // then
+    result.unwrap();
+    assert!(journal.contains(&(String::from("romfiles"), String::from("INSERT"), 1)));
+    assert!(journal.contains(&(String::from("roms"), String::from("UPDATE"), 1)));
+
+    let romfiles = find_romfiles(&mut connection).await;
+    assert!(romfiles.is_empty());
+    assert!(romfile_path.is_file().await);
+    assert!(
+        !system_directory
+            .join("Test Game (USA, Europe).rom")
+            .is_file()
+            .await
+    );
+}
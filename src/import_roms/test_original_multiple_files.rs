@@ -0,0 +1,83 @@
+use super::super::database::*;
+use super::super::import_dats;
+use super::*;
+use async_std::fs;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let mut romfile_paths: Vec<PathBuf> = Vec::new();
+    for rom_name in [
+        "Test Game (Asia).rom",
+        "Test Game (Japan).rom",
+        "Test Game (USA, Europe).rom",
+        "Test Game (USA, Europe) (Beta).rom",
+    ] {
+        let romfile_path = tmp_directory.join(rom_name);
+        fs::copy(test_directory.join(rom_name), &romfile_path)
+            .await
+            .unwrap();
+        romfile_paths.push(romfile_path);
+    }
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
+        .await
+        .unwrap();
+
+    // when
+    let system_ids = import_roms(
+        &mut connection,
+        &progress_bar,
+        Some(&system),
+        &None,
+        &romfile_paths,
+        &ImportOptions {
+            hash_algorithm: HashAlgorithm::Crc,
+            trash: true,
+            force: false,
+            cache: true,
+        },
+    )
+    .await
+    .unwrap();
+
+    // then
+    assert_eq!(system_ids.len(), 1);
+    assert!(system_ids.contains(&system.id));
+
+    let roms = find_roms_with_romfile_by_system_id(&mut connection, system.id).await;
+    assert_eq!(roms.len(), 4);
+    let romfiles = find_romfiles(&mut connection).await;
+    assert_eq!(romfiles.len(), 4);
+
+    for romfile in romfiles {
+        assert!(Path::new(&romfile.path).is_file().await);
+        assert!(Path::new(&romfile.path).starts_with(&system_directory));
+    }
+    for romfile_path in romfile_paths {
+        assert!(!romfile_path.is_file().await);
+    }
+}
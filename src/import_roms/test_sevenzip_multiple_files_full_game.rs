@@ -11,7 +11,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -49,8 +49,12 @@ async fn test() {
         &None,
         &romfile_path,
         romfile_path.extension().unwrap().to_str().unwrap(),
-        &HashAlgorithm::Crc,
-        true,
+        &ImportOptions {
+            hash_algorithm: HashAlgorithm::Crc,
+            trash: true,
+            force: false,
+            cache: true,
+        },
     )
     .await
     .unwrap();
@@ -1,7 +1,6 @@
 use super::progress::*;
 use super::SimpleResult;
 use async_std::path::Path;
-use indicatif::ProgressBar;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::process::Command;
@@ -16,7 +15,7 @@ lazy_static! {
 }
 
 pub fn parse_iso<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     iso_path: &P,
 ) -> SimpleResult<Vec<(String, i64, u64)>> {
     progress_bar.set_message("Parsing ISO header");
@@ -0,0 +1,290 @@
+#[cfg(feature = "chd")]
+use super::chdman;
+use super::checksum::*;
+use super::config::*;
+#[cfg(feature = "cso")]
+use super::cso;
+use super::database::*;
+use super::model::*;
+use super::progress::*;
+use super::sevenzip;
+use super::SimpleResult;
+use async_std::path::PathBuf;
+use cfg_if::cfg_if;
+use clap::{Arg, ArgGroup, ArgMatches, Command};
+use sqlx::sqlite::SqliteConnection;
+
+pub type Match = (System, Game, Rom);
+
+pub fn subcommand() -> Command {
+    Command::new("lookup")
+        .about("Identify ROM files or hashes without importing them")
+        .arg(
+            Arg::new("FILES")
+                .help("Set the files to look up")
+                .required_unless_present("HASH")
+                .num_args(1..)
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with_all(["HASH", "SIZE"]),
+        )
+        .arg(
+            Arg::new("CRC")
+                .short('c')
+                .long("crc")
+                .help("Look up a CRC")
+                .required(false)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("MD5")
+                .short('m')
+                .long("md5")
+                .help("Look up an MD5")
+                .required(false)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("SHA1")
+                .short('s')
+                .long("sha1")
+                .help("Look up a SHA1")
+                .required(false)
+                .num_args(1),
+        )
+        .group(
+            ArgGroup::new("HASH")
+                .args(["CRC", "MD5", "SHA1"])
+                .multiple(true)
+                .requires("SIZE"),
+        )
+        .arg(
+            Arg::new("SIZE")
+                .short('z')
+                .long("size")
+                .help("Set the size of the hashed file in bytes")
+                .required(false)
+                .num_args(1)
+                .value_parser(value_parser!(u64)),
+        )
+}
+
+pub async fn main(
+    connection: &mut SqliteConnection,
+    matches: &ArgMatches,
+    progress_bar: &Progress,
+) -> SimpleResult<()> {
+    match matches.get_many::<PathBuf>("FILES") {
+        Some(file_paths) => {
+            let file_paths: Vec<&PathBuf> = file_paths.collect();
+            for (position, file_path) in file_paths.iter().enumerate() {
+                let entries = lookup_file(
+                    connection,
+                    progress_bar,
+                    file_path,
+                    position + 1,
+                    file_paths.len(),
+                )
+                .await?;
+                for (name, matches) in entries {
+                    progress_bar.println(name);
+                    print_matches(progress_bar, &matches);
+                }
+            }
+        }
+        None => {
+            let size = *matches.get_one::<u64>("SIZE").unwrap();
+            let hashes = Hashes {
+                crc: matches
+                    .get_one::<String>("CRC")
+                    .map(|crc| crc.to_lowercase()),
+                md5: matches
+                    .get_one::<String>("MD5")
+                    .map(|md5| md5.to_lowercase()),
+                sha1: matches
+                    .get_one::<String>("SHA1")
+                    .map(|sha1| sha1.to_lowercase()),
+            };
+            let matches = lookup_hashes(connection, size, &hashes).await;
+            print_matches(progress_bar, &matches);
+        }
+    }
+    Ok(())
+}
+
+// returns the matches for each entry of the file, keyed by a displayable name
+pub async fn lookup_file(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    file_path: &PathBuf,
+    position: usize,
+    total: usize,
+) -> SimpleResult<Vec<(String, Vec<Match>)>> {
+    if !file_path.is_file().await {
+        bail!("{:?} is not a file", file_path);
+    }
+    let extension = file_path
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap()
+        .to_lowercase();
+    let mut entries: Vec<(String, Vec<Match>)> = Vec::new();
+
+    // archives are looked up entry by entry using the CRCs from their listing
+    if ARCHIVE_EXTENSIONS.contains(&extension.as_str()) {
+        let archive_infos = sevenzip::parse_archive(progress_bar, file_path)?;
+        for archive_info in archive_infos {
+            let matches = lookup_hashes(
+                connection,
+                archive_info.size,
+                &Hashes::from_crc(&archive_info.crc),
+            )
+            .await;
+            entries.push((
+                format!(
+                    "{:?} ({})",
+                    file_path.file_name().unwrap(),
+                    archive_info.path
+                ),
+                matches,
+            ));
+        }
+    } else if [CHD_EXTENSION, CSO_EXTENSION, ZSO_EXTENSION].contains(&extension.as_str()) {
+        let (size, hashes) =
+            get_container_size_and_hashes(progress_bar, file_path, &extension, position, total)?;
+        let matches = lookup_hashes(connection, size, &hashes).await;
+        entries.push((format!("{:?}", file_path.file_name().unwrap()), matches));
+    } else {
+        let (size, hashes) =
+            compute_size_and_hashes(progress_bar, file_path, &None, &[], position, total)?;
+        let mut matches = lookup_hashes(connection, size, &hashes).await;
+
+        // headered files only match once their header is stripped
+        if matches.is_empty() {
+            for header in find_headers(connection).await {
+                let rules = find_rules_by_header_id(connection, header.id).await;
+                let (headerless_size, hashes) = compute_size_and_hashes(
+                    progress_bar,
+                    file_path,
+                    &Some(header),
+                    &rules,
+                    position,
+                    total,
+                )?;
+                if headerless_size == size {
+                    continue;
+                }
+                matches = lookup_hashes(connection, headerless_size, &hashes).await;
+                if !matches.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        entries.push((format!("{:?}", file_path.file_name().unwrap()), matches));
+    }
+    Ok(entries)
+}
+
+// containers are decoded so that their content is looked up
+fn get_container_size_and_hashes(
+    progress_bar: &Progress,
+    file_path: &PathBuf,
+    extension: &str,
+    position: usize,
+    total: usize,
+) -> SimpleResult<(u64, Hashes)> {
+    if CHD_EXTENSION == extension {
+        cfg_if! {
+            if #[cfg(feature = "chd")] {
+                chdman::get_chd_size_and_hashes(progress_bar, file_path)
+            } else {
+                bail!("Please rebuild with the CHD feature enabled");
+            }
+        }
+    } else {
+        cfg_if! {
+            if #[cfg(feature = "cso")] {
+                cso::get_cso_size_and_hashes(progress_bar, file_path, position, total)
+            } else {
+                bail!("Please rebuild with the CSO feature enabled");
+            }
+        }
+    }
+}
+
+// the most reliable hash with a match wins, a ROM is owned when it has a ROM file
+pub async fn lookup_hashes(
+    connection: &mut SqliteConnection,
+    size: u64,
+    hashes: &Hashes,
+) -> Vec<Match> {
+    let mut roms: Vec<Rom> = Vec::new();
+    for (hash_algorithm, hash) in hashes.prioritized(&HashAlgorithm::Sha1) {
+        roms = match hash_algorithm {
+            HashAlgorithm::Crc => {
+                let mut roms = find_roms_with_romfile_by_size_and_crc(connection, size, hash).await;
+                roms.append(
+                    &mut find_roms_without_romfile_by_size_and_crc(connection, size, hash).await,
+                );
+                roms
+            }
+            HashAlgorithm::Md5 => {
+                let mut roms = find_roms_with_romfile_by_size_and_md5(connection, size, hash).await;
+                roms.append(
+                    &mut find_roms_without_romfile_by_size_and_md5(connection, size, hash).await,
+                );
+                roms
+            }
+            HashAlgorithm::Sha1 => {
+                let mut roms =
+                    find_roms_with_romfile_by_size_and_sha1(connection, size, hash).await;
+                roms.append(
+                    &mut find_roms_without_romfile_by_size_and_sha1(connection, size, hash).await,
+                );
+                roms
+            }
+        };
+        if !roms.is_empty() {
+            break;
+        }
+    }
+
+    let mut matches: Vec<Match> = Vec::new();
+    for rom in roms {
+        let game = find_game_by_id(connection, rom.game_id).await;
+        let system = find_system_by_id(connection, game.system_id).await;
+        matches.push((system, game, rom));
+    }
+    matches
+}
+
+fn print_matches(progress_bar: &Progress, matches: &[Match]) {
+    if matches.is_empty() {
+        progress_bar.println("  No match");
+    }
+    for (system, game, rom) in matches {
+        progress_bar.println(format!(
+            "  {} / {} / {} ({})",
+            system.name,
+            game.name,
+            rom.name,
+            if rom.romfile_id.is_some() {
+                "owned"
+            } else {
+                "missing"
+            }
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "chd"))]
+mod test_lookup_chd;
+#[cfg(all(test, feature = "cso"))]
+mod test_lookup_cso;
+#[cfg(test)]
+mod test_lookup_file;
+#[cfg(test)]
+mod test_lookup_hash;
+#[cfg(test)]
+mod test_lookup_headered_file;
@@ -0,0 +1,40 @@
+use super::super::import_dats;
+use super::*;
+use async_std::path::Path;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let file_path = test_directory.join("Test Game (USA, Europe) (Single Track).chd");
+
+    // when
+    let entries = lookup_file(&mut connection, &progress_bar, &file_path, 1, 1)
+        .await
+        .unwrap();
+
+    // then
+    assert_eq!(entries.len(), 1);
+    let (_, matches) = entries.first().unwrap();
+    assert_eq!(matches.len(), 1);
+    let (_, game, _) = matches.first().unwrap();
+    assert_eq!(game.name, "Test Game (USA, Europe) (ISO)");
+}
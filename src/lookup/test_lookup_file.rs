@@ -0,0 +1,47 @@
+use super::super::import_dats;
+use super::*;
+use async_std::path::Path;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let file_path = test_directory.join("Test Game (Japan).rom");
+    let (size, hashes) =
+        compute_size_and_hashes(&progress_bar, &file_path, &None, &[], 1, 1).unwrap();
+
+    // when
+    let matches = lookup_hashes(&mut connection, size, &hashes).await;
+
+    // then
+    assert_eq!(matches.len(), 1);
+    let (_, game, rom) = matches.first().unwrap();
+    assert_eq!(game.name, "Test Game (Japan)");
+    assert!(rom.romfile_id.is_none());
+
+    // the file is left where it is
+    let matches =
+        subcommand().get_matches_from(["lookup", file_path.as_os_str().to_str().unwrap()]);
+    main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+    assert!(file_path.is_file().await);
+}
@@ -0,0 +1,64 @@
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use async_std::path::Path;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).rom");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).rom"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+
+    let matches = import_roms::subcommand()
+        .get_matches_from(["import-roms", romfile_path.as_os_str().to_str().unwrap()]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    // when
+    let owned_matches = lookup_hashes(&mut connection, 256, &Hashes::from_crc("CC721E14")).await;
+    let missing_matches = lookup_hashes(&mut connection, 256, &Hashes::from_crc("310212e8")).await;
+    let no_matches = lookup_hashes(&mut connection, 512, &Hashes::from_crc("310212e8")).await;
+
+    // then
+    assert_eq!(owned_matches.len(), 1);
+    let (system, game, rom) = owned_matches.first().unwrap();
+    assert_eq!(system.name, "Test System");
+    assert_eq!(game.name, "Test Game (USA, Europe)");
+    assert_eq!(rom.name, "Test Game (USA, Europe).rom");
+    assert!(rom.romfile_id.is_some());
+
+    assert_eq!(missing_matches.len(), 1);
+    let (_, game, rom) = missing_matches.first().unwrap();
+    assert_eq!(game.name, "Test Game (Japan)");
+    assert!(rom.romfile_id.is_none());
+
+    assert!(no_matches.is_empty());
+}
@@ -11,6 +11,7 @@ extern crate cfg_if;
 #[macro_use]
 extern crate clap;
 extern crate crc32fast;
+extern crate csv;
 extern crate dialoguer;
 extern crate digest;
 extern crate dirs;
@@ -21,6 +22,8 @@ extern crate http_types;
 extern crate indicatif;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "cso")]
+extern crate lz4_flex;
 #[cfg(feature = "ird")]
 extern crate md5;
 extern crate num_derive;
@@ -32,6 +35,8 @@ extern crate rayon;
 extern crate regex;
 extern crate rust_embed;
 extern crate serde;
+extern crate serde_json;
+extern crate sevenz_rust;
 extern crate sha1;
 #[macro_use]
 extern crate simple_error;
@@ -53,12 +58,16 @@ mod benchmark;
 mod chdman;
 mod check_roms;
 mod checksum;
+mod clrmamepro;
 mod config;
 mod convert_roms;
+#[cfg(feature = "cso")]
+mod cso;
 mod database;
 #[cfg(feature = "rvz")]
 mod dolphin;
 mod download_dats;
+mod export_dats;
 mod generate_playlists;
 mod import_dats;
 #[cfg(feature = "ird")]
@@ -66,28 +75,31 @@ mod import_irds;
 mod import_roms;
 #[cfg(feature = "ird")]
 mod isoinfo;
-#[cfg(feature = "cso")]
-mod maxcso;
+mod lookup;
 mod model;
 mod progress;
 mod prompt;
 mod purge_roms;
 mod purge_systems;
 mod rebuild_roms;
+mod report;
 #[cfg(feature = "server")]
 mod server;
 mod sevenzip;
 mod sort_roms;
+mod staging;
+mod undo;
 mod util;
 
 use async_std::path::PathBuf;
 use cfg_if::cfg_if;
-use clap::Command;
-use config::{get_rom_directory, get_tmp_directory};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use config::{get_rom_directory, get_tmp_directory, set_dry_run};
 use database::*;
 use dotenv::dotenv;
 use progress::*;
 use simple_error::SimpleError;
+use sqlx::sqlite::SqliteConnection;
 use std::env;
 use util::*;
 
@@ -100,6 +112,7 @@ async fn main() -> SimpleResult<()> {
         config::subcommand(),
         import_dats::subcommand(),
         download_dats::subcommand(),
+        export_dats::subcommand(),
         import_roms::subcommand(),
         sort_roms::subcommand(),
         convert_roms::subcommand(),
@@ -108,6 +121,9 @@ async fn main() -> SimpleResult<()> {
         purge_roms::subcommand(),
         purge_systems::subcommand(),
         generate_playlists::subcommand(),
+        report::subcommand(),
+        lookup::subcommand(),
+        undo::subcommand(),
     ];
     cfg_if! {
         if #[cfg(feature = "ird")] {
@@ -128,6 +144,14 @@ async fn main() -> SimpleResult<()> {
         .version(env!("CARGO_PKG_VERSION"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .author(env!("CARGO_PKG_AUTHORS"))
+        .arg(
+            Arg::new("DRY_RUN")
+                .long("dry-run")
+                .help("Print the planned changes without applying them")
+                .required(false)
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
         .subcommands(subcommands)
         .get_matches();
 
@@ -152,120 +176,52 @@ async fn main() -> SimpleResult<()> {
         get_rom_directory(&mut pool.acquire().await.unwrap()).await;
         get_tmp_directory(&mut pool.acquire().await.unwrap()).await;
 
+        // clean up after conversions that were interrupted, only where new ones can start
+        if !matches.get_flag("DRY_RUN")
+            && matches!(
+                matches.subcommand_name(),
+                Some("convert-roms") | Some("import-roms") | Some("server")
+            )
+        {
+            staging::recover_staging_directories(&mut pool.acquire().await.unwrap(), &progress_bar)
+                .await?;
+        }
+
         match matches.subcommand_name() {
-            Some("config") => {
-                config::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("config").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("import-dats") => {
-                import_dats::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("import-dats").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("download-dats") => {
-                download_dats::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("download-dats").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("import-irds") => {
+            Some("server") => {
                 cfg_if! {
-                    if #[cfg(feature = "ird")] {
-                        import_irds::main(
-                            &mut pool.acquire().await.unwrap(),
-                            matches.subcommand_matches("import-irds").unwrap(),
-                            &progress_bar,
-                        ).await?
+                    if #[cfg(feature = "server")] {
+                        server::main(pool, matches.subcommand_matches("server").unwrap()).await?
                     }
                 }
             }
-            Some("import-roms") => {
-                import_roms::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("import-roms").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("sort-roms") => {
-                sort_roms::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("sort-roms").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("convert-roms") => {
-                convert_roms::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("convert-roms").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("rebuild-roms") => {
-                rebuild_roms::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("rebuild-roms").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("check-roms") => {
-                check_roms::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("check-roms").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("purge-roms") => {
-                purge_roms::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("purge-roms").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("purge-systems") => {
-                purge_systems::main(&mut pool.acquire().await.unwrap(), &progress_bar).await?
-            }
-            Some("generate-playlists") => {
-                generate_playlists::main(
-                    &mut pool.acquire().await.unwrap(),
-                    matches.subcommand_matches("generate-playlists").unwrap(),
-                    &progress_bar,
-                )
-                .await?
-            }
-            Some("benchmark") => {
-                cfg_if! {
-                    if #[cfg(feature = "benchmark")] {
-                        benchmark::main(
-                            &mut pool.acquire().await.unwrap(),
-                            matches.subcommand_matches("benchmark").unwrap(),
-                            &progress_bar,
-                        ).await?
+            _ => {
+                if matches.get_flag("DRY_RUN") {
+                    // run everything in a transaction that is rolled back at the end
+                    set_dry_run(true);
+                    let mut connection = pool.acquire().await.unwrap();
+                    let mut transaction = begin_transaction(&mut connection).await;
+                    create_dry_run_journal(&mut transaction).await;
+                    run_subcommand(&mut transaction, &matches, &progress_bar).await?;
+                    progress_bar.println("Database changes:");
+                    let journal = find_dry_run_journal(&mut transaction).await;
+                    if journal.is_empty() {
+                        progress_bar.println("None");
                     }
-                }
-            }
-            Some("server") => {
-                cfg_if! {
-                    if #[cfg(feature = "server")] {
-                        server::main(pool, matches.subcommand_matches("server").unwrap()).await?
+                    for (table_name, operation, count) in journal {
+                        progress_bar.println(format!(
+                            "Would {} {} row(s) in \"{}\"",
+                            operation.to_lowercase(),
+                            count,
+                            table_name
+                        ));
                     }
+                    rollback_transaction(transaction).await;
+                } else {
+                    run_subcommand(&mut pool.acquire().await.unwrap(), &matches, &progress_bar)
+                        .await?
                 }
             }
-            _ => (),
         }
         cfg_if! {
             if #[cfg(not(feature = "server"))] {
@@ -276,3 +232,149 @@ async fn main() -> SimpleResult<()> {
 
     Ok(())
 }
+
+async fn run_subcommand(
+    connection: &mut SqliteConnection,
+    matches: &ArgMatches,
+    progress_bar: &Progress,
+) -> SimpleResult<()> {
+    match matches.subcommand_name() {
+        Some("config") => {
+            config::main(
+                connection,
+                matches.subcommand_matches("config").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("import-dats") => {
+            import_dats::main(
+                connection,
+                matches.subcommand_matches("import-dats").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("download-dats") => {
+            download_dats::main(
+                connection,
+                matches.subcommand_matches("download-dats").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("export-dats") => {
+            export_dats::main(
+                connection,
+                matches.subcommand_matches("export-dats").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("import-irds") => {
+            cfg_if! {
+                if #[cfg(feature = "ird")] {
+                    import_irds::main(
+                        connection,
+                        matches.subcommand_matches("import-irds").unwrap(),
+                        progress_bar,
+                    ).await?
+                }
+            }
+        }
+        Some("import-roms") => {
+            import_roms::main(
+                connection,
+                matches.subcommand_matches("import-roms").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("sort-roms") => {
+            sort_roms::main(
+                connection,
+                matches.subcommand_matches("sort-roms").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("convert-roms") => {
+            convert_roms::main(
+                connection,
+                matches.subcommand_matches("convert-roms").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("rebuild-roms") => {
+            rebuild_roms::main(
+                connection,
+                matches.subcommand_matches("rebuild-roms").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("check-roms") => {
+            check_roms::main(
+                connection,
+                matches.subcommand_matches("check-roms").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("purge-roms") => {
+            purge_roms::main(
+                connection,
+                matches.subcommand_matches("purge-roms").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("purge-systems") => purge_systems::main(connection, progress_bar).await?,
+        Some("generate-playlists") => {
+            generate_playlists::main(
+                connection,
+                matches.subcommand_matches("generate-playlists").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("report") => {
+            report::main(
+                connection,
+                matches.subcommand_matches("report").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("lookup") => {
+            lookup::main(
+                connection,
+                matches.subcommand_matches("lookup").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("undo") => {
+            undo::main(
+                connection,
+                matches.subcommand_matches("undo").unwrap(),
+                progress_bar,
+            )
+            .await?
+        }
+        Some("benchmark") => {
+            cfg_if! {
+                if #[cfg(feature = "benchmark")] {
+                    benchmark::main(
+                        connection,
+                        matches.subcommand_matches("benchmark").unwrap(),
+                        progress_bar,
+                    ).await?
+                }
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
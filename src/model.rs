@@ -1,7 +1,7 @@
 #[cfg(feature = "server")]
-use async_graphql::{Enum, SimpleObject};
+use async_graphql::{Enum, InputObject, SimpleObject};
 use num_derive::FromPrimitive;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, Type};
 #[cfg(feature = "ird")]
 use std::collections::HashMap;
@@ -29,9 +29,11 @@ pub struct System {
     pub complete: bool,
     pub arcade: bool,
     pub merging: i64,
+    pub preferred_format: Option<String>,
 }
 
-#[cfg_attr(feature = "server", derive(Clone, SimpleObject))]
+#[derive(Clone)]
+#[cfg_attr(feature = "server", derive(SimpleObject))]
 pub struct Header {
     pub id: i64,
     pub name: String,
@@ -77,6 +79,15 @@ pub struct Game {
     pub playlist_id: Option<i64>,
 }
 
+#[cfg(feature = "server")]
+#[derive(Default, InputObject)]
+pub struct GameFilter {
+    pub name: Option<String>,
+    pub complete: Option<bool>,
+    pub ignored: Option<bool>,
+    pub one_region: Option<bool>,
+}
+
 #[derive(FromRow)]
 #[cfg_attr(feature = "server", derive(Clone, SimpleObject))]
 #[cfg_attr(feature = "server", graphql(complex))]
@@ -102,6 +113,31 @@ pub struct Romfile {
     pub size: i64,
 }
 
+#[derive(Clone, Copy, FromPrimitive, Type, Eq, PartialEq)]
+#[repr(i8)]
+pub enum OperationType {
+    Rename = 0,
+    Create = 1,
+    Delete = 2,
+}
+
+pub struct Operation {
+    pub operation: i64,
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub romfile_id: Option<i64>,
+}
+
+pub struct CachedHashes {
+    pub file_size: i64,
+    pub file_mtime: i64,
+    pub header_id: Option<i64>,
+    pub size: i64,
+    pub crc: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
 #[cfg_attr(feature = "server", derive(Clone, SimpleObject))]
 pub struct Setting {
     pub id: i64,
@@ -109,64 +145,173 @@ pub struct Setting {
     pub value: Option<String>,
 }
 
+#[cfg_attr(feature = "server", derive(Clone, SimpleObject))]
+pub struct SystemSetting {
+    pub id: i64,
+    pub system_id: i64,
+    pub key: String,
+    pub value: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct ProfileXml {
     #[serde(alias = "datfile")]
     pub systems: Vec<SystemXml>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct DatfileXml {
-    #[serde(alias = "header")]
+    #[serde(rename(serialize = "header"), alias = "header")]
     pub system: SystemXml,
-    #[serde(alias = "game", alias = "machine")]
+    #[serde(rename(serialize = "game"), alias = "game", alias = "machine")]
     pub games: Vec<GameXml>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct SystemXml {
     pub name: String,
     pub description: String,
     pub version: String,
-    #[serde(alias = "clrmamepro", default)]
-    pub clrmamepros: Vec<ClrMameProXml>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    #[serde(
+        rename(serialize = "clrmamepro"),
+        alias = "clrmamepro",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub clrmamepros: Vec<ClrMameProXml>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ClrMameProXml {
-    #[serde(rename = "@header")]
+    #[serde(rename = "@header", skip_serializing_if = "Option::is_none")]
     pub header: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct GameXml {
     #[serde(rename = "@name")]
     pub name: String,
-    pub description: String,
-    pub comment: Option<String>,
-    #[serde(rename = "@cloneof")]
+    #[serde(rename = "@cloneof", skip_serializing_if = "Option::is_none")]
     pub cloneof: Option<String>,
-    #[serde(rename = "@romof")]
+    #[serde(rename = "@romof", skip_serializing_if = "Option::is_none")]
     pub romof: Option<String>,
-    #[serde(rename = "@isbios")]
+    #[serde(rename = "@isbios", skip_serializing_if = "Option::is_none")]
     pub isbios: Option<String>,
-    #[serde(alias = "rom", default)]
+    #[serde(rename = "@isdevice", skip_serializing_if = "Option::is_none")]
+    pub isdevice: Option<String>,
+    #[serde(rename = "@runnable", skip_serializing_if = "Option::is_none")]
+    pub runnable: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub description: String,
+    #[serde(rename(serialize = "rom"), alias = "rom", default)]
     pub roms: Vec<RomXml>,
+    #[serde(
+        rename(serialize = "disk"),
+        alias = "disk",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub disks: Vec<DiskXml>,
+    #[serde(skip_serializing)]
+    pub driver: Option<DriverXml>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct RomXml {
     #[serde(rename = "@name")]
     pub name: String,
-    #[serde(rename = "@merge")]
+    #[serde(rename = "@merge", skip_serializing_if = "Option::is_none")]
     pub merge: Option<String>,
     #[serde(rename = "@size")]
     pub size: i64,
-    #[serde(rename = "@crc")]
+    #[serde(rename = "@crc", skip_serializing_if = "Option::is_none")]
     pub crc: Option<String>,
-    #[serde(rename = "@md5")]
+    #[serde(rename = "@md5", skip_serializing_if = "Option::is_none")]
     pub md5: Option<String>,
+    #[serde(rename = "@sha1", skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(rename = "@status", skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DiskXml {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@merge", skip_serializing_if = "Option::is_none")]
+    pub merge: Option<String>,
+    #[serde(rename = "@sha1", skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(rename = "@status", skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DriverXml {
+    #[serde(rename = "@status")]
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct MameXml {
+    #[serde(rename = "@build")]
+    pub build: Option<String>,
+    #[serde(rename = "machine", alias = "game", default)]
+    pub machines: Vec<GameXml>,
+}
+
+#[derive(Deserialize)]
+pub struct SoftwareListXml {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@description")]
+    pub description: Option<String>,
+    #[serde(rename = "software", default)]
+    pub softwares: Vec<SoftwareXml>,
+}
+
+#[derive(Deserialize)]
+pub struct SoftwareXml {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@cloneof")]
+    pub cloneof: Option<String>,
+    pub description: String,
+    #[serde(rename = "part", default)]
+    pub parts: Vec<PartXml>,
+}
+
+#[derive(Deserialize)]
+pub struct PartXml {
+    #[serde(rename = "dataarea", default)]
+    pub dataareas: Vec<DataAreaXml>,
+    #[serde(rename = "diskarea", default)]
+    pub diskareas: Vec<DiskAreaXml>,
+}
+
+#[derive(Deserialize)]
+pub struct DataAreaXml {
+    #[serde(rename = "rom", default)]
+    pub roms: Vec<SoftwareRomXml>,
+}
+
+#[derive(Deserialize)]
+pub struct DiskAreaXml {
+    #[serde(rename = "disk", default)]
+    pub disks: Vec<DiskXml>,
+}
+
+#[derive(Deserialize)]
+pub struct SoftwareRomXml {
+    #[serde(rename = "@name")]
+    pub name: Option<String>,
+    #[serde(rename = "@size")]
+    pub size: Option<i64>,
+    #[serde(rename = "@crc")]
+    pub crc: Option<String>,
     #[serde(rename = "@sha1")]
     pub sha1: Option<String>,
     #[serde(rename = "@status")]
@@ -1,14 +1,138 @@
-use indicatif::{ProgressBar, ProgressStyle};
+#[cfg(feature = "server")]
+use async_graphql::SimpleObject;
+use indicatif::{ProgressBar, ProgressBarIter, ProgressStyle};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const PROGRESS_CHARS: &str = "#-";
+const MAX_RECORDED_LINES: usize = 1000;
 const NONE_TEMPLATE: &str = "{spinner} {wide_msg}";
 const COUNT_TEMPLATE: &str =
     "{spinner} {wide_msg} {pos}/{len} {per_sec} {elapsed_precise} ({eta_precise}) [{bar:80}]";
 const BYTES_TEMPLATE: &str =
     "{spinner} {wide_msg} {bytes}/{total_bytes} {bytes_per_sec} {elapsed_precise} ({eta_precise}) [{bar:80}]";
 
-pub fn get_progress_bar(length: u64, style: ProgressStyle) -> ProgressBar {
-    ProgressBar::new(length).with_style(style)
+#[cfg(feature = "server")]
+#[derive(Clone, Default, SimpleObject)]
+pub struct ProgressState {
+    pub message: String,
+    pub position: u64,
+    pub length: u64,
+    pub lines: Vec<String>,
+}
+
+// only the most recent lines are kept, the offsets still count the dropped ones
+#[derive(Default)]
+struct RecordedLines {
+    lines: VecDeque<String>,
+    dropped: usize,
+}
+
+// progress is rendered by indicatif, and optionally recorded so it can be streamed by the server
+#[derive(Clone)]
+pub struct Progress {
+    progress_bar: ProgressBar,
+    lines: Option<Arc<Mutex<RecordedLines>>>,
+}
+
+impl Progress {
+    pub fn hidden() -> Progress {
+        Progress {
+            progress_bar: ProgressBar::hidden(),
+            lines: None,
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn recorded() -> Progress {
+        Progress {
+            progress_bar: ProgressBar::hidden(),
+            lines: Some(Arc::new(Mutex::new(RecordedLines::default()))),
+        }
+    }
+
+    pub fn println<I: AsRef<str>>(&self, msg: I) {
+        if let Some(lines) = &self.lines {
+            let mut lines = lines.lock().unwrap();
+            if lines.lines.len() == MAX_RECORDED_LINES {
+                lines.lines.pop_front();
+                lines.dropped += 1;
+            }
+            lines.lines.push_back(msg.as_ref().to_owned());
+        }
+        self.progress_bar.println(msg);
+    }
+
+    pub fn set_message(&self, msg: impl Into<Cow<'static, str>>) {
+        self.progress_bar.set_message(msg);
+    }
+
+    pub fn set_style(&self, style: ProgressStyle) {
+        self.progress_bar.set_style(style);
+    }
+
+    pub fn enable_steady_tick(&self, interval: Duration) {
+        self.progress_bar.enable_steady_tick(interval);
+    }
+
+    pub fn disable_steady_tick(&self) {
+        self.progress_bar.disable_steady_tick();
+    }
+
+    pub fn set_length(&self, length: u64) {
+        self.progress_bar.set_length(length);
+    }
+
+    pub fn inc(&self, delta: u64) {
+        self.progress_bar.inc(delta);
+    }
+
+    pub fn reset(&self) {
+        self.progress_bar.reset();
+    }
+
+    pub fn wrap_write<W: Write>(&self, write: W) -> ProgressBarIter<W> {
+        self.progress_bar.wrap_write(write)
+    }
+
+    // lines are returned from the given offset to only send new ones, along with the next offset
+    #[cfg(feature = "server")]
+    pub fn get_state(&self, offset: usize) -> (ProgressState, usize) {
+        let (lines, next_offset) = match &self.lines {
+            Some(lines) => {
+                let lines = lines.lock().unwrap();
+                (
+                    lines
+                        .lines
+                        .iter()
+                        .skip(offset.saturating_sub(lines.dropped))
+                        .cloned()
+                        .collect(),
+                    lines.dropped + lines.lines.len(),
+                )
+            }
+            None => (Vec::new(), offset),
+        };
+        (
+            ProgressState {
+                message: self.progress_bar.message(),
+                position: self.progress_bar.position(),
+                length: self.progress_bar.length().unwrap_or_default(),
+                lines,
+            },
+            next_offset,
+        )
+    }
+}
+
+pub fn get_progress_bar(length: u64, style: ProgressStyle) -> Progress {
+    Progress {
+        progress_bar: ProgressBar::new(length).with_style(style),
+        lines: None,
+    }
 }
 
 pub fn get_none_progress_style() -> ProgressStyle {
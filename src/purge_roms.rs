@@ -1,10 +1,10 @@
 use super::database::*;
+use super::progress::*;
 use super::prompt::*;
 use super::util::*;
 use super::SimpleResult;
 use async_std::path::Path;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use sqlx::sqlite::SqliteConnection;
 
 pub fn subcommand() -> Command {
@@ -47,7 +47,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     if matches.get_flag("MISSING") {
         purge_missing_romfiles(connection, progress_bar).await?;
@@ -70,7 +70,7 @@ pub async fn main(
 
 async fn purge_missing_romfiles(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     progress_bar.println("Processing missing ROM files");
 
@@ -91,13 +91,20 @@ async fn purge_missing_romfiles(
         ));
     }
 
+    // cached hashes of files that are gone would only be inherited by unrelated files
+    for path in find_cached_hashes_paths(connection).await {
+        if !Path::new(&path).is_file().await {
+            delete_cached_hashes_by_path(connection, &path).await;
+        }
+    }
+
     Ok(())
 }
 
 async fn purge_trashed_romfiles(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     progress_bar.println("Processing trashed ROM files");
 
@@ -116,7 +123,8 @@ async fn purge_trashed_romfiles(
             for romfile in &romfiles {
                 let romfile_path = Path::new(&romfile.path);
                 if romfile_path.is_file().await {
-                    remove_file(progress_bar, &romfile_path, false).await?;
+                    remove_cached_file(&mut transaction, progress_bar, &romfile_path, false)
+                        .await?;
                     delete_romfile_by_id(&mut transaction, romfile.id).await;
                     count += 1;
                 }
@@ -135,7 +143,7 @@ async fn purge_trashed_romfiles(
 
 async fn purge_orphan_romfiles(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     progress_bar.println("Processing orphan ROM files");
     delete_romfiles_without_rom(connection).await;
@@ -15,7 +15,7 @@ async fn test() {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -55,6 +55,11 @@ async fn test() {
     let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
         .await
         .unwrap();
+    let trashed_romfile = find_romfiles(&mut connection).await.remove(0);
+    assert_eq!(
+        find_cached_hashes_paths(&mut connection).await,
+        vec![trashed_romfile.path]
+    );
 
     // when
     let matches = subcommand().get_matches_from(&["purge-roms", "-y"]);
@@ -66,6 +71,7 @@ async fn test() {
     // then
     let romfiles = find_romfiles(&mut connection).await;
     assert!(romfiles.is_empty());
+    assert!(find_cached_hashes_paths(&mut connection).await.is_empty());
     assert!(&system_directory
         .join("Trash")
         .read_dir()
@@ -1,11 +1,11 @@
 use super::database::*;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::util::*;
 use super::SimpleResult;
 use async_std::path::Path;
 use clap::Command;
-use indicatif::ProgressBar;
 use sqlx::sqlite::SqliteConnection;
 use std::time::Duration;
 
@@ -13,10 +13,7 @@ pub fn subcommand() -> Command {
     Command::new("purge-systems").about("Purge systems")
 }
 
-pub async fn main(
-    connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
-) -> SimpleResult<()> {
+pub async fn main(connection: &mut SqliteConnection, progress_bar: &Progress) -> SimpleResult<()> {
     let systems = prompt_for_systems(connection, None, false, false).await?;
     progress_bar.enable_steady_tick(Duration::from_millis(100));
     for system in systems {
@@ -28,7 +25,7 @@ pub async fn main(
 
 async fn purge_system(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) -> SimpleResult<()> {
     progress_bar.println(format!("Processing \"{}\"", system.name));
@@ -38,7 +35,7 @@ async fn purge_system(
 
     for romfile in romfiles {
         let new_path = trash_directory.join(Path::new(&romfile.path).file_name().unwrap());
-        rename_file(progress_bar, &romfile.path, &new_path, false).await?;
+        rename_cached_file(connection, progress_bar, &romfile.path, &new_path, false).await?;
         update_romfile(
             connection,
             romfile.id,
@@ -46,6 +43,7 @@ async fn purge_system(
             romfile.size as u64,
         )
         .await;
+        journal_rename(connection, &romfile.path, &new_path, Some(romfile.id)).await;
     }
 
     progress_bar.println("Deleting system");
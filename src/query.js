@@ -1,11 +1,10 @@
 import { GraphQLClient, gql } from "graphql-request";
-import { reject } from "lodash-es";
 import { get } from "svelte/store";
 
 import {
   completeFilter,
-  filteredGames,
   games,
+  gamesCount,
   gamesPage,
   gamesTotalPages,
   ignoredFilter,
@@ -18,12 +17,12 @@ import {
   roms,
   romsPage,
   romsTotalPages,
+  systemId,
   systems,
   systemsPage,
   systemsTotalPages,
   totalActualSize,
   totalOriginalSize,
-  unfilteredGames,
   unfilteredRoms,
   unfilteredSystems,
 } from "./store.js";
@@ -64,47 +63,60 @@ export async function updateSystems() {
 export async function getGamesBySystemId(systemId) {
   const query = gql`
         {
-            games(systemId: ${systemId}) {
-                id
-                name
-                description
-                complete
-                sorting
-            }
+            gamesCount(systemId: ${systemId})
         }
     `;
 
   const data = await graphQLClient.request(query);
-  unfilteredGames.set(data.games);
+  gamesCount.set(data.gamesCount);
   await updateGames();
 }
 
-function filterGames(games) {
-  if (!get(completeFilter)) {
-    games = reject(games, (game) => game.complete);
-  }
-  if (!get(incompleteFilter)) {
-    games = reject(games, (game) => !game.complete);
-  }
-  if (!get(ignoredFilter)) {
-    games = reject(games, (game) => game.sorting === 2);
+// filters are applied by the server, null means no filter
+function getGameFilter() {
+  let complete = null;
+  if (get(completeFilter) && !get(incompleteFilter)) {
+    complete = true;
   }
-  if (get(oneRegionFilter)) {
-    games = reject(games, (game) => game.sorting !== 1);
+  if (!get(completeFilter) && get(incompleteFilter)) {
+    complete = false;
   }
-  if (get(nameFilter).length) {
-    games = reject(
-      games,
-      (game) => !game.name.normalize("NFC").toLowerCase().includes(get(nameFilter).normalize("NFC").toLocaleLowerCase())
-    );
-  }
-  return games;
+  return {
+    name: get(nameFilter).length ? get(nameFilter).normalize("NFC") : null,
+    complete,
+    ignored: get(ignoredFilter) ? null : false,
+    oneRegion: get(oneRegionFilter) ? true : null,
+  };
 }
 
 export async function updateGames() {
-  filteredGames.set(filterGames(get(unfilteredGames)));
-  gamesTotalPages.set(Math.max(Math.ceil(get(filteredGames).length / get(pageSize)), 1));
-  games.set(paginate(get(filteredGames), get(gamesPage), get(pageSize)));
+  if (get(systemId) === -1 || (!get(completeFilter) && !get(incompleteFilter))) {
+    gamesTotalPages.set(1);
+    games.set([]);
+    return;
+  }
+
+  const query = gql`
+    query Games($systemId: Int!, $filter: GameFilter, $offset: Int, $limit: Int) {
+      games(systemId: $systemId, filter: $filter, offset: $offset, limit: $limit) {
+        id
+        name
+        description
+        complete
+        sorting
+      }
+      gamesCount(systemId: $systemId, filter: $filter)
+    }
+  `;
+
+  const data = await graphQLClient.request(query, {
+    systemId: get(systemId),
+    filter: getGameFilter(),
+    offset: get(pageSize) * (get(gamesPage) - 1),
+    limit: get(pageSize),
+  });
+  gamesTotalPages.set(Math.max(Math.ceil(data.gamesCount / get(pageSize)), 1));
+  games.set(data.games);
 }
 
 export async function getRomsByGameIdAndSystemId(gameId, systemId) {
@@ -9,7 +9,6 @@ use super::SimpleResult;
 use async_std::path::{Path, PathBuf};
 use clap::builder::PossibleValuesParser;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use num_traits::FromPrimitive;
 use sqlx::sqlite::SqliteConnection;
 use std::time::Duration;
@@ -49,7 +48,7 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let systems = prompt_for_systems(connection, None, true, matches.get_flag("ALL")).await?;
 
@@ -77,7 +76,7 @@ pub async fn main(
 
 async fn rebuild_system(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     merging: Merging,
 ) -> SimpleResult<()> {
@@ -94,7 +93,8 @@ async fn rebuild_system(
     if (system.merging == Merging::Split as i64 || system.merging == Merging::NonMerged as i64)
         && (merging == Merging::NonMerged || merging == Merging::FullNonMerged)
     {
-        let compression_level = get_integer(connection, "ZIP_COMPRESSION_LEVEL").await;
+        let compression_level =
+            get_integer(connection, "ZIP_COMPRESSION_LEVEL", Some(system.id)).await;
         for game in games {
             expand_game(
                 connection,
@@ -123,7 +123,7 @@ async fn rebuild_system(
 
 async fn expand_game(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     game: &Game,
     merging: Merging,
@@ -149,7 +149,11 @@ async fn expand_game(
         Merging::FullNonMerged => find_roms_by_game_id_parents(&mut transaction, game.id).await,
         _ => bail!("Not possible"),
     };
-    for rom in roms.iter().filter(|rom| rom.romfile_id.is_none()) {
+    // disks don't go in archives
+    for rom in roms
+        .iter()
+        .filter(|rom| rom.romfile_id.is_none() && rom.crc.is_some())
+    {
         let mut source_rom: Option<Rom> = None;
         if let Some(parent_id) = rom.parent_id {
             let parent_rom = find_rom_by_id(&mut transaction, parent_id).await;
@@ -190,7 +194,7 @@ async fn expand_game(
             &mut transaction,
             romfile.id,
             &romfile.path,
-            archive_romfile_path.metadata().await.unwrap().len(),
+            get_file_size(&archive_romfile_path).await,
         )
         .await;
     }
@@ -200,7 +204,7 @@ async fn expand_game(
 
 async fn trim_game(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     game: &Game,
     merging: Merging,
@@ -222,7 +226,11 @@ async fn trim_game(
         }
         _ => bail!("Not possible"),
     };
-    for rom in roms.iter().filter(|rom| rom.romfile_id.is_some()) {
+    // disks are never rebuilt
+    for rom in roms
+        .iter()
+        .filter(|rom| rom.romfile_id.is_some() && rom.crc.is_some())
+    {
         delete_rom(&mut transaction, progress_bar, rom, &archive_romfile).await?;
     }
     if let Some(romfile) = archive_romfile {
@@ -230,7 +238,7 @@ async fn trim_game(
             &mut transaction,
             romfile.id,
             &romfile.path,
-            archive_romfile_path.metadata().await.unwrap().len(),
+            get_file_size(&archive_romfile_path).await,
         )
         .await;
     }
@@ -240,7 +248,7 @@ async fn trim_game(
 
 async fn add_rom(
     transaction: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     rom: &Rom,
     source_rom: &Rom,
     archive_romfile: &Option<Romfile>,
@@ -290,7 +298,8 @@ async fn add_rom(
             game_directory,
         )?;
         if source_rom.name != rom.name {
-            rename_file(
+            rename_cached_file(
+                transaction,
                 progress_bar,
                 &game_directory.join(&source_rom.name),
                 &romfile_path,
@@ -301,7 +310,7 @@ async fn add_rom(
         let romfile_id = create_romfile(
             transaction,
             romfile_path.as_os_str().to_str().unwrap(),
-            romfile_path.metadata().await.unwrap().len(),
+            get_file_size(&romfile_path).await,
         )
         .await;
         update_rom_romfile(transaction, rom.id, Some(romfile_id)).await;
@@ -312,7 +321,7 @@ async fn add_rom(
         let romfile_id = create_romfile(
             transaction,
             romfile_path.as_os_str().to_str().unwrap(),
-            romfile_path.metadata().await.unwrap().len(),
+            get_file_size(&romfile_path).await,
         )
         .await;
         update_rom_romfile(transaction, rom.id, Some(romfile_id)).await;
@@ -322,7 +331,7 @@ async fn add_rom(
 
 async fn delete_rom(
     transaction: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     rom: &Rom,
     archive_romfile: &Option<Romfile>,
 ) -> SimpleResult<()> {
@@ -330,7 +339,7 @@ async fn delete_rom(
         sevenzip::remove_files_from_archive(progress_bar, &archive_romfile.path, &[&rom.name])?;
     } else {
         let romfile = find_romfile_by_id(transaction, rom.romfile_id.unwrap()).await;
-        remove_file(progress_bar, &romfile.path, false).await?;
+        remove_cached_file(transaction, progress_bar, &romfile.path, false).await?;
     }
     update_rom_romfile(transaction, rom.id, None).await;
     Ok(())
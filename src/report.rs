@@ -0,0 +1,290 @@
+use super::config::is_dry_run;
+use super::database::*;
+use super::model::*;
+use super::progress::*;
+use super::prompt::*;
+use super::SimpleResult;
+use async_std::path::{Path, PathBuf};
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use num_traits::FromPrimitive;
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnection;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::str::FromStr;
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
+
+#[derive(Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+pub struct SystemReport {
+    pub name: String,
+    pub description: String,
+    pub version: String,
+    pub complete: bool,
+    pub games: Vec<GameReport>,
+}
+
+#[derive(Serialize)]
+pub struct GameReport {
+    pub name: String,
+    pub status: String,
+    pub sorting: String,
+    pub roms: Vec<RomReport>,
+}
+
+#[derive(Serialize)]
+pub struct RomReport {
+    pub name: String,
+    pub status: String,
+    pub size: i64,
+    pub crc: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub romfile: Option<String>,
+    pub format: Option<String>,
+    pub romfile_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ReportRow<'a> {
+    system: &'a str,
+    system_complete: bool,
+    game: &'a str,
+    game_status: &'a str,
+    sorting: &'a str,
+    rom: Option<&'a str>,
+    rom_status: Option<&'a str>,
+    size: Option<i64>,
+    crc: Option<&'a str>,
+    md5: Option<&'a str>,
+    sha1: Option<&'a str>,
+    romfile: Option<&'a str>,
+    format: Option<&'a str>,
+    romfile_size: Option<i64>,
+}
+
+pub fn subcommand() -> Command {
+    Command::new("report")
+        .about("Report the collection status in CSV or JSON")
+        .arg(
+            Arg::new("ALL")
+                .short('a')
+                .long("all")
+                .help("Report all systems")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("FORMAT")
+                .short('f')
+                .long("format")
+                .help("Set the output format")
+                .required(false)
+                .num_args(1)
+                .value_parser(PossibleValuesParser::new(ReportFormat::VARIANTS))
+                .default_value("csv"),
+        )
+        .arg(
+            Arg::new("OUTPUT")
+                .short('o')
+                .long("output")
+                .help("Write the report to a file instead of the standard output")
+                .required(false)
+                .num_args(1)
+                .value_parser(value_parser!(PathBuf)),
+        )
+}
+
+pub async fn main(
+    connection: &mut SqliteConnection,
+    matches: &ArgMatches,
+    progress_bar: &Progress,
+) -> SimpleResult<()> {
+    let systems = prompt_for_systems(connection, None, false, matches.get_flag("ALL")).await?;
+    let report_format =
+        ReportFormat::from_str(matches.get_one::<String>("FORMAT").unwrap()).unwrap();
+
+    let mut system_reports: Vec<SystemReport> = Vec::new();
+    for system in systems {
+        system_reports.push(build_system_report(connection, &system).await);
+    }
+
+    match matches.get_one::<PathBuf>("OUTPUT") {
+        Some(output) if is_dry_run() => {
+            progress_bar.println(format!("Would create {:?}", output.as_os_str()));
+        }
+        Some(output) => {
+            let file = try_with!(File::create(output), "Failed to create {:?}", output);
+            write_report(file, &system_reports, &report_format)?;
+        }
+        None => write_report(io::stdout(), &system_reports, &report_format)?,
+    }
+
+    Ok(())
+}
+
+async fn build_system_report(connection: &mut SqliteConnection, system: &System) -> SystemReport {
+    let games = find_games_by_system_id(connection, system.id).await;
+    let romfiles: HashMap<i64, Romfile> = find_romfiles_by_system_id(connection, system.id)
+        .await
+        .into_iter()
+        .map(|romfile| (romfile.id, romfile))
+        .collect();
+    let mut roms = find_roms_with_romfile_by_system_id(connection, system.id).await;
+    roms.append(
+        &mut find_roms_without_romfile_by_game_ids(
+            connection,
+            games
+                .iter()
+                .filter(|game| !game.complete)
+                .map(|game| game.id)
+                .collect::<Vec<i64>>()
+                .as_slice(),
+        )
+        .await,
+    );
+    let mut roms_by_game_id: HashMap<i64, Vec<Rom>> = HashMap::new();
+    roms.into_iter().for_each(|rom| {
+        let group = roms_by_game_id.entry(rom.game_id).or_default();
+        group.push(rom);
+    });
+
+    let game_reports = games
+        .into_iter()
+        .map(|game| {
+            let mut roms = roms_by_game_id.remove(&game.id).unwrap_or_default();
+            roms.sort_by(|a, b| a.name.cmp(&b.name));
+            let status = if game.complete {
+                "complete"
+            } else if roms.iter().all(|rom| rom.romfile_id.is_none()) {
+                "missing"
+            } else {
+                "incomplete"
+            };
+            let sorting = match Sorting::from_i64(game.sorting).unwrap() {
+                Sorting::AllRegions => "all_regions",
+                Sorting::OneRegion => "one_region",
+                Sorting::Ignored => "ignored",
+            };
+            GameReport {
+                name: game.name,
+                status: String::from(status),
+                sorting: String::from(sorting),
+                roms: roms
+                    .into_iter()
+                    .map(|rom| {
+                        let romfile = rom
+                            .romfile_id
+                            .map(|romfile_id| romfiles.get(&romfile_id).unwrap());
+                        RomReport {
+                            name: rom.name,
+                            status: String::from(match romfile {
+                                Some(_) => "owned",
+                                None => "missing",
+                            }),
+                            size: rom.size,
+                            crc: rom.crc,
+                            md5: rom.md5,
+                            sha1: rom.sha1,
+                            romfile: romfile.map(|romfile| romfile.path.clone()),
+                            format: romfile.map(|romfile| {
+                                Path::new(&romfile.path)
+                                    .extension()
+                                    .map(|extension| extension.to_str().unwrap().to_lowercase())
+                                    .unwrap_or_default()
+                            }),
+                            romfile_size: romfile.map(|romfile| romfile.size),
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    SystemReport {
+        name: system.name.clone(),
+        description: system.description.clone(),
+        version: system.version.clone(),
+        complete: system.complete,
+        games: game_reports,
+    }
+}
+
+fn write_report<W: io::Write>(
+    writer: W,
+    system_reports: &[SystemReport],
+    report_format: &ReportFormat,
+) -> SimpleResult<()> {
+    match report_format {
+        ReportFormat::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for system_report in system_reports {
+                for game_report in &system_report.games {
+                    let mut rows: Vec<ReportRow> = game_report
+                        .roms
+                        .iter()
+                        .map(|rom_report| ReportRow {
+                            system: &system_report.name,
+                            system_complete: system_report.complete,
+                            game: &game_report.name,
+                            game_status: &game_report.status,
+                            sorting: &game_report.sorting,
+                            rom: Some(&rom_report.name),
+                            rom_status: Some(&rom_report.status),
+                            size: Some(rom_report.size),
+                            crc: rom_report.crc.as_deref(),
+                            md5: rom_report.md5.as_deref(),
+                            sha1: rom_report.sha1.as_deref(),
+                            romfile: rom_report.romfile.as_deref(),
+                            format: rom_report.format.as_deref(),
+                            romfile_size: rom_report.romfile_size,
+                        })
+                        .collect();
+                    // games without any rom still get a row
+                    if rows.is_empty() {
+                        rows.push(ReportRow {
+                            system: &system_report.name,
+                            system_complete: system_report.complete,
+                            game: &game_report.name,
+                            game_status: &game_report.status,
+                            sorting: &game_report.sorting,
+                            rom: None,
+                            rom_status: None,
+                            size: None,
+                            crc: None,
+                            md5: None,
+                            sha1: None,
+                            romfile: None,
+                            format: None,
+                            romfile_size: None,
+                        });
+                    }
+                    for row in rows {
+                        try_with!(csv_writer.serialize(row), "Failed to write CSV row");
+                    }
+                }
+            }
+            try_with!(csv_writer.flush(), "Failed to write CSV report");
+        }
+        ReportFormat::Json => {
+            try_with!(
+                serde_json::to_writer_pretty(writer, system_reports),
+                "Failed to write JSON report"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_csv;
+#[cfg(test)]
+mod test_json;
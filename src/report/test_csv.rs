@@ -0,0 +1,83 @@
+use super::super::config::*;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use std::env;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    env::set_var(
+        "PATH",
+        format!(
+            "{}:{}",
+            test_directory.as_os_str().to_str().unwrap(),
+            env::var("PATH").unwrap()
+        ),
+    );
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).rom");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).rom"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+    let matches = import_roms::subcommand()
+        .get_matches_from(["import-roms", romfile_path.as_os_str().to_str().unwrap()]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_reports = vec![build_system_report(&mut connection, &system).await];
+
+    // when
+    let mut report: Vec<u8> = Vec::new();
+    write_report(&mut report, &system_reports, &ReportFormat::Csv).unwrap();
+
+    // then
+    let report = String::from_utf8(report).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert_eq!(
+        lines[0],
+        "system,system_complete,game,game_status,sorting,rom,rom_status,size,crc,md5,sha1,romfile,format,romfile_size"
+    );
+    assert!(lines.contains(
+        &"Test System,false,Test Game (Japan),missing,all_regions,Test Game (Japan).rom,missing,\
+        256,310212e8,d14b417004b9cc868286a0eafb257d2b,5fcce2e41101d4e0f22a9279e65839145fa61846,,,"
+    ));
+
+    let line = lines
+        .iter()
+        .find(|line| line.starts_with("Test System,false,\"Test Game (USA, Europe)\","))
+        .unwrap();
+    assert!(line.starts_with(
+        "Test System,false,\"Test Game (USA, Europe)\",complete,all_regions,\
+        \"Test Game (USA, Europe).rom\",owned,256,cc721e14,c1fe7a7ed317bd069b0cb7bdbc40be01,\
+        4b3e49f0f22c7ce5f1eb7c30a2bbd6fb4fdc6f3d,"
+    ));
+    assert!(line.ends_with("Test Game (USA, Europe).rom\",rom,256"));
+}
@@ -0,0 +1,129 @@
+use super::super::config::*;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use serde_json::Value;
+use std::env;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    env::set_var(
+        "PATH",
+        format!(
+            "{}:{}",
+            test_directory.as_os_str().to_str().unwrap(),
+            env::var("PATH").unwrap()
+        ),
+    );
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).rom");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).rom"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+    let cue_path = tmp_directory.join("Test Game (USA, Europe).cue");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe) (Multiple Tracks).cue"),
+        &cue_path,
+    )
+    .await
+    .unwrap();
+    let matches = import_roms::subcommand().get_matches_from([
+        "import-roms",
+        romfile_path.as_os_str().to_str().unwrap(),
+        cue_path.as_os_str().to_str().unwrap(),
+    ]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_reports = vec![build_system_report(&mut connection, &system).await];
+
+    // when
+    let mut report: Vec<u8> = Vec::new();
+    write_report(&mut report, &system_reports, &ReportFormat::Json).unwrap();
+
+    // then
+    let v: Value = serde_json::from_slice(&report).unwrap();
+    let systems = v.as_array().unwrap();
+    assert_eq!(systems.len(), 1);
+    assert_eq!(systems[0]["name"], "Test System");
+    assert_eq!(systems[0]["complete"], false);
+
+    let games = systems[0]["games"].as_array().unwrap();
+    assert_eq!(games.len(), 6);
+
+    let game = games
+        .iter()
+        .find(|game| game["name"] == "Test Game (USA, Europe)")
+        .unwrap();
+    assert_eq!(game["status"], "complete");
+    assert_eq!(game["sorting"], "all_regions");
+
+    let roms = game["roms"].as_array().unwrap();
+    assert_eq!(roms.len(), 1);
+    assert_eq!(roms[0]["name"], "Test Game (USA, Europe).rom");
+    assert_eq!(roms[0]["status"], "owned");
+    assert_eq!(roms[0]["size"], 256);
+    assert_eq!(roms[0]["crc"], "cc721e14");
+    assert_eq!(roms[0]["md5"], "c1fe7a7ed317bd069b0cb7bdbc40be01");
+    assert_eq!(roms[0]["sha1"], "4b3e49f0f22c7ce5f1eb7c30a2bbd6fb4fdc6f3d");
+    assert_eq!(roms[0]["format"], "rom");
+    assert_eq!(roms[0]["romfile_size"], 256);
+    assert!(roms[0]["romfile"]
+        .as_str()
+        .unwrap()
+        .ends_with("Test Game (USA, Europe).rom"));
+
+    let game = games
+        .iter()
+        .find(|game| game["name"] == "Test Game (Japan)")
+        .unwrap();
+    assert_eq!(game["status"], "missing");
+
+    let roms = game["roms"].as_array().unwrap();
+    assert_eq!(roms.len(), 1);
+    assert_eq!(roms[0]["status"], "missing");
+    assert!(roms[0]["romfile"].is_null());
+
+    let game = games
+        .iter()
+        .find(|game| game["name"] == "Test Game (USA, Europe) (CUE BIN)")
+        .unwrap();
+    assert_eq!(game["status"], "incomplete");
+
+    let roms = game["roms"].as_array().unwrap();
+    assert_eq!(roms.len(), 3);
+    assert_eq!(roms[0]["name"], "Test Game (USA, Europe) (Track 01).bin");
+    assert_eq!(roms[0]["status"], "missing");
+    assert_eq!(roms[1]["name"], "Test Game (USA, Europe) (Track 02).bin");
+    assert_eq!(roms[1]["status"], "missing");
+    assert_eq!(roms[2]["name"], "Test Game (USA, Europe).cue");
+    assert_eq!(roms[2]["status"], "owned");
+    assert_eq!(roms[2]["format"], "cue");
+}
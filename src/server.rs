@@ -1,15 +1,32 @@
+use super::check_roms;
+use super::checksum::Hashes;
+use super::config::*;
+use super::convert_roms;
 use super::database::*;
+use super::lookup;
 use super::model::*;
+use super::progress::*;
+use super::purge_roms;
+use super::sort_roms;
+use super::util::get_canonicalized_path;
 use async_ctrlc::CtrlC;
 use async_graphql::dataloader::{DataLoader, Loader};
 use async_graphql::{
-    ComplexObject, Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema,
+    ComplexObject, Context, Enum, Error, Guard, Object, Result, Schema, SimpleObject, Subscription,
 };
-use async_std::path::Path;
+use async_std::net::TcpListener;
+use async_std::path::{Path, PathBuf};
 use async_std::prelude::FutureExt;
+use async_std::sync::{Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard};
+use async_std::task;
 use async_trait::async_trait;
 use clap::{Arg, ArgMatches, Command};
-use futures::stream::TryStreamExt;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use futures::{Future, Stream};
+use futures_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use futures_rustls::TlsAcceptor;
+use http_types::auth::{AuthenticationScheme, Authorization, BasicAuth};
+use http_types::headers::WWW_AUTHENTICATE;
 use http_types::mime::BYTE_STREAM;
 use http_types::{Mime, StatusCode};
 use itertools::Itertools;
@@ -17,12 +34,86 @@ use lazy_static::lazy_static;
 use num_traits::FromPrimitive;
 use once_cell::sync::OnceCell;
 use rust_embed::RustEmbed;
+use rustls_pemfile::Item;
 use simple_error::SimpleResult;
-use sqlx::sqlite::SqlitePool;
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use sqlx::Sqlite;
 use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use subtle::ConstantTimeEq;
 
 lazy_static! {
     static ref POOL: OnceCell<SqlitePool> = OnceCell::new();
+    static ref JOBS: Mutex<HashMap<i64, Job>> = Mutex::new(HashMap::new());
+    // jobs move files around, run them one at a time
+    static ref JOB_LOCK: AsyncMutex<()> = AsyncMutex::new(());
+}
+
+static JOB_ID: AtomicI64 = AtomicI64::new(0);
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_FINISHED_JOBS: usize = 100;
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Clone, SimpleObject)]
+struct Job {
+    id: i64,
+    name: String,
+    status: JobStatus,
+    error: Option<String>,
+    #[graphql(skip)]
+    progress: Progress,
+}
+
+impl Job {
+    fn is_finished(&self) -> bool {
+        self.status == JobStatus::Succeeded || self.status == JobStatus::Failed
+    }
+}
+
+#[derive(SimpleObject)]
+struct JobProgress {
+    id: i64,
+    status: JobStatus,
+    error: Option<String>,
+    progress: ProgressState,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Admin,
+    ReadOnly,
+}
+
+#[derive(Default)]
+struct Credentials {
+    username: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Default)]
+struct Auth {
+    admin: Credentials,
+    read_only: Credentials,
+    loopback: bool,
 }
 
 #[derive(RustEmbed)]
@@ -50,6 +141,24 @@ pub fn subcommand() -> Command {
                 .num_args(1)
                 .default_value("8000"),
         )
+        .arg(
+            Arg::new("TLS_CERT")
+                .long("tls-cert")
+                .help("Set the PEM certificate chain to serve over HTTPS")
+                .required(false)
+                .num_args(1)
+                .requires("TLS_KEY")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("TLS_KEY")
+                .long("tls-key")
+                .help("Set the PEM private key to serve over HTTPS")
+                .required(false)
+                .num_args(1)
+                .requires("TLS_CERT")
+                .value_parser(value_parser!(PathBuf)),
+        )
 }
 
 #[ComplexObject]
@@ -194,19 +303,410 @@ impl Loader<i64> for RomfileLoader {
     }
 }
 
+// missing or out of range limits fall back to the default and maximum page sizes
+fn get_page_size(limit: Option<i64>) -> i64 {
+    match limit {
+        Some(limit) if limit > 0 => limit.min(MAX_PAGE_SIZE),
+        _ => DEFAULT_PAGE_SIZE,
+    }
+}
+
+// settings take precedence over their OXYROMON_ prefixed environment variables
+async fn get_setting_or_env(connection: &mut SqliteConnection, key: &str) -> Option<String> {
+    match get_optional_string(connection, key, None).await {
+        Some(value) => Some(value),
+        None => env::var(format!("OXYROMON_{}", key))
+            .ok()
+            .filter(|value| !value.is_empty()),
+    }
+}
+
+// environment variables and older settings hold clear secrets, hash them once at startup
+async fn get_hashed_secret(
+    connection: &mut SqliteConnection,
+    key: &str,
+) -> SimpleResult<Option<String>> {
+    get_setting_or_env(connection, key)
+        .await
+        .map(|secret| match is_hashed_secret(&secret) {
+            true => Ok(secret),
+            false => hash_secret(&secret),
+        })
+        .transpose()
+}
+
+impl Credentials {
+    async fn from_settings(
+        connection: &mut SqliteConnection,
+        prefix: &str,
+    ) -> SimpleResult<Credentials> {
+        let credentials = Credentials {
+            username: get_setting_or_env(connection, &format!("{}_USERNAME", prefix)).await,
+            password: get_hashed_secret(connection, &format!("{}_PASSWORD", prefix)).await?,
+            token: get_hashed_secret(connection, &format!("{}_TOKEN", prefix)).await?,
+        };
+        if credentials.username.is_some() != credentials.password.is_some() {
+            bail!(
+                "Both {}_USERNAME and {}_PASSWORD are required for basic authentication",
+                prefix,
+                prefix
+            );
+        }
+        Ok(credentials)
+    }
+
+    fn is_set(&self) -> bool {
+        self.username.is_some() || self.token.is_some()
+    }
+
+    fn matches(&self, basic_auth: &Option<BasicAuth>, token: &Option<String>) -> bool {
+        let basic_matches = match (&self.username, &self.password, basic_auth) {
+            (Some(username), Some(password), Some(basic_auth)) => {
+                let username_matches: bool = basic_auth
+                    .username()
+                    .as_bytes()
+                    .ct_eq(username.as_bytes())
+                    .into();
+                username_matches & verify_secret(basic_auth.password(), password)
+            }
+            _ => false,
+        };
+        let token_matches = match (&self.token, token) {
+            (Some(hashed_token), Some(token)) => verify_secret(token, hashed_token),
+            _ => false,
+        };
+        basic_matches || token_matches
+    }
+}
+
+impl Auth {
+    async fn from_settings(connection: &mut SqliteConnection, address: &str) -> SimpleResult<Auth> {
+        Ok(Auth {
+            admin: Credentials::from_settings(connection, "SERVER").await?,
+            read_only: Credentials::from_settings(connection, "SERVER_READ_ONLY").await?,
+            loopback: address == "localhost"
+                || IpAddr::from_str(address).is_ok_and(|ip| ip.is_loopback()),
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.admin.is_set() || self.read_only.is_set()
+    }
+
+    fn get_role(&self, basic_auth: &Option<BasicAuth>, token: &Option<String>) -> Option<Role> {
+        if self.admin.matches(basic_auth, token) {
+            Some(Role::Admin)
+        } else if self.read_only.matches(basic_auth, token) {
+            Some(Role::ReadOnly)
+        } else {
+            None
+        }
+    }
+}
+
+// without credentials, everyone is an admin on loopback and read-only elsewhere
+#[async_trait]
+impl tide::Middleware<()> for Auth {
+    async fn handle(&self, mut req: tide::Request<()>, next: tide::Next<'_, ()>) -> tide::Result {
+        if !self.is_enabled() {
+            req.set_ext(match self.loopback {
+                true => Role::Admin,
+                false => Role::ReadOnly,
+            });
+            return Ok(next.run(req).await);
+        }
+        let basic_auth = BasicAuth::from_headers(&req).ok().flatten();
+        let token = Authorization::from_headers(&req)
+            .ok()
+            .flatten()
+            .filter(|authorization| authorization.scheme() == AuthenticationScheme::Bearer)
+            .map(|authorization| authorization.credentials().to_owned());
+        match self.get_role(&basic_auth, &token) {
+            Some(role) => {
+                req.set_ext(role);
+                Ok(next.run(req).await)
+            }
+            None => {
+                let mut res = tide::Response::new(StatusCode::Unauthorized);
+                res.insert_header(WWW_AUTHENTICATE, "Basic realm=\"oxyromon\"");
+                Ok(res)
+            }
+        }
+    }
+}
+
+struct AdminGuard;
+
+#[async_trait]
+impl Guard for AdminGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        match ctx.data_opt::<Role>() {
+            Some(Role::Admin) => Ok(()),
+            _ => Err(Error::new("Read-only access")),
+        }
+    }
+}
+
+fn get_tls_acceptor(cert_path: &PathBuf, key_path: &PathBuf) -> SimpleResult<TlsAcceptor> {
+    let cert_file = try_with!(File::open(cert_path), "Failed to open certificate");
+    let certs = try_with!(
+        rustls_pemfile::certs(&mut BufReader::new(cert_file)),
+        "Failed to read certificate"
+    );
+    let key_file = try_with!(File::open(key_path), "Failed to open private key");
+    let key = try_with!(
+        rustls_pemfile::read_all(&mut BufReader::new(key_file)),
+        "Failed to read private key"
+    )
+    .into_iter()
+    .find_map(|item| match item {
+        Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(key),
+        _ => None,
+    });
+    let key = match key {
+        Some(key) => key,
+        None => bail!("No private key found in {:?}", key_path),
+    };
+    let config = try_with!(
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                certs.into_iter().map(Certificate).collect(),
+                PrivateKey(key)
+            ),
+        "Failed to configure TLS"
+    );
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+// same as tide's TCP listener, with a TLS handshake before handing the stream to async-h1
+async fn listen_tls(app: tide::Server<()>, address: String, acceptor: TlsAcceptor) {
+    let listener = TcpListener::bind(address)
+        .await
+        .expect("Failed to run server");
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let app = app.clone();
+        let acceptor = acceptor.clone();
+        task::spawn(async move {
+            let local_addr = stream.local_addr().ok();
+            let peer_addr = stream.peer_addr().ok();
+            if let Ok(stream) = acceptor.accept(stream).await {
+                // async-h1 needs a cloneable stream to read and write concurrently
+                let stream = async_dup::Arc::new(async_dup::Mutex::new(stream));
+                async_h1::accept(stream, |mut req| async {
+                    req.set_local_addr(local_addr);
+                    req.set_peer_addr(peer_addr);
+                    app.respond(req).await
+                })
+                .await
+                .ok();
+            }
+        });
+    }
+}
+
+fn set_job_status(id: i64, status: JobStatus, error: Option<String>) {
+    let mut jobs = JOBS.lock().unwrap();
+    let job = jobs.get_mut(&id).unwrap();
+    job.status = status;
+    job.error = error;
+    evict_finished_jobs(&mut jobs);
+}
+
+// only the most recent finished jobs are kept, along with their progress
+fn evict_finished_jobs(jobs: &mut HashMap<i64, Job>) {
+    let finished_ids: Vec<i64> = jobs
+        .values()
+        .filter(|job| job.is_finished())
+        .map(|job| job.id)
+        .sorted()
+        .collect();
+    if finished_ids.len() > MAX_FINISHED_JOBS {
+        for id in &finished_ids[..finished_ids.len() - MAX_FINISHED_JOBS] {
+            jobs.remove(id);
+        }
+    }
+}
+
+// the subcommands are not Send, build and run them on a blocking thread
+fn spawn_job<F, Fut>(name: String, run: F) -> Job
+where
+    F: FnOnce(PoolConnection<Sqlite>, Progress) -> Fut + Send + 'static,
+    Fut: Future<Output = SimpleResult<()>>,
+{
+    let id = JOB_ID.fetch_add(1, Ordering::Relaxed) + 1;
+    let job = Job {
+        id,
+        name,
+        status: JobStatus::Queued,
+        error: None,
+        progress: Progress::recorded(),
+    };
+    JOBS.lock().unwrap().insert(id, job.clone());
+    let progress = job.progress.clone();
+    task::spawn_blocking(move || {
+        task::block_on(async move {
+            let _guard = JOB_LOCK.lock().await;
+            set_job_status(id, JobStatus::Running, None);
+            // every job is undone on its own
+            let mut connection = POOL.get().unwrap().acquire().await.unwrap();
+            create_run(&mut connection).await;
+            match run(connection, progress).await {
+                Ok(()) => set_job_status(id, JobStatus::Succeeded, None),
+                Err(error) => set_job_status(id, JobStatus::Failed, Some(error.to_string())),
+            }
+        })
+    });
+    job
+}
+
+// jobs read the settings as they go, only change them in between
+fn lock_settings() -> Result<AsyncMutexGuard<'static, ()>> {
+    JOB_LOCK
+        .try_lock()
+        .ok_or_else(|| Error::new("A job is running, try again once it's done"))
+}
+
+// only regular files in the ROM and temporary directories can be looked up
+async fn get_lookup_path(connection: &mut SqliteConnection, path: &str) -> Result<PathBuf> {
+    let error = || {
+        Error::new(format!(
+            "\"{}\" is not a file in the ROM or temporary directory",
+            path
+        ))
+    };
+    let path = get_canonicalized_path(&PathBuf::from(path))
+        .await
+        .map_err(|_| error())?;
+    let rom_directory = get_canonicalized_path(get_rom_directory(connection).await).await?;
+    let tmp_directory = get_canonicalized_path(get_tmp_directory(connection).await).await?;
+    let metadata = path.metadata().await.map_err(|_| error())?;
+    if !metadata.is_file()
+        || !(path.starts_with(&rom_directory) || path.starts_with(&tmp_directory))
+    {
+        return Err(error());
+    }
+    Ok(path)
+}
+
+async fn find_system(system_id: i64) -> Result<System> {
+    find_systems(&mut POOL.get().unwrap().acquire().await.unwrap())
+        .await
+        .into_iter()
+        .find(|system| system.id == system_id)
+        .ok_or_else(|| Error::new(format!("No system with id {}", system_id)))
+}
+
 struct QueryRoot;
 
 #[Object]
 impl QueryRoot {
+    async fn jobs(&self) -> Result<Vec<Job>> {
+        Ok(JOBS
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .sorted_by_key(|job| job.id)
+            .collect())
+    }
+
+    async fn job(&self, id: i64) -> Result<Option<Job>> {
+        Ok(JOBS.lock().unwrap().get(&id).cloned())
+    }
+
     async fn systems(&self) -> Result<Vec<System>> {
         Ok(find_systems(&mut POOL.get().unwrap().acquire().await.unwrap()).await)
     }
 
-    async fn games(&self, system_id: i64) -> Result<Vec<Game>> {
-        Ok(
-            find_games_by_system_id(&mut POOL.get().unwrap().acquire().await.unwrap(), system_id)
-                .await,
+    async fn games(
+        &self,
+        system_id: i64,
+        filter: Option<GameFilter>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<Game>> {
+        Ok(find_games_by_system_id_and_filter(
+            &mut POOL.get().unwrap().acquire().await.unwrap(),
+            system_id,
+            &filter.unwrap_or_default(),
+            offset.unwrap_or(0).max(0),
+            get_page_size(limit),
+        )
+        .await)
+    }
+
+    async fn games_count(&self, system_id: i64, filter: Option<GameFilter>) -> Result<i64> {
+        Ok(count_games_by_system_id_and_filter(
+            &mut POOL.get().unwrap().acquire().await.unwrap(),
+            system_id,
+            &filter.unwrap_or_default(),
         )
+        .await)
+    }
+
+    // search all systems for games matching a name or containing a ROM with the given hash
+    async fn search_games(
+        &self,
+        name: Option<String>,
+        hash: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<Game>> {
+        if name.is_none() && hash.is_none() {
+            return Err(Error::new("Either a name or a hash is required"));
+        }
+        Ok(find_games_by_name_or_hash(
+            &mut POOL.get().unwrap().acquire().await.unwrap(),
+            name.as_deref(),
+            hash.as_deref(),
+            offset.unwrap_or(0).max(0),
+            get_page_size(limit),
+        )
+        .await)
+    }
+
+    // identify a file on the server or raw hashes, owned ROMs have a romfile
+    #[graphql(guard = "AdminGuard")]
+    async fn lookup(
+        &self,
+        path: Option<String>,
+        size: Option<i64>,
+        crc: Option<String>,
+        md5: Option<String>,
+        sha1: Option<String>,
+    ) -> Result<Vec<Rom>> {
+        let mut connection = POOL.get().unwrap().acquire().await.unwrap();
+        let matches = match path {
+            Some(path) => {
+                let path = get_lookup_path(&mut connection, &path).await?;
+                let entries =
+                    lookup::lookup_file(&mut connection, &Progress::hidden(), &path, 1, 1).await?;
+                entries
+                    .into_iter()
+                    .flat_map(|(_, matches)| matches)
+                    .collect()
+            }
+            None => {
+                if crc.is_none() && md5.is_none() && sha1.is_none() {
+                    return Err(Error::new("Either a path or a hash is required"));
+                }
+                let size = size.ok_or_else(|| Error::new("A size is required with a hash"))?;
+                let hashes = Hashes {
+                    crc: crc.map(|crc| crc.to_lowercase()),
+                    md5: md5.map(|md5| md5.to_lowercase()),
+                    sha1: sha1.map(|sha1| sha1.to_lowercase()),
+                };
+                lookup::lookup_hashes(&mut connection, size as u64, &hashes).await
+            }
+        };
+        Ok(matches.into_iter().map(|(_, _, rom)| rom).collect())
     }
 
     async fn roms(&self, game_id: i64) -> Result<Vec<Rom>> {
@@ -296,6 +796,188 @@ impl QueryRoot {
     }
 }
 
+struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    #[graphql(guard = "AdminGuard")]
+    async fn sort_roms(&self, system_id: i64) -> Result<Job> {
+        let system = find_system(system_id).await?;
+        Ok(spawn_job(
+            format!("Sort \"{}\"", system.name),
+            move |mut connection, progress| async move {
+                let matches = sort_roms::subcommand().get_matches_from(["sort-roms", "-y"]);
+                sort_roms::sort_system_with_settings(&mut connection, &matches, &progress, &system)
+                    .await
+            },
+        ))
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn convert_roms(&self, system_id: i64, format: Option<String>) -> Result<Job> {
+        let system = find_system(system_id).await?;
+        let format = match format.or_else(|| system.preferred_format.clone()) {
+            Some(format) => format,
+            None => return Err(Error::new("No format specified")),
+        };
+        if !convert_roms::ALL_FORMATS.contains(&format.as_str()) {
+            return Err(Error::new(format!("Unsupported format {}", format)));
+        }
+        if system.arcade && !convert_roms::ARCADE_FORMATS.contains(&format.as_str()) {
+            return Err(Error::new(format!(
+                "Only {:?} are supported for arcade systems",
+                convert_roms::ARCADE_FORMATS
+            )));
+        }
+        Ok(spawn_job(
+            format!("Convert \"{}\" to {}", system.name, format),
+            move |mut connection, progress| async move {
+                let games = find_games_with_romfiles_by_system_id(&mut connection, system.id).await;
+                convert_roms::convert_games(
+                    &mut connection,
+                    &progress,
+                    &system,
+                    games,
+                    &format,
+                    false,
+                )
+                .await
+            },
+        ))
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn check_roms(&self, system_id: i64, size: Option<bool>) -> Result<Job> {
+        let system = find_system(system_id).await?;
+        Ok(spawn_job(
+            format!("Check \"{}\"", system.name),
+            move |mut connection, progress| async move {
+                let hash_algorithm = HashAlgorithm::from_str(
+                    &get_string(&mut connection, "HASH_ALGORITHM", None).await,
+                )
+                .unwrap();
+                check_roms::check_system(
+                    &mut connection,
+                    &progress,
+                    &system,
+                    size.unwrap_or_default(),
+                    &hash_algorithm,
+                    true,
+                )
+                .await
+            },
+        ))
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn purge_roms(
+        &self,
+        missing: Option<bool>,
+        trash: Option<bool>,
+        orphan: Option<bool>,
+    ) -> Result<Job> {
+        let mut args = vec!["purge-roms", "-y"];
+        if missing.unwrap_or_default() {
+            args.push("-m");
+        }
+        if trash.unwrap_or_default() {
+            args.push("-t");
+        }
+        if orphan.unwrap_or_default() {
+            args.push("-o");
+        }
+        Ok(spawn_job(
+            String::from("Purge"),
+            move |mut connection, progress| async move {
+                let matches = purge_roms::subcommand().get_matches_from(args);
+                purge_roms::main(&mut connection, &matches, &progress).await
+            },
+        ))
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn set_setting(
+        &self,
+        key: String,
+        value: String,
+        system_id: Option<i64>,
+    ) -> Result<bool> {
+        let _guard = lock_settings()?;
+        set_setting(
+            &mut POOL.get().unwrap().acquire().await.unwrap(),
+            &Progress::hidden(),
+            &key,
+            &value,
+            system_id,
+        )
+        .await
+        .map_err(|error| Error::new(error.to_string()))?;
+        Ok(true)
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn add_to_list(
+        &self,
+        key: String,
+        value: String,
+        system_id: Option<i64>,
+    ) -> Result<Vec<String>> {
+        let _guard = lock_settings()?;
+        let mut connection = POOL.get().unwrap().acquire().await.unwrap();
+        add_to_list(&mut connection, &key, &value, system_id).await;
+        Ok(get_list(&mut connection, &key, system_id).await)
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn remove_from_list(
+        &self,
+        key: String,
+        value: String,
+        system_id: Option<i64>,
+    ) -> Result<Vec<String>> {
+        let _guard = lock_settings()?;
+        let mut connection = POOL.get().unwrap().acquire().await.unwrap();
+        remove_from_list(&mut connection, &key, &value, system_id).await;
+        Ok(get_list(&mut connection, &key, system_id).await)
+    }
+}
+
+struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    // poll the job progress until it's done, only sending new lines each time
+    async fn job_progress(&self, id: i64) -> Result<impl Stream<Item = JobProgress>> {
+        if !JOBS.lock().unwrap().contains_key(&id) {
+            return Err(Error::new(format!("No job with id {}", id)));
+        }
+        Ok(stream::unfold(
+            (0, false, false),
+            move |(offset, started, done)| async move {
+                if done {
+                    return None;
+                }
+                if started {
+                    task::sleep(PROGRESS_INTERVAL).await;
+                }
+                // the job may have been evicted since the last poll
+                let job = JOBS.lock().unwrap().get(&id).cloned()?;
+                let (progress, offset) = job.progress.get_state(offset);
+                let done = job.is_finished();
+                Some((
+                    JobProgress {
+                        id: job.id,
+                        status: job.status,
+                        error: job.error,
+                        progress,
+                    },
+                    (offset, true, done),
+                ))
+            },
+        ))
+    }
+}
+
 async fn serve_asset(req: tide::Request<()>) -> tide::Result {
     let file_path = req.param("path").unwrap_or("index.html");
     match Assets::get(file_path) {
@@ -320,7 +1002,22 @@ async fn serve_asset(req: tide::Request<()>) -> tide::Result {
 pub async fn main(pool: SqlitePool, matches: &ArgMatches) -> SimpleResult<()> {
     POOL.set(pool).expect("Failed to set database pool");
 
-    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+    let address = matches.get_one::<String>("ADDRESS").unwrap();
+    let port = matches.get_one::<String>("PORT").unwrap();
+    let auth =
+        Auth::from_settings(&mut POOL.get().unwrap().acquire().await.unwrap(), address).await?;
+    if !auth.is_enabled() && !auth.loopback {
+        println!("No credentials are set, the server is read-only beyond localhost");
+    }
+    let acceptor = match (
+        matches.get_one::<PathBuf>("TLS_CERT"),
+        matches.get_one::<PathBuf>("TLS_KEY"),
+    ) {
+        (Some(cert_path), Some(key_path)) => Some(get_tls_acceptor(cert_path, key_path)?),
+        _ => None,
+    };
+
+    let schema = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
         .data(DataLoader::new(SystemLoader, async_std::task::spawn))
         .data(DataLoader::new(GameLoader, async_std::task::spawn))
         .data(DataLoader::new(RomfileLoader, async_std::task::spawn))
@@ -330,22 +1027,43 @@ pub async fn main(pool: SqlitePool, matches: &ArgMatches) -> SimpleResult<()> {
     ctrlc
         .race(async {
             let mut app = tide::new();
+            app.with(auth);
 
             app.at("/").get(serve_asset);
             app.at("/*path").get(serve_asset);
 
-            app.at("/graphql").post(async_graphql_tide::graphql(schema));
+            let graphql_schema = schema.clone();
+            app.at("/graphql")
+                .post(move |req: tide::Request<()>| {
+                    let schema = graphql_schema.clone();
+                    async move {
+                        let role = req.ext::<Role>().copied().unwrap_or(Role::ReadOnly);
+                        let request = async_graphql_tide::receive_request(req).await?.data(role);
+                        async_graphql_tide::respond(schema.execute(request).await)
+                    }
+                })
+                .get(async_graphql_tide::GraphQLSubscription::new(schema).build());
 
-            let address = matches.get_one::<String>("ADDRESS").unwrap();
-            let port = matches.get_one::<String>("PORT").unwrap();
-            app.listen(format!("{}:{}", address, port))
-                .await
-                .expect("Failed to run server");
+            let address = format!("{}:{}", address, port);
+            match acceptor {
+                Some(acceptor) => listen_tls(app, address, acceptor).await,
+                None => app.listen(address).await.expect("Failed to run server"),
+            }
         })
         .await;
     close_connection(POOL.get().unwrap()).await;
     Ok(())
 }
 
+#[cfg(test)]
+mod test_auth;
+#[cfg(test)]
+mod test_games_filter;
+#[cfg(test)]
+mod test_jobs;
+#[cfg(test)]
+mod test_lookup_path;
 #[cfg(test)]
 mod test_server;
+#[cfg(test)]
+mod test_settings_lock;
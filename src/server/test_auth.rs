@@ -0,0 +1,79 @@
+use super::*;
+use tide::http::{Method, Request, Response, Url};
+
+async fn get(app: &tide::Server<()>, authorization: Option<&str>) -> Response {
+    let mut req = Request::new(Method::Get, Url::parse("http://127.0.0.1/").unwrap());
+    if let Some(authorization) = authorization {
+        req.insert_header("Authorization", authorization);
+    }
+    app.respond(req).await.unwrap()
+}
+
+fn get_app(auth: Auth) -> tide::Server<()> {
+    let mut app = tide::new();
+    app.with(auth);
+    app.at("/").get(|req: tide::Request<()>| async move {
+        Ok(match req.ext::<Role>() {
+            Some(Role::Admin) => "admin",
+            Some(Role::ReadOnly) => "read-only",
+            None => "none",
+        })
+    });
+    app
+}
+
+#[async_std::test]
+async fn test() {
+    // given
+    let auth = Auth {
+        admin: Credentials {
+            username: Some(String::from("admin")),
+            password: Some(hash_secret("password").unwrap()),
+            token: None,
+        },
+        read_only: Credentials {
+            username: None,
+            password: None,
+            token: Some(hash_secret("token").unwrap()),
+        },
+        loopback: false,
+    };
+    let app = get_app(auth);
+    let loopback_app = get_app(Auth {
+        loopback: true,
+        ..Default::default()
+    });
+    let remote_app = get_app(Auth::default());
+
+    // when
+    let mut anonymous = get(&app, None).await;
+    let mut wrong_password = get(&app, Some("Basic YWRtaW46d3Jvbmc=")).await;
+    let mut admin = get(&app, Some("Basic YWRtaW46cGFzc3dvcmQ=")).await;
+    let mut read_only = get(&app, Some("Bearer token")).await;
+    let mut loopback_anonymous = get(&loopback_app, None).await;
+    let mut remote_anonymous = get(&remote_app, None).await;
+
+    // then
+    assert_eq!(anonymous.status(), StatusCode::Unauthorized);
+    assert!(anonymous.header(WWW_AUTHENTICATE).is_some());
+    assert_eq!(anonymous.body_string().await.unwrap(), "");
+    assert_eq!(wrong_password.status(), StatusCode::Unauthorized);
+    assert_eq!(wrong_password.body_string().await.unwrap(), "");
+    assert_eq!(admin.body_string().await.unwrap(), "admin");
+    assert_eq!(read_only.body_string().await.unwrap(), "read-only");
+    assert_eq!(loopback_anonymous.body_string().await.unwrap(), "admin");
+    assert_eq!(remote_anonymous.body_string().await.unwrap(), "read-only");
+
+    // when
+    let schema = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot).finish();
+    let response = schema
+        .execute(
+            async_graphql::Request::new("mutation { sortRoms(systemId: 1) { id } }")
+                .data(Role::ReadOnly),
+        )
+        .await;
+
+    // then
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message, "Read-only access");
+}
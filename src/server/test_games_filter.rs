@@ -0,0 +1,79 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::super::import_dats;
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+
+    // when
+    let usa_games = find_games_by_system_id_and_filter(
+        &mut connection,
+        system.id,
+        &GameFilter {
+            name: Some(String::from("usa")),
+            ..Default::default()
+        },
+        0,
+        get_page_size(None),
+    )
+    .await;
+    let percent_games = find_games_by_system_id_and_filter(
+        &mut connection,
+        system.id,
+        &GameFilter {
+            name: Some(String::from("%")),
+            ..Default::default()
+        },
+        0,
+        get_page_size(None),
+    )
+    .await;
+    let underscore_count = count_games_by_system_id_and_filter(
+        &mut connection,
+        system.id,
+        &GameFilter {
+            name: Some(String::from("_")),
+            ..Default::default()
+        },
+    )
+    .await;
+    let searched_games =
+        find_games_by_name_or_hash(&mut connection, Some("%"), None, 0, get_page_size(None)).await;
+    let first_game =
+        find_games_by_system_id_and_filter(&mut connection, system.id, &Default::default(), 0, 1)
+            .await;
+
+    // then
+    assert!(!usa_games.is_empty());
+    assert!(percent_games.is_empty());
+    assert_eq!(underscore_count, 0);
+    assert!(searched_games.is_empty());
+    assert_eq!(first_game.len(), 1);
+    assert_eq!(get_page_size(None), DEFAULT_PAGE_SIZE);
+    assert_eq!(get_page_size(Some(-1)), DEFAULT_PAGE_SIZE);
+    assert_eq!(get_page_size(Some(10)), 10);
+    assert_eq!(get_page_size(Some(MAX_PAGE_SIZE + 1)), MAX_PAGE_SIZE);
+}
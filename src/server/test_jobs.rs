@@ -0,0 +1,46 @@
+use super::*;
+
+#[async_std::test]
+async fn test() {
+    // given
+    let mut jobs: HashMap<i64, Job> = HashMap::new();
+    for id in 1..=MAX_FINISHED_JOBS as i64 + 2 {
+        jobs.insert(
+            id,
+            Job {
+                id,
+                name: String::from("job"),
+                status: match id {
+                    1 => JobStatus::Running,
+                    _ => JobStatus::Succeeded,
+                },
+                error: None,
+                progress: Progress::recorded(),
+            },
+        );
+    }
+
+    let progress = jobs.get(&1).unwrap().progress.clone();
+    for i in 0..1010 {
+        progress.println(i.to_string());
+    }
+
+    // when
+    evict_finished_jobs(&mut jobs);
+
+    // then
+    assert_eq!(jobs.len(), MAX_FINISHED_JOBS + 1);
+    assert!(jobs.contains_key(&1));
+    assert!(!jobs.contains_key(&2));
+    assert!(jobs.contains_key(&3));
+
+    // only the most recent lines are kept
+    let (state, offset) = progress.get_state(0);
+    assert_eq!(state.lines.len(), 1000);
+    assert_eq!(state.lines.first().unwrap(), "10");
+    assert_eq!(offset, 1010);
+
+    let (state, offset) = progress.get_state(1005);
+    assert_eq!(state.lines.len(), 5);
+    assert_eq!(offset, 1010);
+}
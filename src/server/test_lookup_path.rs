@@ -0,0 +1,62 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::*;
+use async_std::fs;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    let rom_directory = set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let romfile_path = rom_directory.join("Test Game (USA, Europe).rom");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).rom"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+
+    // when
+    let inside = get_lookup_path(&mut connection, romfile_path.as_os_str().to_str().unwrap()).await;
+    let outside = get_lookup_path(&mut connection, "tests/Test Game (USA, Europe).rom").await;
+    let traversal = get_lookup_path(
+        &mut connection,
+        rom_directory
+            .join("..")
+            .join("Test Game (USA, Europe).rom")
+            .as_os_str()
+            .to_str()
+            .unwrap(),
+    )
+    .await;
+    let directory =
+        get_lookup_path(&mut connection, rom_directory.as_os_str().to_str().unwrap()).await;
+
+    // then
+    assert_eq!(inside.unwrap(), romfile_path.canonicalize().await.unwrap());
+    assert!(outside.is_err());
+    assert!(traversal.is_err());
+    assert!(directory.is_err());
+
+    // lookups are only allowed to admins
+    let schema = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot).finish();
+    let response = schema
+        .execute(
+            async_graphql::Request::new("{ lookup(size: 256, crc: \"310212E8\") { id } }")
+                .data(Role::ReadOnly),
+        )
+        .await;
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message, "Read-only access");
+}
@@ -10,7 +10,7 @@ use super::*;
 use async_std::fs;
 use async_std::path::PathBuf;
 use async_std::task;
-use indicatif::ProgressBar;
+use futures::StreamExt;
 use serde_json::{json, Value};
 use std::time::Duration;
 use tempfile::{NamedTempFile, TempDir};
@@ -22,7 +22,7 @@ async fn test() -> Result<()> {
     let _guard = MUTEX.lock().await;
 
     let test_directory = Path::new("tests");
-    let progress_bar = ProgressBar::hidden();
+    let progress_bar = Progress::hidden();
 
     let db_file = NamedTempFile::new().unwrap();
     let pool = establish_connection(db_file.path().to_str().unwrap()).await;
@@ -147,6 +147,85 @@ async fn test() -> Result<()> {
                 )
             );
 
+            let string = surf::post("http://127.0.0.1:8000/graphql")
+                .body(Body::from(
+                    r#"{"query":"{ games(systemId: 1, filter: { name: \"usa\" }, offset: 1, limit: 2) { id, name }, gamesCount(systemId: 1, filter: { name: \"usa\", complete: true }) }"}"#,
+                ))
+                .header("Content-Type", "application/json")
+                .recv_string()
+                .await?;
+
+            let v: Value = serde_json::from_str(&string)?;
+            assert_eq!(
+                v["data"],
+                json!(
+                    {
+                        "games": [
+                            {
+                                "id": 6,
+                                "name": "Test Game (USA, Europe) (Beta)"
+                            },
+                            {
+                                "id": 3,
+                                "name": "Test Game (USA, Europe) (CUE BIN)"
+                            }
+                        ],
+                        "gamesCount": 1
+                    }
+                )
+            );
+
+            let string = surf::post("http://127.0.0.1:8000/graphql")
+                .body(Body::from(
+                    r#"{"query":"{ searchGames(hash: \"310212E8\") { id, name, system { id } } }"}"#,
+                ))
+                .header("Content-Type", "application/json")
+                .recv_string()
+                .await?;
+
+            let v: Value = serde_json::from_str(&string)?;
+            assert_eq!(
+                v["data"]["searchGames"],
+                json!(
+                    [
+                        {
+                            "id": 4,
+                            "name": "Test Game (Japan)",
+                            "system": {
+                                "id": 1
+                            }
+                        }
+                    ]
+                )
+            );
+
+            let string = surf::post("http://127.0.0.1:8000/graphql")
+                .body(Body::from(
+                    r#"{"query":"{ lookup(size: 256, crc: \"310212E8\") { id, name, romfile { id }, game { id } } }"}"#,
+                ))
+                .header("Content-Type", "application/json")
+                .recv_string()
+                .await?;
+
+            let v: Value = serde_json::from_str(&string)?;
+            assert_eq!(
+                v["data"]["lookup"],
+                json!(
+                    [
+                        {
+                            "id": 6,
+                            "name": "Test Game (Japan).rom",
+                            "romfile": {
+                                "id": 2
+                            },
+                            "game": {
+                                "id": 4
+                            }
+                        }
+                    ]
+                )
+            );
+
             let string = surf::post("http://127.0.0.1:8000/graphql")
                 .body(Body::from(
                     r#"{"query":"{ roms(gameId: 1) { id, name, romfile { id, path, size }, game { id, name, system { id, name } } } }"}"#,
@@ -202,6 +281,78 @@ async fn test() -> Result<()> {
                 )
             );
 
+            let string = surf::post("http://127.0.0.1:8000/graphql")
+                .body(Body::from(
+                    r#"{"query":"mutation { setSetting(key: \"REGIONS_ONE_STRICT\", value: \"true\", systemId: 1) }"}"#,
+                ))
+                .header("Content-Type", "application/json")
+                .recv_string()
+                .await?;
+
+            let v: Value = serde_json::from_str(&string)?;
+            assert_eq!(v["data"]["setSetting"], json!(true));
+
+            let string = surf::post("http://127.0.0.1:8000/graphql")
+                .body(Body::from(
+                    r#"{"query":"mutation { sortRoms(systemId: 1) { id, status } }"}"#,
+                ))
+                .header("Content-Type", "application/json")
+                .recv_string()
+                .await?;
+
+            let v: Value = serde_json::from_str(&string)?;
+            assert_eq!(
+                v["data"]["sortRoms"],
+                json!(
+                    {
+                        "id": 1,
+                        "status": "QUEUED"
+                    }
+                )
+            );
+
+            let mut v = Value::Null;
+            for _ in 0..50 {
+                task::sleep(Duration::from_millis(100)).await;
+                let string = surf::post("http://127.0.0.1:8000/graphql")
+                    .body(Body::from(
+                        r#"{"query":"{ job(id: 1) { status, error } }"}"#,
+                    ))
+                    .header("Content-Type", "application/json")
+                    .recv_string()
+                    .await?;
+                v = serde_json::from_str(&string)?;
+                if v["data"]["job"]["status"] != json!("QUEUED")
+                    && v["data"]["job"]["status"] != json!("RUNNING")
+                {
+                    break;
+                }
+            }
+            assert_eq!(
+                v["data"]["job"],
+                json!(
+                    {
+                        "status": "SUCCEEDED",
+                        "error": null
+                    }
+                )
+            );
+
+            let schema = Schema::build(QueryRoot, MutationRoot, SubscriptionRoot).finish();
+            let responses: Vec<async_graphql::Response> = schema
+                .execute_stream(
+                    "subscription { jobProgress(id: 1) { status, progress { lines } } }",
+                )
+                .collect()
+                .await;
+            assert_eq!(responses.len(), 1);
+            let v = responses[0].data.clone().into_json()?;
+            assert_eq!(v["jobProgress"]["status"], json!("SUCCEEDED"));
+            assert!(!v["jobProgress"]["progress"]["lines"]
+                .as_array()
+                .unwrap()
+                .is_empty());
+
             Ok(())
         });
 
@@ -0,0 +1,20 @@
+use super::super::config::MUTEX;
+use super::*;
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let job_guard = JOB_LOCK.lock().await;
+
+    // when
+    let result = lock_settings();
+
+    // then
+    assert!(result.is_err());
+
+    // settings can be changed again once the job is done
+    drop(job_guard);
+    assert!(lock_settings().is_ok());
+}
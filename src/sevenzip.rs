@@ -1,7 +1,8 @@
+use super::config::*;
 use super::progress::*;
 use super::SimpleResult;
 use async_std::path::{Path, PathBuf};
-use indicatif::ProgressBar;
+use sevenz_rust::Archive;
 use std::fs::{File, OpenOptions};
 use std::iter::zip;
 use std::process::Command;
@@ -25,13 +26,109 @@ pub struct ArchiveInfo {
 }
 
 pub fn parse_archive<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     archive_path: &P,
 ) -> SimpleResult<Vec<ArchiveInfo>> {
     progress_bar.set_message("Parsing archive");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
 
+    let extension = archive_path
+        .as_ref()
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    // try to read the archive headers natively before falling back to the 7z binary
+    let native_infos = if extension == ZIP_EXTENSION {
+        parse_zip(archive_path).ok()
+    } else if extension == SEVENZIP_EXTENSION {
+        parse_sevenzip(archive_path).ok()
+    } else {
+        None
+    };
+    let sevenzip_infos = match native_infos {
+        Some(sevenzip_infos) => sevenzip_infos,
+        None => parse_archive_with_7z(archive_path)?,
+    };
+
+    progress_bar.set_message("");
+    progress_bar.disable_steady_tick();
+
+    Ok(sevenzip_infos)
+}
+
+fn parse_zip<P: AsRef<Path>>(archive_path: &P) -> SimpleResult<Vec<ArchiveInfo>> {
+    let file = try_with!(
+        File::open(archive_path.as_ref()),
+        "Failed to open {:?}",
+        archive_path.as_ref()
+    );
+    let mut archive = try_with!(
+        ZipArchive::new(file),
+        "Failed to read {:?}",
+        archive_path.as_ref()
+    );
+
+    let mut sevenzip_infos: Vec<ArchiveInfo> = Vec::new();
+    for i in 0..archive.len() {
+        let file = try_with!(
+            archive.by_index_raw(i),
+            "Failed to read {:?}",
+            archive_path.as_ref()
+        );
+        if file.is_dir() {
+            continue;
+        }
+        sevenzip_infos.push(ArchiveInfo {
+            path: file.name().to_string(),
+            size: file.size(),
+            crc: format!("{:08x}", file.crc32()),
+        });
+    }
+
+    Ok(sevenzip_infos)
+}
+
+fn parse_sevenzip<P: AsRef<Path>>(archive_path: &P) -> SimpleResult<Vec<ArchiveInfo>> {
+    let mut file = try_with!(
+        File::open(archive_path.as_ref()),
+        "Failed to open {:?}",
+        archive_path.as_ref()
+    );
+    let len = try_with!(
+        file.metadata(),
+        "Failed to get metadata for {:?}",
+        archive_path.as_ref()
+    )
+    .len();
+    let archive = try_with!(
+        Archive::read(&mut file, len, &[]),
+        "Failed to read {:?}",
+        archive_path.as_ref()
+    );
+
+    let sevenzip_infos: Vec<ArchiveInfo> = archive
+        .files
+        .iter()
+        .filter(|entry| !entry.is_directory())
+        .map(|entry| ArchiveInfo {
+            path: entry.name().to_string(),
+            size: entry.size(),
+            crc: if entry.has_crc {
+                format!("{:08x}", entry.crc)
+            } else {
+                String::new()
+            },
+        })
+        .collect();
+
+    Ok(sevenzip_infos)
+}
+
+fn parse_archive_with_7z<P: AsRef<Path>>(archive_path: &P) -> SimpleResult<Vec<ArchiveInfo>> {
     let output = Command::new("7z")
         .arg("l")
         .arg("-slt")
@@ -50,7 +147,7 @@ pub fn parse_archive<P: AsRef<Path>>(
             line.starts_with("Path =") || line.starts_with("Size =") || line.starts_with("CRC =")
         })
         .skip(1) // the first line is the archive itself
-        .map(|line| line.split('=').last().unwrap().trim()) // keep only the rhs
+        .map(|line| line.split_once('=').unwrap().1.trim()) // keep only the rhs
         .collect();
 
     // each chunk will have the path, size and crc respectively
@@ -66,18 +163,25 @@ pub fn parse_archive<P: AsRef<Path>>(
         }
     }
 
-    progress_bar.set_message("");
-    progress_bar.disable_steady_tick();
-
     Ok(sevenzip_infos)
 }
 
 pub fn rename_file_in_archive<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     archive_path: &P,
     file_name: &str,
     new_file_name: &str,
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        progress_bar.println(format!(
+            "Would rename \"{}\" to \"{}\" in {:?}",
+            file_name,
+            new_file_name,
+            archive_path.as_ref().as_os_str()
+        ));
+        return Ok(());
+    }
+
     progress_bar.set_message("Renaming file in archive");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
@@ -105,7 +209,30 @@ pub fn rename_file_in_archive<P: AsRef<Path>>(
 }
 
 pub fn extract_files_from_archive<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
+    archive_path: &P,
+    file_names: &[&str],
+    directory: &Q,
+) -> SimpleResult<Vec<PathBuf>> {
+    if is_dry_run() {
+        for &file_name in file_names {
+            progress_bar.println(format!(
+                "Would extract \"{}\" to {:?}",
+                file_name,
+                directory.as_ref().as_os_str()
+            ));
+        }
+        return Ok(file_names
+            .iter()
+            .map(|file_name| directory.as_ref().join(file_name))
+            .collect());
+    }
+    extract_files_for_hashing(progress_bar, archive_path, file_names, directory)
+}
+
+// hashing only reads the archive, so it also runs in dry-run mode
+pub fn extract_files_for_hashing<P: AsRef<Path>, Q: AsRef<Path>>(
+    progress_bar: &Progress,
     archive_path: &P,
     file_names: &[&str],
     directory: &Q,
@@ -140,13 +267,24 @@ pub fn extract_files_from_archive<P: AsRef<Path>, Q: AsRef<Path>>(
 }
 
 pub fn add_files_to_archive<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     archive_path: &P,
     file_names: &[&str],
     directory: &Q,
     compression_level: usize,
     solid: bool,
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        for &file_name in file_names {
+            progress_bar.println(format!(
+                "Would compress \"{}\" into {:?}",
+                file_name,
+                archive_path.as_ref().as_os_str()
+            ));
+        }
+        return Ok(());
+    }
+
     progress_bar.set_message("Compressing files");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
@@ -179,10 +317,21 @@ pub fn add_files_to_archive<P: AsRef<Path>, Q: AsRef<Path>>(
 }
 
 pub fn remove_files_from_archive<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     archive_path: &P,
     file_names: &[&str],
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        for &file_name in file_names {
+            progress_bar.println(format!(
+                "Would delete \"{}\" from {:?}",
+                file_name,
+                archive_path.as_ref().as_os_str()
+            ));
+        }
+        return Ok(());
+    }
+
     progress_bar.set_message("Deleting files");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
@@ -209,12 +358,24 @@ pub fn remove_files_from_archive<P: AsRef<Path>>(
 }
 
 pub async fn copy_files_between_archives<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     source_path: &P,
     destination_path: &Q,
     source_names: &[&str],
     destination_names: &[&str],
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        for (&source_name, &destination_name) in zip(source_names, destination_names) {
+            progress_bar.println(format!(
+                "Would copy \"{}\" to \"{}\" in {:?}",
+                source_name,
+                destination_name,
+                destination_path.as_ref().as_os_str()
+            ));
+        }
+        return Ok(());
+    }
+
     progress_bar.set_message("Copying files between archives");
     progress_bar.set_style(get_none_progress_style());
     progress_bar.enable_steady_tick(Duration::from_millis(100));
@@ -260,3 +421,8 @@ pub async fn copy_files_between_archives<P: AsRef<Path>, Q: AsRef<Path>>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test_parse_sevenzip;
+#[cfg(test)]
+mod test_parse_zip;
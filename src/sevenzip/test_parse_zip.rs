@@ -0,0 +1,22 @@
+use super::*;
+
+#[test]
+fn test() {
+    // given
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+    let archive_path = test_directory.join("Test Game (USA, Europe).rom.zip");
+
+    // when
+    let sevenzip_infos = parse_zip(&archive_path).unwrap();
+
+    // then
+    assert_eq!(sevenzip_infos.len(), 1);
+    let sevenzip_info = sevenzip_infos.first().unwrap();
+    assert_eq!(sevenzip_info.path, "Test Game (USA, Europe).rom");
+    assert_eq!(sevenzip_info.size, 256);
+    assert_eq!(sevenzip_info.crc, "cc721e14");
+
+    let sevenzip_infos = parse_archive(&progress_bar, &archive_path).unwrap();
+    assert_eq!(sevenzip_infos.len(), 1);
+}
@@ -2,6 +2,7 @@ use super::config::*;
 use super::database::*;
 use super::generate_playlists::DISC_REGEX;
 use super::model::*;
+use super::progress::*;
 use super::prompt::*;
 use super::util::*;
 use super::SimpleResult;
@@ -9,9 +10,9 @@ use async_std::path::{Path, PathBuf};
 use async_std::stream::StreamExt;
 use clap::builder::PossibleValuesParser;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use indicatif::ProgressBar;
 use rayon::prelude::*;
 use shiratsu_naming::naming::nointro::{NoIntroName, NoIntroToken};
+use shiratsu_naming::naming::tosec::{TOSECName, TOSECToken};
 use shiratsu_naming::naming::TokenizedName;
 use shiratsu_naming::region::Region;
 use sqlx::sqlite::SqliteConnection;
@@ -22,6 +23,48 @@ use std::str::FromStr;
 use std::time::Duration;
 use strum::VariantNames;
 
+type Version<'a> = Vec<(
+    &'a str,
+    &'a str,
+    Option<&'a str>,
+    Option<&'a str>,
+    Option<Vec<&'a str>>,
+    Option<&'a str>,
+)>;
+
+// TOSEC dump info codes and their meaning
+const TOSEC_DUMP_FLAGS: [(&str, &str); 13] = [
+    ("!", "Verified Good Dump"),
+    ("a", "Alternate"),
+    ("b", "Bad Dump"),
+    ("cr", "Cracked"),
+    ("f", "Fixed"),
+    ("h", "Hack"),
+    ("m", "Modified"),
+    ("o", "Over Dump"),
+    ("p", "Pirate"),
+    ("t", "Trained"),
+    ("tr", "Translated"),
+    ("u", "Under Dump"),
+    ("v", "Virus"),
+];
+
+// TOSEC development status and their No-Intro release equivalent
+const TOSEC_RELEASES: [(&str, &str); 5] = [
+    ("alpha", "Alpha"),
+    ("beta", "Beta"),
+    ("preview", "Preview"),
+    ("pre-release", "Pre-release"),
+    ("proto", "Proto"),
+];
+
+#[derive(Default)]
+struct NameTokens<'a> {
+    releases: Vec<&'a str>,
+    flags: Vec<&'a str>,
+    version: Option<Version<'a>>,
+}
+
 pub fn subcommand() -> Command {
     Command::new("sort-roms")
         .about("Sort ROM files according to region and version preferences")
@@ -86,73 +129,85 @@ pub fn subcommand() -> Command {
 pub async fn main(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
 ) -> SimpleResult<()> {
     let systems = prompt_for_systems(connection, None, false, matches.get_flag("ALL")).await?;
 
-    let all_regions = get_regions(connection, matches, "REGIONS_ALL").await;
-    let one_regions = get_regions(connection, matches, "REGIONS_ONE").await;
-    let ignored_releases = get_list(connection, "DISCARD_RELEASES").await;
-    let ignored_flags = get_list(connection, "DISCARD_FLAGS").await;
-    let prefer_parents = get_bool(connection, "PREFER_PARENTS").await;
+    for system in systems {
+        sort_system_with_settings(connection, matches, progress_bar, &system).await?;
+        progress_bar.println("");
+    }
+
+    Ok(())
+}
+
+// read the settings in the scope of the system before sorting it
+pub async fn sort_system_with_settings(
+    connection: &mut SqliteConnection,
+    matches: &ArgMatches,
+    progress_bar: &Progress,
+    system: &System,
+) -> SimpleResult<()> {
+    let all_regions = get_regions(connection, matches, "REGIONS_ALL", Some(system.id)).await;
+    let one_regions = get_regions(connection, matches, "REGIONS_ONE", Some(system.id)).await;
+    let ignored_releases = get_list(connection, "DISCARD_RELEASES", Some(system.id)).await;
+    let ignored_flags = get_list(connection, "DISCARD_FLAGS", Some(system.id)).await;
+    let prefer_parents = get_bool(connection, "PREFER_PARENTS", Some(system.id)).await;
     let prefer_regions =
-        PreferRegion::from_str(&get_string(connection, "PREFER_REGIONS").await).unwrap();
+        PreferRegion::from_str(&get_string(connection, "PREFER_REGIONS", Some(system.id)).await)
+            .unwrap();
     let prefer_versions =
-        PreferVersion::from_str(&get_string(connection, "PREFER_VERSIONS").await).unwrap();
-    let prefer_flags = get_list(connection, "PREFER_FLAGS").await;
+        PreferVersion::from_str(&get_string(connection, "PREFER_VERSIONS", Some(system.id)).await)
+            .unwrap();
+    let prefer_flags = get_list(connection, "PREFER_FLAGS", Some(system.id)).await;
     let all_regions_subfolders = SubfolderScheme::from_str(
         matches
             .get_one::<String>("REGIONS_ALL_SUBFOLDERS")
-            .unwrap_or(&get_string(connection, "REGIONS_ALL_SUBFOLDERS").await),
+            .unwrap_or(&get_string(connection, "REGIONS_ALL_SUBFOLDERS", Some(system.id)).await),
     )
     .unwrap();
     let one_regions_subfolders = SubfolderScheme::from_str(
         matches
             .get_one::<String>("REGIONS_ONE_SUBFOLDERS")
-            .unwrap_or(&get_string(connection, "REGIONS_ONE_SUBFOLDERS").await),
+            .unwrap_or(&get_string(connection, "REGIONS_ONE_SUBFOLDERS", Some(system.id)).await),
     )
     .unwrap();
-    let one_regions_strict = get_bool(connection, "REGIONS_ONE_STRICT").await;
-
-    for system in systems {
-        sort_system(
-            connection,
-            matches,
-            progress_bar,
-            &system,
-            &all_regions,
-            &one_regions,
-            &ignored_releases
-                .iter()
-                .map(String::as_str)
-                .collect::<Vec<&str>>(),
-            &ignored_flags
-                .iter()
-                .map(String::as_str)
-                .collect::<Vec<&str>>(),
-            prefer_parents,
-            &prefer_regions,
-            &prefer_versions,
-            &prefer_flags
-                .iter()
-                .map(String::as_str)
-                .collect::<Vec<&str>>(),
-            &all_regions_subfolders,
-            &one_regions_subfolders,
-            one_regions_strict,
-        )
-        .await?;
+    let one_regions_strict = get_bool(connection, "REGIONS_ONE_STRICT", Some(system.id)).await;
 
-        progress_bar.println("");
-    }
-
-    Ok(())
+    sort_system(
+        connection,
+        matches,
+        progress_bar,
+        system,
+        &all_regions,
+        &one_regions,
+        &ignored_releases
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>(),
+        &ignored_flags
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>(),
+        prefer_parents,
+        &prefer_regions,
+        &prefer_versions,
+        &prefer_flags
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>(),
+        &all_regions_subfolders,
+        &one_regions_subfolders,
+        one_regions_strict,
+    )
+    .await
 }
 
 pub async fn get_regions(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
     key: &str,
+    system_id: Option<i64>,
 ) -> Vec<Region> {
     let all_regions: Vec<String> = if matches.contains_id(key) {
         let mut regions: Vec<String> = matches
@@ -163,7 +218,7 @@ pub async fn get_regions(
         regions.dedup();
         regions
     } else {
-        get_list(connection, key).await
+        get_list(connection, key, system_id).await
     };
     all_regions
         .into_iter()
@@ -174,7 +229,7 @@ pub async fn get_regions(
 async fn sort_system(
     connection: &mut SqliteConnection,
     matches: &ArgMatches,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
     all_regions: &[Region],
     one_regions: &[Region],
@@ -364,7 +419,7 @@ async fn sort_system(
                     "{} ({}) [{}]",
                     rom.name,
                     game.name,
-                    rom.crc.as_ref().unwrap()
+                    rom.crc.as_ref().or(rom.sha1.as_ref()).unwrap()
                 ));
             }
         } else {
@@ -512,7 +567,14 @@ async fn sort_system(
         // prompt user for confirmation
         if matches.get_flag("YES") || confirm(true)? {
             for romfile_move in romfile_moves {
-                rename_file(progress_bar, &romfile_move.0.path, &romfile_move.1, true).await?;
+                rename_cached_file(
+                    &mut transaction,
+                    progress_bar,
+                    &romfile_move.0.path,
+                    &romfile_move.1,
+                    true,
+                )
+                .await?;
                 update_romfile(
                     &mut transaction,
                     romfile_move.0.id,
@@ -520,6 +582,13 @@ async fn sort_system(
                     romfile_move.0.size as u64,
                 )
                 .await;
+                journal_rename(
+                    &mut transaction,
+                    &romfile_move.0.path,
+                    &romfile_move.1,
+                    Some(romfile_move.0.id),
+                )
+                .await;
                 // delete empty directories
                 let mut directory = Path::new(&romfile_move.0.path).parent().unwrap();
                 while directory.read_dir().await.unwrap().next().await.is_none() {
@@ -640,23 +709,12 @@ fn trim_ignored_games(
         })
     } else {
         games.into_iter().partition(|game| {
-            if let Ok(name) = NoIntroName::try_parse(&game.name) {
-                for token in name.iter() {
-                    if let NoIntroToken::Release(release, _) = token {
-                        if ignored_releases.contains(release) {
-                            return true;
-                        }
-                    }
-                    if let NoIntroToken::Flag(_, flags) = token {
-                        for flag in flags.split(", ") {
-                            if ignored_flags.contains(&flag) {
-                                return true;
-                            }
-                        }
-                    }
-                }
-            }
-            false
+            let tokens = parse_name_tokens(&game.name);
+            tokens
+                .releases
+                .iter()
+                .any(|release| ignored_releases.contains(release))
+                || tokens.flags.iter().any(|flag| ignored_flags.contains(flag))
         })
     }
 }
@@ -700,37 +758,20 @@ fn sort_games_by_weight(
     }
 
     if prefer_versions != &PreferVersion::None {
-        let mut version_a = None;
-        let mut version_b = None;
-
-        if let Ok(name) = NoIntroName::try_parse(&game_a.name) {
-            for token in name.iter() {
-                if let NoIntroToken::Version(version) = token {
-                    version_a = Some(version.to_owned());
-                }
-                if let NoIntroToken::Flag(_, flags) = token {
-                    for flag in flags.split(", ") {
-                        if preferred_flags.contains(&flag) {
-                            weight_a += 1;
-                        }
-                    }
-                }
-            }
-        }
-        if let Ok(name) = NoIntroName::try_parse(&game_b.name) {
-            for token in name.iter() {
-                if let NoIntroToken::Version(version) = token {
-                    version_b = Some(version.to_owned());
-                }
-                if let NoIntroToken::Flag(_, flags) = token {
-                    for flag in flags.split(", ") {
-                        if preferred_flags.contains(&flag) {
-                            weight_b += 1;
-                        }
-                    }
-                }
-            }
-        }
+        let tokens_a = parse_name_tokens(&game_a.name);
+        let tokens_b = parse_name_tokens(&game_b.name);
+        weight_a += tokens_a
+            .flags
+            .iter()
+            .filter(|flag| preferred_flags.contains(flag))
+            .count() as u8;
+        weight_b += tokens_b
+            .flags
+            .iter()
+            .filter(|flag| preferred_flags.contains(flag))
+            .count() as u8;
+        let version_a = tokens_a.version;
+        let version_b = tokens_b.version;
         if let (Some(version_a), Some(version_b)) = (version_a.as_ref(), version_b.as_ref()) {
             match version_b.partial_cmp(version_a).unwrap() {
                 Ordering::Less => match *prefer_versions {
@@ -764,6 +805,50 @@ fn sort_games_by_weight(
     weight_b.partial_cmp(&weight_a).unwrap()
 }
 
+// No-Intro names are tried first, TOSEC names are used as a fallback
+fn parse_name_tokens(name: &str) -> NameTokens<'_> {
+    let mut tokens = NameTokens::default();
+    if let Ok(name) = NoIntroName::try_parse(name) {
+        for token in name.iter() {
+            match token {
+                NoIntroToken::Release(release, _) => tokens.releases.push(release),
+                NoIntroToken::Flag(_, flags) => tokens.flags.extend(flags.split(", ")),
+                NoIntroToken::Version(version) => tokens.version = Some(version.to_owned()),
+                _ => (),
+            }
+        }
+    } else if let Ok(name) = TOSECName::try_parse(name) {
+        for token in name.iter() {
+            match token {
+                TOSECToken::Development(status) => tokens.releases.push(
+                    TOSEC_RELEASES
+                        .iter()
+                        .find(|(code, _)| code.eq_ignore_ascii_case(status))
+                        .map(|(_, release)| *release)
+                        .unwrap_or(status),
+                ),
+                TOSECToken::Demo(_) => tokens.releases.push("Demo"),
+                TOSECToken::DumpInfo(code, _, _) => {
+                    if let Some((_, flag)) = TOSEC_DUMP_FLAGS.iter().find(|(c, _)| c == code) {
+                        // hacks are releases in No-Intro
+                        if *code == "h" {
+                            tokens.releases.push(flag);
+                        }
+                        tokens.flags.push(flag);
+                    }
+                }
+                TOSECToken::Copyright(copyright) => tokens.flags.push(copyright),
+                TOSECToken::Flag(_, flag) => tokens.flags.push(flag),
+                TOSECToken::Version(version_type, major, minor) => {
+                    tokens.version = Some(vec![(version_type, major, *minor, None, None, None)])
+                }
+                _ => (),
+            }
+        }
+    }
+    tokens
+}
+
 async fn compute_new_romfile_path<P: AsRef<Path>>(
     system: &System,
     game: &Game,
@@ -781,11 +866,19 @@ async fn compute_new_romfile_path<P: AsRef<Path>>(
         .to_lowercase();
     let mut new_romfile_path: PathBuf;
 
-    if ARCHIVE_EXTENSIONS.contains(&romfile_extension.as_str())
+    // disks are stored as roms that only have a SHA1, and live in a directory named after their game
+    if romfile_extension == CHD_EXTENSION && rom.crc.is_none() && rom.sha1.is_some() {
+        new_romfile_path = directory.as_ref().to_path_buf();
+        if subfolders == &SubfolderScheme::Alpha {
+            new_romfile_path = new_romfile_path.join(compute_alpha_subfolder(&game.name));
+        }
+        new_romfile_path = new_romfile_path.join(&game.name).join(&rom.name);
+    } else if ARCHIVE_EXTENSIONS.contains(&romfile_extension.as_str())
         || romfile_extension == CHD_EXTENSION
         || romfile_extension == CSO_EXTENSION
+        || romfile_extension == ZSO_EXTENSION
         || PS3_EXTENSIONS.contains(&romfile_extension.as_str()) && !game.jbfolder
-        || romfile_extension == RVZ_EXTENSION
+        || DOLPHIN_EXTENSIONS.contains(&romfile_extension.as_str())
     {
         new_romfile_path = directory.as_ref().to_path_buf();
         if subfolders == &SubfolderScheme::Alpha {
@@ -847,12 +940,16 @@ mod test_order_revision_vs_revision;
 #[cfg(test)]
 mod test_order_revision_vs_vanilla;
 #[cfg(test)]
+mod test_order_tosec_version_vs_version;
+#[cfg(test)]
 mod test_order_vanilla_vs_revision;
 #[cfg(test)]
 mod test_path_archive_multiple_files;
 #[cfg(test)]
 mod test_path_archive_single_file;
 #[cfg(all(test, feature = "chd"))]
+mod test_path_chd_disk;
+#[cfg(all(test, feature = "chd"))]
 mod test_path_chd_multiple_tracks;
 #[cfg(all(test, feature = "chd"))]
 mod test_path_chd_single_track;
@@ -891,4 +988,8 @@ mod test_sort_discard_asia_and_beta;
 #[cfg(test)]
 mod test_sort_discard_beta;
 #[cfg(test)]
+mod test_sort_disks;
+#[cfg(test)]
 mod test_trim_ignored;
+#[cfg(test)]
+mod test_trim_ignored_tosec;
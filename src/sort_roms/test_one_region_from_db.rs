@@ -11,11 +11,11 @@ async fn test() {
 
     let key = "REGIONS_ONE";
 
-    add_to_list(&mut connection, key, "US").await;
+    add_to_list(&mut connection, key, "US", None).await;
     let matches = subcommand().get_matches_from(&["sort-roms", "-y"]);
 
     // when
-    let all_regions = get_regions(&mut connection, &matches, key).await;
+    let all_regions = get_regions(&mut connection, &matches, key, None).await;
 
     // then
     assert_eq!(all_regions.len(), 1);
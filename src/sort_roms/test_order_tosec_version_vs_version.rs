@@ -0,0 +1,51 @@
+use super::*;
+
+#[async_std::test]
+async fn test() {
+    // given
+    let game_a = Game {
+        id: 1,
+        name: String::from("Game v1.0 (1990)(Publisher)"),
+        description: String::from(""),
+        comment: None,
+        external_id: None,
+        bios: false,
+        jbfolder: false,
+        regions: String::from(""),
+        sorting: Sorting::AllRegions as i64,
+        complete: true,
+        system_id: 1,
+        parent_id: Some(3),
+        bios_id: None,
+        playlist_id: None,
+    };
+    let game_b = Game {
+        id: 1,
+        name: String::from("Game v1.1 (1990)(Publisher)"),
+        description: String::from(""),
+        comment: None,
+        external_id: None,
+        bios: false,
+        jbfolder: false,
+        regions: String::from(""),
+        sorting: Sorting::AllRegions as i64,
+        complete: true,
+        system_id: 1,
+        parent_id: Some(3),
+        bios_id: None,
+        playlist_id: None,
+    };
+
+    // when
+    let ordering = sort_games_by_weight(
+        &game_a,
+        &game_b,
+        false,
+        &PreferRegion::None,
+        &PreferVersion::New,
+        &[],
+    );
+
+    // then
+    assert_eq!(ordering, Ordering::Greater);
+}
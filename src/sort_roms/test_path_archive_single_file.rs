@@ -13,6 +13,7 @@ async fn test() {
         arcade: false,
         merging: Merging::Split as i64,
         complete: false,
+        preferred_format: None,
     };
     let game = Game {
         id: 1,
@@ -0,0 +1,94 @@
+use super::super::database::*;
+use super::super::import_dats;
+use super::super::util::*;
+use super::*;
+use async_std::fs;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand().get_matches_from([
+        "import-dats",
+        "tests/Test Arcade (Disks).dat",
+        "--arcade",
+    ]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
+        .await
+        .unwrap();
+
+    // disks are imported in a directory named after their game
+    let game_directory = system_directory.join("testgame");
+    fs::create_dir_all(&game_directory).await.unwrap();
+    let game_ids: Vec<i64> = find_games_by_system_id(&mut connection, system.id)
+        .await
+        .iter()
+        .map(|game| game.id)
+        .collect();
+    let roms = find_roms_without_romfile_by_game_ids(&mut connection, &game_ids).await;
+    assert_eq!(roms.len(), 2);
+    for rom in &roms {
+        let romfile_path = game_directory.join(&rom.name);
+        fs::write(&romfile_path, b"disk").await.unwrap();
+        let romfile_id = create_romfile(
+            &mut connection,
+            romfile_path.as_os_str().to_str().unwrap(),
+            4,
+        )
+        .await;
+        update_rom_romfile(&mut connection, rom.id, Some(romfile_id)).await;
+    }
+
+    let matches = subcommand().get_matches_from(["sort-roms", "-y"]);
+
+    // when
+    sort_system(
+        &mut connection,
+        &matches,
+        &progress_bar,
+        &system,
+        &[],
+        &[],
+        &[],
+        &[],
+        true,
+        &PreferRegion::None,
+        &PreferVersion::None,
+        &[],
+        &SubfolderScheme::None,
+        &SubfolderScheme::None,
+        false,
+    )
+    .await
+    .unwrap();
+
+    // then
+    let romfiles = find_romfiles_by_system_id(&mut connection, system.id).await;
+    assert_eq!(romfiles.len(), 2);
+    for (romfile, disk_name) in romfiles.iter().zip(["testdisk1.chd", "testdisk2.chd"]) {
+        assert_eq!(
+            romfile.path,
+            game_directory.join(disk_name).as_os_str().to_str().unwrap()
+        );
+        assert!(Path::new(&romfile.path).is_file().await);
+    }
+}
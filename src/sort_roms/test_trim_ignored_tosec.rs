@@ -0,0 +1,87 @@
+use super::*;
+
+#[async_std::test]
+async fn test() {
+    // given
+    let games = vec![
+        Game {
+            id: 1,
+            name: String::from("Game (1990)(Publisher)(US)"),
+            description: String::from(""),
+            comment: None,
+            external_id: None,
+            bios: false,
+            jbfolder: false,
+            regions: String::from(""),
+            sorting: Sorting::AllRegions as i64,
+            complete: true,
+            system_id: 1,
+            parent_id: None,
+            bios_id: None,
+            playlist_id: None,
+        },
+        Game {
+            id: 2,
+            name: String::from("Game (1990)(Publisher)(US)(beta)"),
+            description: String::from(""),
+            comment: None,
+            external_id: None,
+            bios: false,
+            jbfolder: false,
+            regions: String::from(""),
+            sorting: Sorting::AllRegions as i64,
+            complete: true,
+            system_id: 1,
+            parent_id: None,
+            bios_id: None,
+            playlist_id: None,
+        },
+        Game {
+            id: 3,
+            name: String::from("Game (1990)(Publisher)(US)[h Hacker]"),
+            description: String::from(""),
+            comment: None,
+            external_id: None,
+            bios: false,
+            jbfolder: false,
+            regions: String::from(""),
+            sorting: Sorting::AllRegions as i64,
+            complete: true,
+            system_id: 1,
+            parent_id: None,
+            bios_id: None,
+            playlist_id: None,
+        },
+        Game {
+            id: 4,
+            name: String::from("Game (1990)(Publisher)(US)[cr Cracker]"),
+            description: String::from(""),
+            comment: None,
+            external_id: None,
+            bios: false,
+            jbfolder: false,
+            regions: String::from(""),
+            sorting: Sorting::AllRegions as i64,
+            complete: true,
+            system_id: 1,
+            parent_id: None,
+            bios_id: None,
+            playlist_id: None,
+        },
+    ];
+
+    let ignored_releases = vec!["Beta", "Hack"];
+    let ignored_flags = vec!["Cracked"];
+
+    // when
+    let (ignored_games, regular_games) =
+        trim_ignored_games(games, &ignored_releases, &ignored_flags, false);
+
+    // then
+    assert_eq!(ignored_games.len(), 3);
+    assert_eq!(regular_games.len(), 1);
+    assert_eq!(
+        regular_games.first().unwrap().name,
+        "Game (1990)(Publisher)(US)"
+    );
+}
@@ -0,0 +1,476 @@
+use super::checksum::*;
+use super::config::*;
+use super::database::*;
+use super::model::*;
+use super::progress::*;
+use super::sevenzip;
+use super::util::*;
+use super::SimpleResult;
+use async_std::fs;
+use async_std::path::{Path, PathBuf};
+use async_std::stream::StreamExt;
+use fd_lock::RwLock;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnection;
+use std::io::ErrorKind;
+use std::mem;
+use tempfile::{Builder, TempDir};
+
+const STAGING_PREFIX: &str = ".oxyromon-staging-";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const LOCK_FILE_NAME: &str = "lock";
+const BACKUP_DIRECTORY_NAME: &str = "backup";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    old_paths: Vec<String>,
+    new_paths: Vec<String>,
+    #[serde(default)]
+    backups: Vec<Backup>,
+}
+
+// a file that was about to be overwritten by a staged file of the given size
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    path: String,
+    backup_path: String,
+    size: u64,
+}
+
+// the lock is held as long as the directory exists, so that other processes leave it alone
+pub struct StagingDirectory {
+    _lock: std::fs::File,
+    directory: TempDir,
+}
+
+impl StagingDirectory {
+    pub fn path(&self) -> &std::path::Path {
+        self.directory.path()
+    }
+}
+
+// external programs run in other directories so the staging path has to be absolute
+pub async fn create_staging_directory(
+    connection: &mut SqliteConnection,
+) -> SimpleResult<StagingDirectory> {
+    let tmp_directory = get_canonicalized_path(get_tmp_directory(connection).await).await?;
+    let directory = try_with!(
+        Builder::new()
+            .prefix(STAGING_PREFIX)
+            .tempdir_in(&tmp_directory),
+        "Failed to create staging directory"
+    );
+    let lock = match lock_staging_directory(&directory.path())? {
+        Some(lock) => lock,
+        None => bail!("Failed to lock {:?}", directory.path()),
+    };
+    Ok(StagingDirectory {
+        _lock: lock,
+        directory,
+    })
+}
+
+// returns None when another process holds the lock
+fn lock_staging_directory<P: AsRef<std::path::Path>>(
+    staging_directory: &P,
+) -> SimpleResult<Option<std::fs::File>> {
+    let lock_path = staging_directory.as_ref().join(LOCK_FILE_NAME);
+    let lock = try_with!(
+        std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path),
+        "Failed to open {:?}",
+        lock_path
+    );
+    let mut lock = RwLock::new(lock);
+    match lock.try_write() {
+        // the lock is only released when the file is closed
+        Ok(guard) => mem::forget(guard),
+        Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(None),
+        Err(error) => bail!("Failed to lock {:?}: {}", lock_path, error),
+    }
+    Ok(Some(lock.into_inner()))
+}
+
+pub fn get_final_path<P: AsRef<Path>, Q: AsRef<Path>>(
+    staged_path: &P,
+    romfile_path: &Q,
+) -> PathBuf {
+    romfile_path
+        .as_ref()
+        .parent()
+        .unwrap()
+        .join(staged_path.as_ref().file_name().unwrap())
+}
+
+// move the staged files next to the original ones, overwritten files are kept until the staging directory is deleted
+pub async fn swap_staged_files<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
+    progress_bar: &Progress,
+    staging_directory: &StagingDirectory,
+    staged_paths: &[P],
+    new_paths: &[Q],
+    old_paths: &[R],
+    roms: &[&Rom],
+    header: &Option<Header>,
+    rules: &[Rule],
+) -> SimpleResult<()> {
+    if is_dry_run() {
+        for (staged_path, new_path) in staged_paths.iter().zip(new_paths) {
+            rename_file(progress_bar, staged_path, new_path, false).await?;
+        }
+        return Ok(());
+    }
+
+    let mut sizes: Vec<u64> = Vec::new();
+    for staged_path in staged_paths {
+        check_staged_file(
+            progress_bar,
+            staging_directory,
+            staged_path,
+            roms,
+            header,
+            rules,
+        )?;
+        sizes.push(get_file_size(staged_path).await);
+    }
+
+    // several swaps can share a staging directory until the transaction is committed
+    let manifest_path = Path::new(staging_directory.path()).join(MANIFEST_FILE_NAME);
+    let mut manifest = match manifest_path.is_file().await {
+        true => read_manifest(&manifest_path).await?,
+        false => Manifest {
+            old_paths: Vec::new(),
+            new_paths: Vec::new(),
+            backups: Vec::new(),
+        },
+    };
+    let mut backups: Vec<Backup> = Vec::new();
+    for (new_path, size) in new_paths.iter().zip(&sizes) {
+        if new_path.as_ref().is_file().await {
+            let backup_path = Path::new(staging_directory.path())
+                .join(BACKUP_DIRECTORY_NAME)
+                .join((manifest.backups.len() + backups.len()).to_string())
+                .join(new_path.as_ref().file_name().unwrap());
+            backups.push(Backup {
+                path: new_path.as_ref().as_os_str().to_str().unwrap().to_string(),
+                backup_path: backup_path.as_os_str().to_str().unwrap().to_string(),
+                size: *size,
+            });
+        }
+    }
+    manifest.old_paths.extend(
+        old_paths
+            .iter()
+            .map(|path| path.as_ref().as_os_str().to_str().unwrap().to_string()),
+    );
+    manifest.new_paths.extend(
+        new_paths
+            .iter()
+            .map(|path| path.as_ref().as_os_str().to_str().unwrap().to_string()),
+    );
+    let backups_index = manifest.backups.len();
+    manifest.backups.extend(backups);
+    try_with!(
+        fs::write(
+            &manifest_path,
+            try_with!(
+                serde_json::to_string(&manifest),
+                "Failed to serialize manifest"
+            )
+        )
+        .await,
+        "Failed to write {:?}",
+        manifest_path
+    );
+    let backups = &manifest.backups[backups_index..];
+
+    for backup in backups {
+        rename_file(progress_bar, &backup.path, &backup.backup_path, true).await?;
+    }
+    for (i, (staged_path, new_path)) in staged_paths.iter().zip(new_paths).enumerate() {
+        let result = rename_file(progress_bar, staged_path, new_path, false).await;
+        let size = new_path
+            .as_ref()
+            .metadata()
+            .await
+            .map(|metadata| metadata.len());
+        if result.is_err() || size.ok() != Some(sizes[i]) {
+            for new_path in &new_paths[..=i] {
+                if new_path.as_ref().is_file().await {
+                    remove_file(progress_bar, new_path, false).await?;
+                }
+            }
+            for backup in backups {
+                rename_file(progress_bar, &backup.backup_path, &backup.path, true).await?;
+            }
+            bail!(
+                "Failed to move {:?}, the original files were left untouched",
+                new_path.as_ref().as_os_str()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// archives and files named after a ROM are checked against it, other formats have their own verify settings
+fn check_staged_file<P: AsRef<Path>>(
+    progress_bar: &Progress,
+    staging_directory: &StagingDirectory,
+    staged_path: &P,
+    roms: &[&Rom],
+    header: &Option<Header>,
+    rules: &[Rule],
+) -> SimpleResult<()> {
+    let file_name = staged_path.as_ref().file_name().unwrap().to_str().unwrap();
+    let extension = staged_path
+        .as_ref()
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap();
+    let sizes_and_hashes: Vec<(&Rom, u64, Option<Hashes>)> =
+        if ARCHIVE_EXTENSIONS.contains(&extension) {
+            let archive_infos = sevenzip::parse_archive(progress_bar, staged_path)?;
+            let mut sizes_and_hashes = Vec::new();
+            for &rom in roms {
+                match archive_infos
+                    .iter()
+                    .find(|archive_info| archive_info.path == rom.name)
+                {
+                    // headers aren't part of the ROM hashes, the file has to be extracted to skip them
+                    Some(archive_info)
+                        if matches!(header, Some(header)
+                            if archive_info.size == (rom.size + header.size) as u64) =>
+                    {
+                        let tmp_directory = try_with!(
+                            TempDir::new_in(staging_directory.path()),
+                            "Failed to create temporary directory"
+                        );
+                        let extracted_path = sevenzip::extract_files_for_hashing(
+                            progress_bar,
+                            staged_path,
+                            &[&rom.name],
+                            &tmp_directory.path(),
+                        )?
+                        .remove(0);
+                        let (size, hashes) = compute_size_and_hashes(
+                            progress_bar,
+                            &extracted_path,
+                            header,
+                            rules,
+                            1,
+                            1,
+                        )?;
+                        sizes_and_hashes.push((rom, size, Some(hashes)));
+                    }
+                    // archives only store CRCs
+                    Some(archive_info) => sizes_and_hashes.push((
+                        rom,
+                        archive_info.size,
+                        rom.crc
+                            .as_ref()
+                            .map(|_| Hashes::from_crc(&archive_info.crc)),
+                    )),
+                    None => bail!(
+                        "Failed to verify {:?}, the original files were left untouched",
+                        file_name
+                    ),
+                }
+            }
+            sizes_and_hashes
+        } else {
+            match roms.iter().find(|rom| rom.name == file_name) {
+                Some(&rom) => {
+                    let (size, hashes) =
+                        compute_size_and_hashes(progress_bar, staged_path, header, rules, 1, 1)?;
+                    vec![(rom, size, Some(hashes))]
+                }
+                None => Vec::new(),
+            }
+        };
+    for (rom, size, hashes) in sizes_and_hashes {
+        let matches = size == rom.size as u64
+            && match hashes {
+                Some(hashes) => hashes.matches(rom),
+                // ROMs without a CRC can only be checked by size in archives
+                None => true,
+            };
+        if !matches {
+            bail!(
+                "Failed to verify {:?}, the original files were left untouched",
+                file_name
+            );
+        }
+    }
+    Ok(())
+}
+
+// only called once the database points to the new files
+pub async fn remove_original_files<P: AsRef<Path>, Q: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    old_paths: &[P],
+    new_paths: &[Q],
+) -> SimpleResult<()> {
+    for old_path in old_paths {
+        if new_paths
+            .iter()
+            .all(|new_path| new_path.as_ref() != old_path.as_ref())
+        {
+            remove_journaled_file(connection, progress_bar, old_path, false).await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn recover_staging_directories(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+) -> SimpleResult<()> {
+    let tmp_directory = get_tmp_directory(connection).await;
+    let mut entries = try_with!(
+        fs::read_dir(tmp_directory).await,
+        "Failed to read {:?}",
+        tmp_directory
+    );
+    while let Some(entry) = entries.next().await {
+        let entry = try_with!(entry, "Failed to read {:?}", tmp_directory);
+        let staging_directory = entry.path();
+        if !staging_directory.is_dir().await
+            || !entry
+                .file_name()
+                .to_str()
+                .unwrap_or_default()
+                .starts_with(STAGING_PREFIX)
+        {
+            continue;
+        }
+        // the directory is still used by a running conversion
+        let lock = match lock_staging_directory(&staging_directory)? {
+            Some(lock) => lock,
+            None => continue,
+        };
+        progress_bar.println(format!(
+            "Recovering interrupted conversion in {:?}",
+            staging_directory.as_os_str()
+        ));
+        recover_staging_directory(connection, progress_bar, &staging_directory).await?;
+        drop(lock);
+        remove_directory(progress_bar, &staging_directory, true).await?;
+    }
+    Ok(())
+}
+
+async fn recover_staging_directory(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    staging_directory: &Path,
+) -> SimpleResult<()> {
+    // without a manifest the conversion never reached the swap, nothing outside was touched
+    let manifest_path = staging_directory.join(MANIFEST_FILE_NAME);
+    if !manifest_path.is_file().await {
+        return Ok(());
+    }
+    let manifest = read_manifest(&manifest_path).await?;
+
+    for backup in &manifest.backups {
+        recover_backup(connection, progress_bar, backup).await?;
+    }
+
+    let mut committed = false;
+    for new_path in &manifest.new_paths {
+        if !manifest.old_paths.contains(new_path)
+            && find_romfile_by_path(connection, new_path).await.is_some()
+        {
+            committed = true;
+        }
+    }
+
+    // delete whichever side the database doesn't know about
+    let (stale_paths, kept_paths) = match committed {
+        true => (&manifest.old_paths, &manifest.new_paths),
+        false => (&manifest.new_paths, &manifest.old_paths),
+    };
+    for stale_path in stale_paths {
+        if !kept_paths.contains(stale_path)
+            && Path::new(stale_path).is_file().await
+            && find_romfile_by_path(connection, stale_path).await.is_none()
+        {
+            match committed {
+                true => remove_journaled_file(connection, progress_bar, stale_path, false).await?,
+                false => remove_cached_file(connection, progress_bar, stale_path, false).await?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the size of the romfile tells whether the overwritten file or the staged one was committed
+async fn recover_backup(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    backup: &Backup,
+) -> SimpleResult<()> {
+    let backup_size = match Path::new(&backup.backup_path).metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(()),
+    };
+    let size = Path::new(&backup.path)
+        .metadata()
+        .await
+        .map(|metadata| metadata.len())
+        .ok();
+    let romfile_size = find_romfile_by_path(connection, &backup.path)
+        .await
+        .map(|romfile| romfile.size as u64);
+    let swapped = size == Some(backup.size);
+    let committed = backup_size != backup.size && romfile_size == Some(backup.size);
+    let uncommitted = backup_size != backup.size && romfile_size == Some(backup_size);
+    if !swapped || uncommitted {
+        if size.is_some() {
+            remove_file(progress_bar, &backup.path, false).await?;
+        }
+        rename_file(progress_bar, &backup.backup_path, &backup.path, false).await?;
+    } else if !committed {
+        // keep both files when in doubt
+        let trash_path = get_trash_directory(connection, progress_bar, None)
+            .await?
+            .join(Path::new(&backup.backup_path).file_name().unwrap());
+        rename_file(progress_bar, &backup.backup_path, &trash_path, false).await?;
+        progress_bar.println(format!(
+            "Moved the previous version of {:?} to {:?}",
+            backup.path, trash_path
+        ));
+    }
+    Ok(())
+}
+
+async fn read_manifest(manifest_path: &Path) -> SimpleResult<Manifest> {
+    let manifest: Manifest = try_with!(
+        serde_json::from_str(&try_with!(
+            fs::read_to_string(manifest_path).await,
+            "Failed to read {:?}",
+            manifest_path
+        )),
+        "Failed to parse {:?}",
+        manifest_path
+    );
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod test_recover_committed;
+#[cfg(test)]
+mod test_recover_locked;
+#[cfg(test)]
+mod test_recover_overwritten;
+#[cfg(test)]
+mod test_recover_uncommitted;
+#[cfg(test)]
+mod test_swap_headered_mismatch;
+#[cfg(test)]
+mod test_swap_mismatch;
@@ -0,0 +1,56 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    let rom_directory = set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let old_path = rom_directory.join("Test Game (USA, Europe).iso");
+    let new_path = rom_directory.join("Test Game (USA, Europe).chd");
+    fs::write(&old_path, "old").await.unwrap();
+    fs::write(&new_path, "new").await.unwrap();
+    create_romfile(&mut connection, new_path.as_os_str().to_str().unwrap(), 3).await;
+
+    // the lock is released like after a crash
+    let StagingDirectory {
+        _lock: lock,
+        directory,
+    } = create_staging_directory(&mut connection).await.unwrap();
+    drop(lock);
+    let staging_directory = directory.into_path();
+    let manifest = Manifest {
+        old_paths: vec![old_path.as_os_str().to_str().unwrap().to_string()],
+        new_paths: vec![new_path.as_os_str().to_str().unwrap().to_string()],
+        backups: Vec::new(),
+    };
+    fs::write(
+        staging_directory.join(MANIFEST_FILE_NAME),
+        serde_json::to_string(&manifest).unwrap(),
+    )
+    .await
+    .unwrap();
+
+    // when
+    recover_staging_directories(&mut connection, &progress_bar)
+        .await
+        .unwrap();
+
+    // then
+    assert!(!old_path.is_file().await);
+    assert!(new_path.is_file().await);
+    assert!(!Path::new(&staging_directory).is_dir().await);
+}
@@ -0,0 +1,65 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    let rom_directory = set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let path = rom_directory.join("Test Game (USA, Europe).rom");
+    fs::write(&path, "staged").await.unwrap();
+    create_romfile(&mut connection, path.as_os_str().to_str().unwrap(), 3).await;
+
+    // the lock is released like after a crash
+    let StagingDirectory {
+        _lock: lock,
+        directory,
+    } = create_staging_directory(&mut connection).await.unwrap();
+    drop(lock);
+    let staging_directory = directory.into_path();
+    let backup_path = staging_directory
+        .join(BACKUP_DIRECTORY_NAME)
+        .join("0")
+        .join("Test Game (USA, Europe).rom");
+    fs::create_dir_all(backup_path.parent().unwrap())
+        .await
+        .unwrap();
+    fs::write(&backup_path, "old").await.unwrap();
+    let manifest = Manifest {
+        old_paths: vec![path.as_os_str().to_str().unwrap().to_string()],
+        new_paths: vec![path.as_os_str().to_str().unwrap().to_string()],
+        backups: vec![Backup {
+            path: path.as_os_str().to_str().unwrap().to_string(),
+            backup_path: backup_path.as_os_str().to_str().unwrap().to_string(),
+            size: 6,
+        }],
+    };
+    fs::write(
+        staging_directory.join(MANIFEST_FILE_NAME),
+        serde_json::to_string(&manifest).unwrap(),
+    )
+    .await
+    .unwrap();
+
+    // when
+    recover_staging_directories(&mut connection, &progress_bar)
+        .await
+        .unwrap();
+
+    // then
+    assert_eq!(fs::read_to_string(&path).await.unwrap(), "old");
+    assert!(!Path::new(&staging_directory).is_dir().await);
+}
@@ -0,0 +1,62 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::super::import_dats;
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    let rom_directory = set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20210402) (Headered).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let header = find_header_by_system_id(&mut connection, system.id).await;
+    let rules = find_rules_by_header_id(&mut connection, header.as_ref().unwrap().id).await;
+    let rom = find_roms(&mut connection).await.remove(0);
+
+    let path = rom_directory.join(&rom.name);
+    fs::write(&path, "old").await.unwrap();
+
+    // same size as the headered ROM, but different data
+    let mut data = fs::read(test_directory.join("Test Game (USA, Europe) (Headered).rom"))
+        .await
+        .unwrap();
+    *data.last_mut().unwrap() ^= 0xff;
+    let staging_directory = create_staging_directory(&mut connection).await.unwrap();
+    let staged_path = Path::new(staging_directory.path()).join(&rom.name);
+    fs::write(&staged_path, &data).await.unwrap();
+
+    // when
+    let result = swap_staged_files(
+        &progress_bar,
+        &staging_directory,
+        &[&staged_path],
+        &[&path],
+        &[&path],
+        &[&rom],
+        &header,
+        &rules,
+    )
+    .await;
+
+    // then
+    assert!(result.is_err());
+    assert_eq!(fs::read_to_string(&path).await.unwrap(), "old");
+}
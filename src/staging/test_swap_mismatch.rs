@@ -0,0 +1,65 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::*;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    let rom_directory = set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let path = rom_directory.join("Test Game (USA, Europe).rom");
+    fs::write(&path, "old").await.unwrap();
+
+    let staging_directory = create_staging_directory(&mut connection).await.unwrap();
+    let staged_path = Path::new(staging_directory.path()).join("Test Game (USA, Europe).rom");
+    fs::write(&staged_path, "bad").await.unwrap();
+
+    let rom = Rom {
+        id: 1,
+        name: String::from("Test Game (USA, Europe).rom"),
+        bios: false,
+        size: 3,
+        crc: Some(String::from("00000000")),
+        md5: None,
+        sha1: None,
+        rom_status: None,
+        game_id: 1,
+        romfile_id: Some(1),
+        parent_id: None,
+    };
+
+    // when
+    let result = swap_staged_files(
+        &progress_bar,
+        &staging_directory,
+        &[&staged_path],
+        &[&path],
+        &[&path],
+        &[&rom],
+        &None,
+        &[],
+    )
+    .await;
+
+    // then
+    assert!(result.is_err());
+    assert_eq!(fs::read_to_string(&path).await.unwrap(), "old");
+    assert!(
+        !Path::new(staging_directory.path())
+            .join(MANIFEST_FILE_NAME)
+            .is_file()
+            .await
+    );
+}
@@ -7,8 +7,7 @@ export const gameId = writable(-1);
 export const roms = writable([]);
 
 export const unfilteredSystems = writable([]);
-export const unfilteredGames = writable([]);
-export const filteredGames = writable([]);
+export const gamesCount = writable(0);
 export const unfilteredRoms = writable([]);
 
 export const totalOriginalSize = writable(0);
@@ -0,0 +1,202 @@
+use super::database::*;
+use super::model::*;
+use super::progress::*;
+use super::prompt::*;
+use super::util::*;
+use super::SimpleResult;
+use async_std::path::Path;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use num_traits::FromPrimitive;
+use sqlx::sqlite::SqliteConnection;
+use std::collections::HashMap;
+
+pub fn subcommand() -> Command {
+    Command::new("undo")
+        .about("Undo the file operations of the last run")
+        .arg(
+            Arg::new("YES")
+                .short('y')
+                .long("yes")
+                .help("Automatically say yes to prompts")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub async fn main(
+    connection: &mut SqliteConnection,
+    matches: &ArgMatches,
+    progress_bar: &Progress,
+) -> SimpleResult<()> {
+    let run_id = match find_last_run_id(connection).await {
+        Some(run_id) => run_id,
+        None => {
+            progress_bar.println("Nothing to undo");
+            return Ok(());
+        }
+    };
+    let operations = find_operations_by_run_id(connection, run_id).await;
+
+    check_operations(&operations).await?;
+
+    progress_bar.println("Summary:");
+    for operation in &operations {
+        match OperationType::from_i64(operation.operation).unwrap() {
+            OperationType::Rename => progress_bar.println(format!(
+                "\"{}\" -> \"{}\"",
+                operation.new_path.as_ref().unwrap(),
+                operation.old_path.as_ref().unwrap()
+            )),
+            OperationType::Create => progress_bar.println(format!(
+                "\"{}\" -> Delete",
+                operation.new_path.as_ref().unwrap()
+            )),
+            OperationType::Delete => progress_bar.println(format!(
+                "\"{}\" -> Restore",
+                operation.old_path.as_ref().unwrap()
+            )),
+        }
+    }
+
+    if matches.get_flag("YES") || confirm(true)? {
+        let mut transaction = begin_transaction(connection).await;
+        undo_operations(&mut transaction, progress_bar, &operations).await?;
+        delete_operations_by_run_id(&mut transaction, run_id).await;
+        commit_transaction(transaction).await;
+    }
+
+    Ok(())
+}
+
+// walk the operations in reverse to make sure every file is where the run left it
+async fn check_operations(operations: &[Operation]) -> SimpleResult<()> {
+    let mut existing_paths: HashMap<&str, bool> = HashMap::new();
+    for operation in operations {
+        match OperationType::from_i64(operation.operation).unwrap() {
+            OperationType::Rename => {
+                let old_path = operation.old_path.as_deref().unwrap();
+                let new_path = operation.new_path.as_deref().unwrap();
+                if !path_exists(&existing_paths, new_path).await {
+                    bail!("Can't undo the last run, \"{}\" is missing", new_path);
+                }
+                if path_exists(&existing_paths, old_path).await {
+                    bail!("Can't undo the last run, \"{}\" already exists", old_path);
+                }
+                existing_paths.insert(new_path, false);
+                existing_paths.insert(old_path, true);
+            }
+            OperationType::Create => {
+                let new_path = operation.new_path.as_deref().unwrap();
+                if !path_exists(&existing_paths, new_path).await {
+                    bail!("Can't undo the last run, \"{}\" is missing", new_path);
+                }
+                existing_paths.insert(new_path, false);
+            }
+            OperationType::Delete => {
+                let old_path = operation.old_path.as_deref().unwrap();
+                let backup_path = operation.new_path.as_deref().unwrap();
+                if !path_exists(&existing_paths, backup_path).await {
+                    bail!(
+                        "Can't undo the last run, the backup of \"{}\" is missing",
+                        old_path
+                    );
+                }
+                if path_exists(&existing_paths, old_path).await {
+                    bail!("Can't undo the last run, \"{}\" already exists", old_path);
+                }
+                existing_paths.insert(backup_path, false);
+                existing_paths.insert(old_path, true);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn path_exists(existing_paths: &HashMap<&str, bool>, path: &str) -> bool {
+    match existing_paths.get(path) {
+        Some(exists) => *exists,
+        None => Path::new(path).is_file().await,
+    }
+}
+
+async fn undo_operations(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    operations: &[Operation],
+) -> SimpleResult<()> {
+    for operation in operations {
+        let romfile = match operation.romfile_id {
+            Some(romfile_id) => find_romfiles_by_ids(connection, &[romfile_id])
+                .await
+                .into_iter()
+                .next(),
+            None => None,
+        };
+        match OperationType::from_i64(operation.operation).unwrap() {
+            OperationType::Rename => {
+                let old_path = operation.old_path.as_ref().unwrap();
+                let new_path = operation.new_path.as_ref().unwrap();
+                rename_cached_file(connection, progress_bar, new_path, old_path, false).await?;
+                if let Some(romfile) = romfile {
+                    update_romfile(connection, romfile.id, old_path, romfile.size as u64).await;
+                }
+            }
+            OperationType::Create => {
+                remove_cached_file(
+                    connection,
+                    progress_bar,
+                    operation.new_path.as_ref().unwrap(),
+                    false,
+                )
+                .await?;
+            }
+            OperationType::Delete => {
+                let old_path = operation.old_path.as_ref().unwrap();
+                let backup_path = operation.new_path.as_ref().unwrap();
+                rename_file(progress_bar, backup_path, old_path, false).await?;
+                match romfile {
+                    Some(romfile) => {
+                        update_romfile(
+                            connection,
+                            romfile.id,
+                            old_path,
+                            get_file_size(old_path).await,
+                        )
+                        .await;
+                    }
+                    None => progress_bar.println(format!(
+                        "\"{}\" is no longer tracked, please import it again",
+                        old_path
+                    )),
+                }
+            }
+        }
+    }
+
+    // romfiles still pointing to a removed file were created by the run
+    for operation in operations {
+        if let (Some(OperationType::Create), Some(romfile_id)) = (
+            OperationType::from_i64(operation.operation),
+            operation.romfile_id,
+        ) {
+            if let Some(romfile) = find_romfiles_by_ids(connection, &[romfile_id])
+                .await
+                .into_iter()
+                .next()
+            {
+                if Some(&romfile.path) == operation.new_path.as_ref() {
+                    delete_romfile_by_id(connection, romfile.id).await;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_run_per_job;
+#[cfg(all(test, feature = "cso"))]
+mod test_undo_conversion;
+#[cfg(test)]
+mod test_undo_sort;
@@ -0,0 +1,41 @@
+use super::super::config::{set_tmp_directory, MUTEX};
+use super::*;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    journal_rename(&mut connection, &"first", &"first.old", None).await;
+    journal_rename(&mut connection, &"second", &"second.old", None).await;
+
+    // when
+    create_run(&mut connection).await;
+    journal_rename(&mut connection, &"third", &"third.old", None).await;
+
+    // then
+    let run_id = find_last_run_id(&mut connection).await.unwrap();
+    let operations = find_operations_by_run_id(&mut connection, run_id).await;
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0].old_path.as_deref(), Some("third"));
+
+    let operations = find_operations_by_run_id(&mut connection, run_id - 1).await;
+    assert_eq!(operations.len(), 2);
+    assert_eq!(operations[0].old_path.as_deref(), Some("second"));
+    assert_eq!(operations[1].old_path.as_deref(), Some("first"));
+
+    // run ids are never reused, even once their operations are undone
+    delete_operations_by_run_id(&mut connection, run_id).await;
+    assert_eq!(create_run(&mut connection).await, run_id + 1);
+}
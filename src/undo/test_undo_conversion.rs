@@ -0,0 +1,76 @@
+use super::super::config::{set_rom_directory, set_tmp_directory, MUTEX};
+use super::super::convert_roms;
+use super::super::import_dats;
+use super::super::import_roms;
+use super::*;
+use async_std::fs;
+use async_std::path::PathBuf;
+use tempfile::{NamedTempFile, TempDir};
+
+#[async_std::test]
+async fn test() {
+    // given
+    let _guard = MUTEX.lock().await;
+
+    let test_directory = Path::new("tests");
+    let progress_bar = Progress::hidden();
+
+    let db_file = NamedTempFile::new().unwrap();
+    let pool = establish_connection(db_file.path().to_str().unwrap()).await;
+    let mut connection = pool.acquire().await.unwrap();
+
+    let rom_directory = TempDir::new_in(test_directory).unwrap();
+    set_rom_directory(PathBuf::from(rom_directory.path()));
+    let tmp_directory = TempDir::new_in(test_directory).unwrap();
+    let tmp_directory = set_tmp_directory(PathBuf::from(tmp_directory.path()));
+
+    let matches = import_dats::subcommand()
+        .get_matches_from(["import-dats", "tests/Test System (20200721).dat"]);
+    import_dats::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let romfile_path = tmp_directory.join("Test Game (USA, Europe).iso");
+    fs::copy(
+        test_directory.join("Test Game (USA, Europe).iso"),
+        &romfile_path,
+    )
+    .await
+    .unwrap();
+
+    let matches = import_roms::subcommand()
+        .get_matches_from(["import-roms", romfile_path.as_os_str().to_str().unwrap()]);
+    import_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    let system = find_systems(&mut connection).await.remove(0);
+    let system_directory = get_system_directory(&mut connection, &progress_bar, &system)
+        .await
+        .unwrap();
+    let iso_path = system_directory.join("Test Game (USA, Europe).iso");
+    let cso_path = system_directory.join("Test Game (USA, Europe).cso");
+
+    let matches = convert_roms::subcommand().get_matches_from(["convert-roms", "-a", "-f", "CSO"]);
+    convert_roms::main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+    assert!(!iso_path.is_file().await);
+    assert!(cso_path.is_file().await);
+
+    let matches = subcommand().get_matches_from(["undo", "-y"]);
+
+    // when
+    main(&mut connection, &matches, &progress_bar)
+        .await
+        .unwrap();
+
+    // then
+    let romfiles = find_romfiles_by_system_id(&mut connection, system.id).await;
+    assert_eq!(romfiles.len(), 1);
+    assert_eq!(romfiles[0].path, iso_path.as_os_str().to_str().unwrap());
+    assert!(iso_path.is_file().await);
+    assert!(!cso_path.is_file().await);
+
+    assert!(find_last_run_id(&mut connection).await.is_none());
+}
@@ -5,8 +5,8 @@ use super::progress::*;
 use super::SimpleResult;
 use async_std::fs;
 use async_std::path::{Path, PathBuf};
+use async_std::prelude::*;
 use cfg_if::cfg_if;
-use indicatif::ProgressBar;
 use num_traits::FromPrimitive;
 use regex::Regex;
 use sqlx::sqlite::SqliteConnection;
@@ -16,10 +16,21 @@ use std::time::Duration;
 use tempfile::NamedTempFile;
 use tempfile::TempDir;
 
+const UNDO_DIRECTORY_NAME: &str = ".oxyromon-undo";
+
 lazy_static! {
     static ref SYSTEM_NAME_REGEX: Regex = Regex::new(r" \(.*\)").unwrap();
 }
 
+pub async fn get_file_size<P: AsRef<Path>>(path: &P) -> u64 {
+    match path.as_ref().metadata().await {
+        Ok(metadata) => metadata.len(),
+        // the file was never written
+        Err(_) if is_dry_run() => 0,
+        Err(_) => panic!("Failed to read {:?} metadata", path.as_ref()),
+    }
+}
+
 pub async fn get_canonicalized_path<P: AsRef<Path>>(path: &P) -> SimpleResult<PathBuf> {
     let canonicalized_path = try_with!(
         path.as_ref().canonicalize().await,
@@ -56,7 +67,7 @@ pub fn get_reader_sync<P: AsRef<Path>>(
 }
 
 pub async fn create_file<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     path: &P,
     quiet: bool,
 ) -> SimpleResult<fs::File> {
@@ -81,12 +92,20 @@ pub async fn create_tmp_file(connection: &mut SqliteConnection) -> SimpleResult<
 }
 
 pub async fn copy_file<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     old_path: &P,
     new_path: &Q,
     quiet: bool,
 ) -> SimpleResult<()> {
     if old_path.as_ref() != new_path.as_ref() {
+        if is_dry_run() {
+            progress_bar.println(format!(
+                "Would copy {:?} to {:?}",
+                old_path.as_ref().as_os_str(),
+                new_path.as_ref().as_os_str()
+            ));
+            return Ok(());
+        }
         let new_directory = new_path.as_ref().parent().unwrap();
         if !new_directory.is_dir().await {
             create_directory(progress_bar, &new_directory, quiet).await?;
@@ -105,12 +124,20 @@ pub async fn copy_file<P: AsRef<Path>, Q: AsRef<Path>>(
 }
 
 pub async fn rename_file<P: AsRef<Path>, Q: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     old_path: &P,
     new_path: &Q,
     quiet: bool,
 ) -> SimpleResult<()> {
     if old_path.as_ref() != new_path.as_ref() {
+        if is_dry_run() {
+            progress_bar.println(format!(
+                "Would move {:?} to {:?}",
+                old_path.as_ref().as_os_str(),
+                new_path.as_ref().as_os_str()
+            ));
+            return Ok(());
+        }
         let new_directory = new_path.as_ref().parent().unwrap();
         if !new_directory.is_dir().await {
             create_directory(progress_bar, &new_directory, quiet).await?;
@@ -129,10 +156,16 @@ pub async fn rename_file<P: AsRef<Path>, Q: AsRef<Path>>(
 }
 
 pub async fn remove_file<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     path: &P,
     quiet: bool,
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        if !quiet {
+            progress_bar.println(format!("Would delete {:?}", path.as_ref().as_os_str()));
+        }
+        return Ok(());
+    }
     if !quiet {
         progress_bar.println(format!("Deleting {:?}", path.as_ref().as_os_str()));
     }
@@ -144,11 +177,175 @@ pub async fn remove_file<P: AsRef<Path>>(
     Ok(())
 }
 
+// cached hashes follow the files they were computed from
+pub async fn rename_cached_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    old_path: &P,
+    new_path: &Q,
+    quiet: bool,
+) -> SimpleResult<()> {
+    rename_file(progress_bar, old_path, new_path, quiet).await?;
+    if old_path.as_ref() != new_path.as_ref() {
+        update_cached_hashes_path(
+            connection,
+            old_path.as_ref().as_os_str().to_str().unwrap(),
+            new_path.as_ref().as_os_str().to_str().unwrap(),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+pub async fn remove_cached_file<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    path: &P,
+    quiet: bool,
+) -> SimpleResult<()> {
+    remove_file(progress_bar, path, quiet).await?;
+    delete_cached_hashes_by_path(connection, path.as_ref().as_os_str().to_str().unwrap()).await;
+    Ok(())
+}
+
+// files deleted by a run are moved aside so that it can be undone
+pub async fn remove_journaled_file<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    progress_bar: &Progress,
+    path: &P,
+    quiet: bool,
+) -> SimpleResult<()> {
+    let backup_path =
+        find_last_backup_path_by_path(connection, path.as_ref().as_os_str().to_str().unwrap())
+            .await;
+    match backup_path {
+        // never overwrite the backup of another run
+        Some(backup_path) if !Path::new(&backup_path).is_file().await => {
+            rename_file(progress_bar, path, &backup_path, quiet).await?;
+            delete_cached_hashes_by_path(connection, path.as_ref().as_os_str().to_str().unwrap())
+                .await;
+        }
+        _ => remove_cached_file(connection, progress_bar, path, quiet).await?,
+    }
+    Ok(())
+}
+
+async fn get_run_id(connection: &mut SqliteConnection) -> i64 {
+    match find_current_run_id(connection).await {
+        Some(run_id) => run_id,
+        None => {
+            let run_id = create_run(connection).await;
+            if !is_dry_run() {
+                prune_undo_directory(connection, run_id).await;
+            }
+            run_id
+        }
+    }
+}
+
+async fn get_undo_directory(connection: &mut SqliteConnection) -> PathBuf {
+    get_tmp_directory(connection)
+        .await
+        .join(UNDO_DIRECTORY_NAME)
+}
+
+// the backup mirrors the absolute path of the file, so that files with the same name don't collide
+async fn get_backup_path<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    run_id: i64,
+    path: &P,
+) -> PathBuf {
+    let mut backup_path = get_undo_directory(connection)
+        .await
+        .join(run_id.to_string());
+    for component in path.as_ref().components() {
+        if let std::path::Component::Normal(name) = component {
+            backup_path.push(name);
+        }
+    }
+    backup_path
+}
+
+// only the last run can be undone, the files deleted by the previous ones are dropped
+async fn prune_undo_directory(connection: &mut SqliteConnection, run_id: i64) {
+    let undo_directory = get_undo_directory(connection).await;
+    if let Ok(mut entries) = fs::read_dir(&undo_directory).await {
+        while let Some(Ok(entry)) = entries.next().await {
+            if entry.file_name().to_str() != Some(run_id.to_string().as_str()) {
+                fs::remove_dir_all(entry.path()).await.ok();
+            }
+        }
+    }
+}
+
+pub async fn journal_rename<P: AsRef<Path>, Q: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    old_path: &P,
+    new_path: &Q,
+    romfile_id: Option<i64>,
+) {
+    if old_path.as_ref() != new_path.as_ref() {
+        let run_id = get_run_id(connection).await;
+        create_operation(
+            connection,
+            run_id,
+            OperationType::Rename,
+            old_path.as_ref().as_os_str().to_str(),
+            new_path.as_ref().as_os_str().to_str(),
+            romfile_id,
+        )
+        .await;
+    }
+}
+
+pub async fn journal_create<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    path: &P,
+    romfile_id: Option<i64>,
+) {
+    let run_id = get_run_id(connection).await;
+    create_operation(
+        connection,
+        run_id,
+        OperationType::Create,
+        None,
+        path.as_ref().as_os_str().to_str(),
+        romfile_id,
+    )
+    .await;
+}
+
+// the romfile id is only journaled when its row outlives the deleted file
+// the file itself has to be removed with remove_journaled_file afterwards
+pub async fn journal_delete<P: AsRef<Path>>(
+    connection: &mut SqliteConnection,
+    path: &P,
+    romfile_id: Option<i64>,
+) {
+    let run_id = get_run_id(connection).await;
+    let backup_path = get_backup_path(connection, run_id, path).await;
+    create_operation(
+        connection,
+        run_id,
+        OperationType::Delete,
+        path.as_ref().as_os_str().to_str(),
+        backup_path.as_os_str().to_str(),
+        romfile_id,
+    )
+    .await;
+}
+
 pub async fn create_directory<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     path: &P,
     quiet: bool,
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        if !path.as_ref().is_dir().await {
+            progress_bar.println(format!("Would create {:?}", path.as_ref().as_os_str()));
+        }
+        return Ok(());
+    }
     if !quiet {
         progress_bar.println(format!("Creating {:?}", path.as_ref().as_os_str()));
     }
@@ -171,10 +368,14 @@ pub async fn create_tmp_directory(connection: &mut SqliteConnection) -> SimpleRe
 }
 
 pub async fn remove_directory<P: AsRef<Path>>(
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     path: &P,
     quiet: bool,
 ) -> SimpleResult<()> {
+    if is_dry_run() {
+        progress_bar.println(format!("Would delete {:?}", path.as_ref().as_os_str()));
+        return Ok(());
+    }
     if !quiet {
         progress_bar.println(format!("Deleting {:?}", path.as_ref().as_os_str()));
     }
@@ -188,10 +389,10 @@ pub async fn remove_directory<P: AsRef<Path>>(
 
 pub async fn get_system_directory(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) -> SimpleResult<PathBuf> {
-    let system_name = if get_bool(connection, "GROUP_SUBSYSTEMS").await {
+    let system_name = if get_bool(connection, "GROUP_SUBSYSTEMS", Some(system.id)).await {
         SYSTEM_NAME_REGEX
             .replace(&system.name, "")
             .trim()
@@ -206,7 +407,7 @@ pub async fn get_system_directory(
 
 pub async fn get_one_region_directory(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) -> SimpleResult<PathBuf> {
     let trash_directory = get_system_directory(connection, progress_bar, system)
@@ -218,7 +419,7 @@ pub async fn get_one_region_directory(
 
 pub async fn get_trash_directory(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: Option<&System>,
 ) -> SimpleResult<PathBuf> {
     let trash_directory = match system {
@@ -231,7 +432,7 @@ pub async fn get_trash_directory(
     Ok(trash_directory)
 }
 
-pub fn is_update(progress_bar: &ProgressBar, old_version: &str, new_version: &str) -> bool {
+pub fn is_update(progress_bar: &Progress, old_version: &str, new_version: &str) -> bool {
     match new_version.cmp(old_version) {
         Ordering::Less => {
             progress_bar.println(format!(
@@ -256,7 +457,7 @@ pub fn is_update(progress_bar: &ProgressBar, old_version: &str, new_version: &st
 
 pub async fn compute_system_completion(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) {
     progress_bar.set_style(get_none_progress_style());
@@ -275,7 +476,7 @@ pub async fn compute_system_completion(
 
 pub async fn compute_system_incompletion(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) {
     progress_bar.set_style(get_none_progress_style());
@@ -294,7 +495,7 @@ pub async fn compute_system_incompletion(
 
 pub async fn compute_arcade_system_completion(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) {
     progress_bar.set_style(get_none_progress_style());
@@ -320,7 +521,7 @@ pub async fn compute_arcade_system_completion(
 
 pub async fn compute_arcade_system_incompletion(
     connection: &mut SqliteConnection,
-    progress_bar: &ProgressBar,
+    progress_bar: &Progress,
     system: &System,
 ) {
     progress_bar.set_style(get_none_progress_style());
@@ -0,0 +1,6 @@
+FILE "Test Game (World) (Track 01).bin" BINARY
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+FILE "Test Game (World) (Track 02).bin" BINARY
+  TRACK 02 MODE1/2352
+    INDEX 01 00:00:00